@@ -0,0 +1,100 @@
+//! Memory sources for the storage of a `SafeUninitializedVec`.
+//!
+//! By default a vector gets its memory from the global allocator, like `Vec`. Implementing
+//! `RawAlloc` lets tracked buffers live in arenas, bump allocators, or pinned memory that
+//! a device can access directly. This mirrors `Vec<T, A>`, which is still unstable.
+
+use std::alloc::{self, Layout};
+use std::ptr::{self, NonNull};
+
+/// A source of memory blocks.
+///
+/// The layouts passed to these methods never have a size of zero.
+///
+/// # Safety
+/// A block returned by `allocate`, `grow` or `shrink` must fit the layout it was requested
+/// with, and must stay valid until it is passed to `deallocate`, `grow` or `shrink`, even
+/// if the allocator is moved. Blocks may be freed through a clone of the allocator.
+pub unsafe trait RawAlloc {
+    /// Allocates a block for `layout`, returning `None` if no memory is available.
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>>;
+
+    /// Frees a block.
+    ///
+    /// # Safety
+    /// `ptr` must be a block from this allocator, and `layout` the layout it was last
+    /// allocated, grown or shrunk with.
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout);
+
+    /// Moves a block to one that fits `new`, which is at least as large as `old`, keeping
+    /// its contents. If this returns `None`, the old block is still valid.
+    ///
+    /// The default implementation allocates a new block and copies the contents over.
+    ///
+    /// # Safety
+    /// `ptr` and `old` must meet the requirements of `deallocate`, and `new` must have the
+    /// same alignment as `old`.
+    unsafe fn grow(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Option<NonNull<u8>> {
+        let block = self.allocate(new)?;
+        ptr::copy_nonoverlapping(ptr.as_ptr(), block.as_ptr(), old.size());
+        self.deallocate(ptr, old);
+        Some(block)
+    }
+
+    /// Moves a block to one that fits `new`, which is at most as large as `old`, keeping
+    /// the contents that fit. If this returns `None`, the old block is still valid.
+    ///
+    /// The default implementation allocates a new block and copies the contents over.
+    ///
+    /// # Safety
+    /// `ptr` and `old` must meet the requirements of `deallocate`, and `new` must have the
+    /// same alignment as `old`.
+    unsafe fn shrink(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Option<NonNull<u8>> {
+        let block = self.allocate(new)?;
+        ptr::copy_nonoverlapping(ptr.as_ptr(), block.as_ptr(), new.size());
+        self.deallocate(ptr, old);
+        Some(block)
+    }
+}
+
+unsafe impl<A: RawAlloc + ?Sized> RawAlloc for &A {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        (**self).allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        (**self).deallocate(ptr, layout)
+    }
+
+    unsafe fn grow(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Option<NonNull<u8>> {
+        (**self).grow(ptr, old, new)
+    }
+
+    unsafe fn shrink(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Option<NonNull<u8>> {
+        (**self).shrink(ptr, old, new)
+    }
+}
+
+/// The global allocator, which `Vec` uses too.
+///
+/// Vectors that use it can be converted to and from `Vec` without copying.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Global;
+
+unsafe impl RawAlloc for Global {
+    fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+        NonNull::new(unsafe { alloc::alloc(layout) })
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        alloc::dealloc(ptr.as_ptr(), layout)
+    }
+
+    unsafe fn grow(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Option<NonNull<u8>> {
+        NonNull::new(alloc::realloc(ptr.as_ptr(), old, new.size()))
+    }
+
+    unsafe fn shrink(&self, ptr: NonNull<u8>, old: Layout, new: Layout) -> Option<NonNull<u8>> {
+        NonNull::new(alloc::realloc(ptr.as_ptr(), old, new.size()))
+    }
+}
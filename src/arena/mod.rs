@@ -0,0 +1,176 @@
+//! An arena that stores values at stable, pinned addresses.
+//!
+//! Values in a `PinArena` are never moved once they are inserted, so they can be
+//! accessed through `Pin`. This makes the arena usable for futures and other types
+//! that are not `Unpin`. Values that are not `Unpin` are always dropped in place.
+
+use std::mem::{self, MaybeUninit};
+use std::pin::Pin;
+use std::ptr;
+
+use mask::InitMask;
+
+/// The number of slots in each chunk if no chunk size is given.
+pub const DEFAULT_CHUNK_SIZE: usize = 64;
+
+/// An arena of slots whose values never move.
+///
+/// The arena grows by allocating new chunks, and existing chunks are never reallocated.
+pub struct PinArena<T> {
+    chunks: Vec<Box<[MaybeUninit<T>]>>,
+    initialized: InitMask,
+    // uninitialized slots that can be reused
+    free: Vec<usize>,
+    chunk_size: usize,
+}
+
+impl<T> Drop for PinArena<T> {
+    fn drop(&mut self) {
+        // if a destructor panics, the guard drops the remaining values while unwinding
+        struct Guard<'a, T: 'a>(&'a mut PinArena<T>);
+
+        impl<'a, T> Drop for Guard<'a, T> {
+            fn drop(&mut self) {
+                self.0.drop_values();
+            }
+        }
+
+        let guard = Guard(self);
+        guard.0.drop_values();
+        mem::forget(guard);
+    }
+}
+
+impl<T> Default for PinArena<T> {
+    fn default() -> PinArena<T> {
+        PinArena::new()
+    }
+}
+
+impl<T> PinArena<T> {
+    /// Creates a new empty arena.
+    pub fn new() -> PinArena<T> {
+        PinArena::with_chunk_size(DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Creates a new empty arena that allocates `chunk_size` slots at a time.
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn with_chunk_size(chunk_size: usize) -> PinArena<T> {
+        assert!(chunk_size > 0, "chunk size must not be zero");
+        PinArena {
+            chunks: Vec::new(),
+            initialized: InitMask::default(),
+            free: Vec::new(),
+            chunk_size,
+        }
+    }
+
+    /// The number of slots in the arena, including uninitialized slots.
+    pub fn capacity(&self) -> usize {
+        self.initialized.len()
+    }
+
+    /// Returns true if the slot at `i` contains a value.
+    pub fn is_initialized(&self, i: usize) -> bool {
+        i < self.initialized.len() && self.initialized.is_set(i)
+    }
+
+    /// Moves a value into the arena and returns the slot it was placed in.
+    /// The value will not move again until it is dropped or taken.
+    pub fn insert(&mut self, val: T) -> usize {
+        let i = match self.free.pop() {
+            Some(i) => i,
+            None => {
+                let start = self.initialized.len();
+                let mut chunk = Vec::with_capacity(self.chunk_size);
+                chunk.resize_with(self.chunk_size, MaybeUninit::uninit);
+                self.chunks.push(chunk.into_boxed_slice());
+                self.initialized.resize(start + self.chunk_size, false);
+                // hand out slots in increasing order
+                self.free.extend((start + 1..start + self.chunk_size).rev());
+                start
+            }
+        };
+        self.slot(i).write(val);
+        self.initialized.set(i, true);
+        i
+    }
+
+    /// Gets a reference to the value in slot `i`.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        if self.is_initialized(i) {
+            let chunk = &self.chunks[i / self.chunk_size];
+            unsafe { Some(&*chunk[i % self.chunk_size].as_ptr()) }
+        } else {
+            None
+        }
+    }
+
+    /// Gets a pinned reference to the value in slot `i`.
+    pub fn get_pin(&self, i: usize) -> Option<Pin<&T>> {
+        // values in the arena are never moved
+        self.get(i).map(|val| unsafe { Pin::new_unchecked(val) })
+    }
+
+    /// Gets a pinned mutable reference to the value in slot `i`.
+    pub fn get_pin_mut(&mut self, i: usize) -> Option<Pin<&mut T>> {
+        if self.is_initialized(i) {
+            // values in the arena are never moved, and the caller only gets pinned access
+            unsafe { Some(Pin::new_unchecked(&mut *self.slot(i).as_mut_ptr())) }
+        } else {
+            None
+        }
+    }
+
+    /// Drops the value in slot `i` in place, returning true if there was a value.
+    /// The slot can then be reused by later insertions.
+    pub fn remove(&mut self, i: usize) -> bool {
+        if self.is_initialized(i) {
+            self.initialized.set(i, false);
+            self.free.push(i);
+            unsafe {
+                ptr::drop_in_place(self.slot(i).as_mut_ptr());
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    // drops every value in place, unmarking each slot before its value is dropped
+    fn drop_values(&mut self) {
+        let mut from = 0;
+        while let Some(i) = self.initialized.next_one(from) {
+            self.initialized.set(i, false);
+            from = i + 1;
+            // this value can never be accessed again, so it is dropped without moving it
+            unsafe {
+                ptr::drop_in_place(self.slot(i).as_mut_ptr());
+            }
+        }
+    }
+
+    fn slot(&mut self, i: usize) -> &mut MaybeUninit<T> {
+        &mut self.chunks[i / self.chunk_size][i % self.chunk_size]
+    }
+}
+
+impl<T: Unpin> PinArena<T> {
+    /// Gets a mutable reference to the value in slot `i`.
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        self.get_pin_mut(i).map(Pin::into_inner)
+    }
+
+    /// Moves the value in slot `i` out of the arena. This is only possible for
+    /// values that are `Unpin`, since other values must be dropped in place.
+    pub fn take(&mut self, i: usize) -> Option<T> {
+        if self.is_initialized(i) {
+            self.initialized.set(i, false);
+            self.free.push(i);
+            unsafe { Some(self.slot(i).as_ptr().read()) }
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! A byte buffer that only tracks an initialized prefix.
+//!
+//! Byte buffers are usually filled from the front, so instead of keeping a mask with a
+//! flag for every byte, `TrackedBytes` only keeps the length of the initialized prefix.
+//! Everything after it is spare capacity that may be uninitialized.
+
+use std::cmp;
+use std::io::{self, Read};
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ptr;
+use std::slice;
+
+#[cfg(feature = "bytes")]
+use bytes::Bytes;
+
+/// A growable byte buffer whose initialized bytes always form a prefix.
+pub struct TrackedBytes {
+    // the whole allocation. Its length is always its capacity, so the vec never treats
+    // any of it as spare capacity, and bytes written to it stay there, even when it is
+    // reallocated
+    buf: Vec<MaybeUninit<u8>>,
+    // the initialized bytes are `buf[start..end]`. Bytes split off the front leave a gap
+    // before `start`, which is reclaimed when more room is needed
+    start: usize,
+    end: usize,
+    // the number of bytes at the start of the allocation that have been written to at
+    // some point, so they don't need to be zeroed again before reading into them
+    zeroed: usize,
+}
+
+impl TrackedBytes {
+    /// Creates an empty buffer.
+    pub fn new() -> TrackedBytes {
+        TrackedBytes::with_capacity(0)
+    }
+
+    /// Creates an empty buffer with room for `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> TrackedBytes {
+        let mut buf = Vec::with_capacity(capacity);
+        unsafe {
+            // the bytes do not need to be initialized
+            buf.set_len(buf.capacity());
+        }
+        TrackedBytes {
+            buf,
+            start: 0,
+            end: 0,
+            zeroed: 0,
+        }
+    }
+
+    /// The number of initialized bytes.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns true if no bytes are initialized.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The number of bytes the buffer can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.buf.len() - self.start
+    }
+
+    /// The number of uninitialized bytes after the initialized prefix.
+    pub fn remaining(&self) -> usize {
+        self.buf.len() - self.end
+    }
+
+    /// Reserves room for at least `additional` more bytes.
+    pub fn reserve(&mut self, additional: usize) {
+        if self.remaining() >= additional {
+            return;
+        }
+        self.compact();
+        if self.remaining() < additional {
+            let len = self.buf.len();
+            // the vec is full, so this grows the allocation and copies every byte of it
+            self.buf.reserve(self.end + additional - len);
+            unsafe {
+                self.buf.set_len(self.buf.capacity());
+            }
+        }
+    }
+
+    /// Appends bytes to the initialized prefix, growing the buffer if needed.
+    pub fn put_slice(&mut self, src: &[u8]) {
+        self.reserve(src.len());
+        unsafe {
+            ptr::copy_nonoverlapping(
+                src.as_ptr(),
+                self.buf.as_mut_ptr().add(self.end) as *mut u8,
+                src.len(),
+            );
+        }
+        self.end += src.len();
+        self.zeroed = cmp::max(self.zeroed, self.end);
+    }
+
+    /// Appends a single byte.
+    pub fn put_u8(&mut self, byte: u8) {
+        self.put_slice(&[byte]);
+    }
+
+    /// Reads from `reader` into the spare capacity, returning the number of bytes read.
+    ///
+    /// This does not grow the buffer, so it returns `Ok(0)` if there is no spare capacity.
+    /// The spare capacity is only zeroed the first time it is read into.
+    pub fn fill_from_read<R: Read>(&mut self, reader: &mut R) -> io::Result<usize> {
+        if self.remaining() == 0 {
+            self.compact();
+            if self.remaining() == 0 {
+                return Ok(0);
+            }
+        }
+        let capacity = self.buf.len();
+        unsafe {
+            let buf = self.buf.as_mut_ptr() as *mut u8;
+            if self.zeroed < capacity {
+                ptr::write_bytes(buf.add(self.zeroed), 0, capacity - self.zeroed);
+                self.zeroed = capacity;
+            }
+            // every byte of the spare capacity has been written to, so it can be lent out
+            let spare = slice::from_raw_parts_mut(buf.add(self.end), capacity - self.end);
+            let read = reader.read(spare)?;
+            assert!(read <= spare.len(), "reader returned an invalid length");
+            self.end += read;
+            Ok(read)
+        }
+    }
+
+    /// Splits off the first `at` bytes into a new buffer, leaving the rest in this one.
+    ///
+    /// Only the bytes that are split off are copied. The spare capacity stays with this
+    /// buffer.
+    ///
+    /// # Panics
+    /// Panics if `at` is greater than the number of initialized bytes.
+    pub fn split_to(&mut self, at: usize) -> TrackedBytes {
+        assert!(at <= self.len(), "split index out of bounds");
+        let front = TrackedBytes::from(self.as_slice()[..at].to_vec());
+        self.start += at;
+        if self.start == self.end {
+            self.clear();
+        }
+        front
+    }
+
+    /// Drops the initialized bytes after the first `len`.
+    pub fn truncate(&mut self, len: usize) {
+        self.end = self.start + cmp::min(len, self.len());
+    }
+
+    /// Marks every byte as uninitialized, keeping the capacity.
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.end = 0;
+    }
+
+    /// The initialized bytes.
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.buf.as_ptr().add(self.start) as *const u8, self.len()) }
+    }
+
+    /// The initialized bytes, mutably.
+    pub fn as_mut_slice(&mut self) -> &mut [u8] {
+        let len = self.len();
+        unsafe { slice::from_raw_parts_mut(self.buf.as_mut_ptr().add(self.start) as *mut u8, len) }
+    }
+
+    /// Returns the initialized bytes as a `Vec`.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        self.compact();
+        let mut buf = ManuallyDrop::new(self.buf);
+        // the first `end` bytes are initialized, and MaybeUninit<u8> has the layout of u8
+        unsafe { Vec::from_raw_parts(buf.as_mut_ptr() as *mut u8, self.end, buf.capacity()) }
+    }
+
+    /// Converts the initialized bytes into an immutable `Bytes` without copying.
+    #[cfg(feature = "bytes")]
+    pub fn freeze(self) -> Bytes {
+        Bytes::from(self.into_vec())
+    }
+
+    // moves the initialized bytes to the start of the allocation, reclaiming the room left
+    // by bytes that were split off
+    fn compact(&mut self) {
+        if self.start == 0 {
+            return;
+        }
+        let len = self.len();
+        unsafe {
+            let buf = self.buf.as_mut_ptr();
+            ptr::copy(buf.add(self.start), buf, len);
+        }
+        // the bytes that were moved had already been written, so nothing new is zeroed
+        self.start = 0;
+        self.end = len;
+    }
+}
+
+impl Default for TrackedBytes {
+    fn default() -> TrackedBytes {
+        TrackedBytes::new()
+    }
+}
+
+impl From<Vec<u8>> for TrackedBytes {
+    fn from(buf: Vec<u8>) -> TrackedBytes {
+        let mut buf = ManuallyDrop::new(buf);
+        let (len, capacity) = (buf.len(), buf.capacity());
+        // every byte of the allocation can be treated as a possibly uninitialized byte
+        let buf = unsafe {
+            Vec::from_raw_parts(buf.as_mut_ptr() as *mut MaybeUninit<u8>, capacity, capacity)
+        };
+        TrackedBytes {
+            buf,
+            start: 0,
+            end: len,
+            zeroed: len,
+        }
+    }
+}
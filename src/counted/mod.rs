@@ -0,0 +1,203 @@
+//! Values that count how many times they are created and dropped.
+//!
+//! This is meant for testing containers that manage initialization by hand, such as the
+//! ones in this crate. Wrapping the stored values in `CountedDrop` makes leaks and double
+//! drops show up as mismatched counts instead of as silent memory errors.
+//!
+//! Every `CountedDrop` is counted globally. Since tests usually run in parallel, the global
+//! counts are only useful as a rough check, so values can also be counted by a
+//! `DropCounter` that belongs to a single test.
+
+use std::fmt;
+use std::mem;
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// marks a value that has not been dropped yet, so that a second drop can be detected
+const ALIVE: u32 = 0x00c0_ffee;
+const DEAD: u32 = 0xdead_dead;
+
+struct Counts {
+    created: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+impl Counts {
+    const fn new() -> Counts {
+        Counts {
+            created: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    fn record_create(&self) {
+        self.created.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn record_drop(&self) {
+        self.dropped.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+static GLOBAL: Counts = Counts::new();
+
+/// The number of `CountedDrop` values that have been created by the whole program.
+pub fn global_created() -> usize {
+    GLOBAL.created.load(Ordering::SeqCst)
+}
+
+/// The number of `CountedDrop` values that have been dropped by the whole program.
+pub fn global_dropped() -> usize {
+    GLOBAL.dropped.load(Ordering::SeqCst)
+}
+
+/// Counts the values that were created through it. Clones share the same counts.
+#[derive(Clone)]
+pub struct DropCounter {
+    counts: Arc<Counts>,
+}
+
+impl DropCounter {
+    /// Creates a counter with no values.
+    pub fn new() -> DropCounter {
+        DropCounter {
+            counts: Arc::new(Counts::new()),
+        }
+    }
+
+    /// Wraps a value so that it is counted by this counter.
+    pub fn wrap<T>(&self, val: T) -> CountedDrop<T> {
+        self.counts.record_create();
+        GLOBAL.record_create();
+        CountedDrop {
+            val,
+            counter: Some(self.counts.clone()),
+            state: ALIVE,
+        }
+    }
+
+    /// The number of values that have been created.
+    pub fn created(&self) -> usize {
+        self.counts.created.load(Ordering::SeqCst)
+    }
+
+    /// The number of values that have been dropped.
+    pub fn dropped(&self) -> usize {
+        self.counts.dropped.load(Ordering::SeqCst)
+    }
+
+    /// The number of values that are still alive.
+    pub fn live(&self) -> usize {
+        self.created().saturating_sub(self.dropped())
+    }
+
+    /// Panics unless every value has been dropped exactly once.
+    pub fn assert_all_dropped(&self) {
+        let (created, dropped) = (self.created(), self.dropped());
+        assert_eq!(
+            created, dropped,
+            "{} values were created but {} were dropped",
+            created, dropped
+        );
+    }
+}
+
+impl Default for DropCounter {
+    fn default() -> DropCounter {
+        DropCounter::new()
+    }
+}
+
+impl fmt::Debug for DropCounter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DropCounter")
+            .field("created", &self.created())
+            .field("dropped", &self.dropped())
+            .finish()
+    }
+}
+
+/// A value that is counted when it is created and dropped.
+///
+/// Dropping the same value twice panics, as long as its memory has not been reused.
+pub struct CountedDrop<T> {
+    val: T,
+    counter: Option<Arc<Counts>>,
+    state: u32,
+}
+
+impl<T> Drop for CountedDrop<T> {
+    fn drop(&mut self) {
+        assert_eq!(self.state, ALIVE, "a CountedDrop was dropped twice");
+        self.state = DEAD;
+        self.count_drop();
+    }
+}
+
+impl<T> CountedDrop<T> {
+    /// Wraps a value so that it is only counted globally.
+    pub fn new(val: T) -> CountedDrop<T> {
+        GLOBAL.record_create();
+        CountedDrop {
+            val,
+            counter: None,
+            state: ALIVE,
+        }
+    }
+
+    /// Unwraps the value. This counts as a drop.
+    pub fn into_inner(self) -> T {
+        self.count_drop();
+        let mut this = mem::ManuallyDrop::new(self);
+        unsafe {
+            // the wrapper is never dropped, so the value and counter are only moved out once
+            drop(ptr::read(&this.counter));
+            this.state = DEAD;
+            ptr::read(&this.val)
+        }
+    }
+
+    fn count_drop(&self) {
+        GLOBAL.record_drop();
+        if let Some(ref counter) = self.counter {
+            counter.record_drop();
+        }
+    }
+}
+
+impl<T: Clone> Clone for CountedDrop<T> {
+    /// Clones the value, counting the clone with the same counter.
+    fn clone(&self) -> CountedDrop<T> {
+        GLOBAL.record_create();
+        if let Some(ref counter) = self.counter {
+            counter.record_create();
+        }
+        CountedDrop {
+            val: self.val.clone(),
+            counter: self.counter.clone(),
+            state: ALIVE,
+        }
+    }
+}
+
+impl<T> Deref for CountedDrop<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.val
+    }
+}
+
+impl<T> DerefMut for CountedDrop<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.val
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for CountedDrop<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("CountedDrop").field(&self.val).finish()
+    }
+}
@@ -0,0 +1,175 @@
+//! A copy-on-write tracked vector.
+//!
+//! The values are split into chunks that are shared between clones using `Arc`.
+//! When a clone is mutated, only the chunk containing the mutated value is copied.
+//! This also allows taking cheap `Snapshot`s of the vector while it is being filled.
+
+use std::sync::Arc;
+
+use uninitialized::SafeUninitializedVec;
+
+/// The number of values in each chunk if no chunk size is given.
+pub const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+/// A tracked vector whose clones share their storage until one of them is mutated.
+pub struct CowUninitVec<T> {
+    chunks: Vec<Arc<SafeUninitializedVec<T>>>,
+    chunk_size: usize,
+    len: usize,
+}
+
+impl<T> Clone for CowUninitVec<T> {
+    fn clone(&self) -> CowUninitVec<T> {
+        CowUninitVec {
+            chunks: self.chunks.clone(),
+            chunk_size: self.chunk_size,
+            len: self.len,
+        }
+    }
+}
+
+impl<T> CowUninitVec<T> {
+    /// Creates a new `CowUninitVec` with a set length and the default chunk size.
+    pub fn new(len: usize) -> CowUninitVec<T> {
+        CowUninitVec::with_chunk_size(len, DEFAULT_CHUNK_SIZE)
+    }
+
+    /// Creates a new `CowUninitVec` with a set length, where the values are
+    /// copied `chunk_size` at a time.
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn with_chunk_size(len: usize, chunk_size: usize) -> CowUninitVec<T> {
+        assert!(chunk_size > 0, "chunk size must not be zero");
+        let mut chunks = Vec::with_capacity(len.div_ceil(chunk_size));
+        let mut remaining = len;
+        while remaining > 0 {
+            let size = remaining.min(chunk_size);
+            chunks.push(Arc::new(SafeUninitializedVec::new(size)));
+            remaining -= size;
+        }
+        CowUninitVec {
+            chunks,
+            chunk_size,
+            len,
+        }
+    }
+
+    /// The number of slots in the vector.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the vector has no slots.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The number of values in each chunk.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Gets a reference to an element of the vector. Will return none
+    /// if the value is not initialized.
+    pub fn get_value(&self, i: usize) -> Option<&T> {
+        assert!(i < self.len, "index out of bounds");
+        self.chunks[i / self.chunk_size].get_value(i % self.chunk_size)
+    }
+
+    /// Creates an immutable view of the current state of the vector.
+    ///
+    /// The snapshot shares every chunk with the vector. Chunks are only copied when the
+    /// vector is later mutated, so the snapshot is not affected by writes to the vector.
+    pub fn snapshot(&self) -> Snapshot<T> {
+        Snapshot {
+            vec: Arc::new(self.clone()),
+        }
+    }
+
+    /// Returns true if the chunk containing `i` is shared with another clone.
+    pub fn is_shared(&self, i: usize) -> bool {
+        Arc::strong_count(&self.chunks[i / self.chunk_size]) > 1
+    }
+}
+
+impl<T: Clone> CowUninitVec<T> {
+    /// Sets a value in the vector, copying its chunk first if it is shared.
+    pub fn set_value(&mut self, i: usize, val: T) {
+        let offset = i % self.chunk_size;
+        self.chunk_mut(i).set_value(offset, val);
+    }
+
+    /// Gets a mutable reference to an element of the vector, copying its chunk
+    /// first if it is shared. Will return none if the value is not initialized.
+    pub fn get_value_mut(&mut self, i: usize) -> Option<&mut T> {
+        let offset = i % self.chunk_size;
+        self.chunk_mut(i).get_value_mut(offset)
+    }
+
+    /// Moves a value out of the vector, copying its chunk first if it is shared.
+    pub fn take(&mut self, i: usize) -> Option<T> {
+        let offset = i % self.chunk_size;
+        self.chunk_mut(i).take(offset)
+    }
+
+    fn chunk_mut(&mut self, i: usize) -> &mut SafeUninitializedVec<T> {
+        assert!(i < self.len, "index out of bounds");
+        let start = i - i % self.chunk_size;
+        let len = self.chunk_size.min(self.len - start);
+        let chunk = &mut self.chunks[i / self.chunk_size];
+        if Arc::get_mut(chunk).is_none() {
+            *chunk = Arc::new(copy_chunk(chunk, len));
+        }
+        Arc::get_mut(chunk).unwrap()
+    }
+}
+
+/// An immutable view of a `CowUninitVec` at the time the snapshot was taken.
+///
+/// Cloning a snapshot only increases a reference count.
+pub struct Snapshot<T> {
+    vec: Arc<CowUninitVec<T>>,
+}
+
+impl<T> Clone for Snapshot<T> {
+    fn clone(&self) -> Snapshot<T> {
+        Snapshot {
+            vec: self.vec.clone(),
+        }
+    }
+}
+
+impl<T> Snapshot<T> {
+    /// The number of slots in the snapshot.
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    /// Returns true if the snapshot has no slots.
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    /// Gets a reference to an element of the snapshot. Will return none
+    /// if the value was not initialized when the snapshot was taken.
+    pub fn get_value(&self, i: usize) -> Option<&T> {
+        self.vec.get_value(i)
+    }
+
+    /// Creates a new live vector starting from the state of the snapshot.
+    /// The new vector shares its chunks with the snapshot.
+    pub fn to_live(&self) -> CowUninitVec<T> {
+        (*self.vec).clone()
+    }
+}
+
+// copies the initialized values of a chunk into a new chunk
+fn copy_chunk<T: Clone>(chunk: &SafeUninitializedVec<T>, len: usize) -> SafeUninitializedVec<T> {
+    let mut copy = SafeUninitializedVec::new(len);
+    for i in 0..len {
+        if let Some(val) = chunk.get_value(i) {
+            copy.set_value(i, val.clone());
+        }
+    }
+    copy
+}
@@ -0,0 +1,174 @@
+//! A buffer for building C strings, written from Rust or by C code.
+//!
+//! The bytes written so far are always an initialized prefix of the buffer, and they are
+//! checked for nul bytes as they are written. Finishing the string therefore never has to
+//! scan or copy it again.
+
+use std::error;
+use std::ffi::{CStr, CString};
+use std::fmt;
+use std::slice;
+
+/// A growable buffer that builds a nul terminated string.
+///
+/// The buffer always has room for the terminator after the written bytes.
+pub struct TrackedCStringBuf {
+    // the length of this vec is the number of bytes written, none of which are nul
+    buf: Vec<u8>,
+}
+
+/// The error returned when bytes written to a `TrackedCStringBuf` contain a nul byte.
+/// None of the bytes are added to the string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InteriorNulError {
+    position: usize,
+}
+
+impl InteriorNulError {
+    /// The position of the nul byte within the string.
+    pub fn nul_position(&self) -> usize {
+        self.position
+    }
+}
+
+impl fmt::Display for InteriorNulError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "nul byte found at position {}", self.position)
+    }
+}
+
+impl error::Error for InteriorNulError {}
+
+impl TrackedCStringBuf {
+    /// Creates an empty string.
+    pub fn new() -> TrackedCStringBuf {
+        TrackedCStringBuf::with_capacity(0)
+    }
+
+    /// Creates an empty string with room for `capacity` bytes, not counting the terminator.
+    pub fn with_capacity(capacity: usize) -> TrackedCStringBuf {
+        TrackedCStringBuf {
+            buf: Vec::with_capacity(capacity + 1),
+        }
+    }
+
+    /// The number of bytes written, not counting the terminator.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns true if nothing has been written.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// The number of bytes that can be written without reallocating.
+    pub fn spare_capacity(&self) -> usize {
+        self.buf.capacity() - self.buf.len() - 1
+    }
+
+    /// Reserves room to write at least `additional` more bytes.
+    pub fn reserve(&mut self, additional: usize) {
+        self.buf.reserve(additional + 1);
+    }
+
+    /// Appends bytes to the string. Fails without writing anything if they contain a nul.
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> Result<(), InteriorNulError> {
+        if let Some(i) = bytes.iter().position(|&b| b == 0) {
+            return Err(InteriorNulError {
+                position: self.buf.len() + i,
+            });
+        }
+        self.buf.reserve(bytes.len() + 1);
+        self.buf.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    /// Appends a string. Fails without writing anything if it contains a nul.
+    pub fn push_str(&mut self, s: &str) -> Result<(), InteriorNulError> {
+        self.push_bytes(s.as_bytes())
+    }
+
+    /// A pointer to the spare capacity, where up to `spare_capacity` bytes can be written,
+    /// for example by C code. The bytes must then be added with `commit` or
+    /// `commit_terminated`.
+    pub fn spare_ptr(&mut self) -> *mut u8 {
+        unsafe { self.buf.as_mut_ptr().add(self.buf.len()) }
+    }
+
+    /// Adds `written` bytes that were written to the spare capacity to the string.
+    /// Fails without adding anything if they contain a nul.
+    ///
+    /// # Safety
+    /// The first `written` bytes of the spare capacity must have been initialized, and
+    /// `written` must be at most `spare_capacity`.
+    pub unsafe fn commit(&mut self, written: usize) -> Result<(), InteriorNulError> {
+        assert!(
+            written <= self.spare_capacity(),
+            "commit past the spare capacity"
+        );
+        let bytes = slice::from_raw_parts(self.spare_ptr(), written);
+        if let Some(i) = bytes.iter().position(|&b| b == 0) {
+            return Err(InteriorNulError {
+                position: self.buf.len() + i,
+            });
+        }
+        self.buf.set_len(self.buf.len() + written);
+        Ok(())
+    }
+
+    /// Adds the bytes written to the spare capacity up to the first nul, which is how C
+    /// functions usually write strings. Returns the number of bytes that were added, or
+    /// none if there was no nul within `max` bytes, in which case nothing is added.
+    ///
+    /// # Safety
+    /// Every byte of the spare capacity up to and including the first nul, or the first
+    /// `max` bytes if there is no nul, must have been initialized. `max` must be at most
+    /// `spare_capacity + 1`, since the terminator may be written to the reserved byte.
+    pub unsafe fn commit_terminated(&mut self, max: usize) -> Option<usize> {
+        assert!(max <= self.spare_capacity() + 1, "commit past the capacity");
+        let spare = self.spare_ptr();
+        let written = (0..max).find(|&i| *spare.add(i) == 0)?;
+        self.buf.set_len(self.buf.len() + written);
+        Some(written)
+    }
+
+    /// The bytes that have been written, without the terminator.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    /// Gets the string as a `CStr`, writing the terminator into the reserved byte.
+    pub fn as_c_str(&mut self) -> &CStr {
+        let len = self.buf.len();
+        unsafe {
+            *self.spare_ptr() = 0;
+            // the written bytes contain no nul, and the byte after them was just set to nul
+            CStr::from_bytes_with_nul_unchecked(slice::from_raw_parts(self.buf.as_ptr(), len + 1))
+        }
+    }
+
+    /// Finishes the string. The bytes are not scanned again, and they are only moved if
+    /// the allocator moves them while releasing unused capacity.
+    pub fn into_c_string(mut self) -> CString {
+        // there is always room for the terminator, so this does not reallocate
+        self.buf.push(0);
+        unsafe { CString::from_vec_with_nul_unchecked(self.buf) }
+    }
+}
+
+impl Default for TrackedCStringBuf {
+    fn default() -> TrackedCStringBuf {
+        TrackedCStringBuf::new()
+    }
+}
+
+impl fmt::Debug for TrackedCStringBuf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "TrackedCStringBuf({:?})",
+            String::from_utf8_lossy(&self.buf)
+        )
+    }
+}
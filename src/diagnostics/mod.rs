@@ -0,0 +1,449 @@
+//! Diagnostics for finding reference counting leaks.
+//!
+//! `TrackedRc` and `TrackedArc` work like `Rc` and `Arc`, but every allocation is recorded
+//! along with the place it was created. `report` lists how many allocations are alive for
+//! each place, and finds allocations that are only kept alive by other tracked
+//! allocations, which is what a leaked reference cycle looks like.
+//!
+//! Finding cycles needs to know which tracked pointers a value holds. Values created with
+//! `new_traced` report these through the `Trace` trait. Values created with `new` are
+//! assumed to hold none.
+//!
+//! `TrackedRc` allocations are recorded per thread, so a report only includes the ones
+//! that belong to the current thread. `TrackedArc` allocations are recorded globally.
+
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Deref;
+use std::panic::Location;
+use std::rc::{self, Rc};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{self, Arc, Mutex};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    static RCS: RefCell<BTreeMap<usize, Entry<Rc<dyn Node>>>> = RefCell::new(BTreeMap::new());
+}
+
+static ARCS: Mutex<BTreeMap<usize, Entry<Arc<dyn Node + Send + Sync>>>> =
+    Mutex::new(BTreeMap::new());
+
+struct Entry<N> {
+    site: &'static Location<'static>,
+    type_name: &'static str,
+    node: N,
+}
+
+// a type erased view of an allocation
+trait Node {
+    fn strong_count(&self) -> usize;
+    fn trace(&self, tracer: &mut Tracer);
+}
+
+// the allocation shared by every clone of a tracked pointer
+struct Inner<T> {
+    id: usize,
+    // whether the allocation is recorded globally instead of per thread
+    shared: bool,
+    val: T,
+}
+
+/// A value that can report the tracked pointers it holds.
+pub trait Trace {
+    /// Reports every `TrackedRc` and `TrackedArc` held by this value to the tracer.
+    fn trace(&self, tracer: &mut Tracer);
+}
+
+/// Collects the tracked pointers held by a value.
+pub struct Tracer {
+    edges: Vec<usize>,
+}
+
+impl Tracer {
+    /// Records that the value holds a `TrackedRc`.
+    pub fn visit_rc<T>(&mut self, rc: &TrackedRc<T>) {
+        self.edges.push(rc.inner.id);
+    }
+
+    /// Records that the value holds a `TrackedArc`.
+    pub fn visit_arc<T>(&mut self, arc: &TrackedArc<T>) {
+        self.edges.push(arc.inner.id);
+    }
+}
+
+/// A reference counted pointer whose allocation is recorded for diagnostics.
+pub struct TrackedRc<T> {
+    inner: Rc<Inner<T>>,
+}
+
+struct RcNode<T> {
+    weak: rc::Weak<Inner<T>>,
+    trace: Option<fn(&T, &mut Tracer)>,
+}
+
+impl<T> Node for RcNode<T> {
+    fn strong_count(&self) -> usize {
+        self.weak.strong_count()
+    }
+
+    fn trace(&self, tracer: &mut Tracer) {
+        if let (Some(trace), Some(inner)) = (self.trace, self.weak.upgrade()) {
+            trace(&inner.val, tracer);
+        }
+    }
+}
+
+impl<T> Drop for Inner<T> {
+    fn drop(&mut self) {
+        let id = self.id;
+        if self.shared {
+            ARCS.lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .remove(&id);
+        } else {
+            // the registry may already be gone if this is dropped while the thread exits
+            let _ = RCS.try_with(|rcs| rcs.borrow_mut().remove(&id));
+        }
+    }
+}
+
+impl<T: 'static> TrackedRc<T> {
+    /// Allocates a value that holds no tracked pointers.
+    #[track_caller]
+    pub fn new(val: T) -> TrackedRc<T> {
+        TrackedRc::with_trace(val, None)
+    }
+
+    #[track_caller]
+    fn with_trace(val: T, trace: Option<fn(&T, &mut Tracer)>) -> TrackedRc<T> {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let inner = Rc::new(Inner {
+            id,
+            shared: false,
+            val,
+        });
+        let node: Rc<dyn Node> = Rc::new(RcNode {
+            weak: Rc::downgrade(&inner),
+            trace,
+        });
+        let entry = Entry {
+            site: Location::caller(),
+            type_name: ::std::any::type_name::<T>(),
+            node,
+        };
+        RCS.with(|rcs| rcs.borrow_mut().insert(id, entry));
+        TrackedRc { inner }
+    }
+}
+
+impl<T: Trace + 'static> TrackedRc<T> {
+    /// Allocates a value whose tracked pointers are followed when looking for cycles.
+    #[track_caller]
+    pub fn new_traced(val: T) -> TrackedRc<T> {
+        TrackedRc::with_trace(val, Some(T::trace))
+    }
+}
+
+impl<T> TrackedRc<T> {
+    /// The number of strong pointers to the allocation.
+    pub fn strong_count(this: &TrackedRc<T>) -> usize {
+        Rc::strong_count(&this.inner)
+    }
+
+    /// Returns true if both pointers point to the same allocation.
+    pub fn ptr_eq(this: &TrackedRc<T>, other: &TrackedRc<T>) -> bool {
+        Rc::ptr_eq(&this.inner, &other.inner)
+    }
+}
+
+impl<T> Clone for TrackedRc<T> {
+    fn clone(&self) -> TrackedRc<T> {
+        TrackedRc {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Deref for TrackedRc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner.val
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for TrackedRc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner.val, f)
+    }
+}
+
+/// A thread safe reference counted pointer whose allocation is recorded for diagnostics.
+pub struct TrackedArc<T> {
+    inner: Arc<Inner<T>>,
+}
+
+struct ArcNode<T> {
+    weak: sync::Weak<Inner<T>>,
+    trace: Option<fn(&T, &mut Tracer)>,
+}
+
+impl<T> Node for ArcNode<T> {
+    fn strong_count(&self) -> usize {
+        self.weak.strong_count()
+    }
+
+    fn trace(&self, tracer: &mut Tracer) {
+        if let (Some(trace), Some(inner)) = (self.trace, self.weak.upgrade()) {
+            trace(&inner.val, tracer);
+        }
+    }
+}
+
+impl<T: Send + Sync + 'static> TrackedArc<T> {
+    /// Allocates a value that holds no tracked pointers.
+    #[track_caller]
+    pub fn new(val: T) -> TrackedArc<T> {
+        TrackedArc::with_trace(val, None)
+    }
+
+    #[track_caller]
+    fn with_trace(val: T, trace: Option<fn(&T, &mut Tracer)>) -> TrackedArc<T> {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let inner = Arc::new(Inner {
+            id,
+            shared: true,
+            val,
+        });
+        let node: Arc<dyn Node + Send + Sync> = Arc::new(ArcNode {
+            weak: Arc::downgrade(&inner),
+            trace,
+        });
+        let entry = Entry {
+            site: Location::caller(),
+            type_name: ::std::any::type_name::<T>(),
+            node,
+        };
+        ARCS.lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(id, entry);
+        TrackedArc { inner }
+    }
+}
+
+impl<T: Trace + Send + Sync + 'static> TrackedArc<T> {
+    /// Allocates a value whose tracked pointers are followed when looking for cycles.
+    #[track_caller]
+    pub fn new_traced(val: T) -> TrackedArc<T> {
+        TrackedArc::with_trace(val, Some(T::trace))
+    }
+}
+
+impl<T> TrackedArc<T> {
+    /// The number of strong pointers to the allocation.
+    pub fn strong_count(this: &TrackedArc<T>) -> usize {
+        Arc::strong_count(&this.inner)
+    }
+
+    /// Returns true if both pointers point to the same allocation.
+    pub fn ptr_eq(this: &TrackedArc<T>, other: &TrackedArc<T>) -> bool {
+        Arc::ptr_eq(&this.inner, &other.inner)
+    }
+}
+
+impl<T> Clone for TrackedArc<T> {
+    fn clone(&self) -> TrackedArc<T> {
+        TrackedArc {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Deref for TrackedArc<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner.val
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for TrackedArc<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner.val, f)
+    }
+}
+
+impl<T> Trace for TrackedRc<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.visit_rc(self);
+    }
+}
+
+impl<T> Trace for TrackedArc<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.visit_arc(self);
+    }
+}
+
+impl<T: Trace> Trace for Option<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Some(ref val) = *self {
+            val.trace(tracer);
+        }
+    }
+}
+
+impl<T: Trace> Trace for Vec<T> {
+    fn trace(&self, tracer: &mut Tracer) {
+        for val in self {
+            val.trace(tracer);
+        }
+    }
+}
+
+impl<T: Trace> Trace for RefCell<T> {
+    /// Traces the value if it is not mutably borrowed.
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Ok(val) = self.try_borrow() {
+            val.trace(tracer);
+        }
+    }
+}
+
+impl<T: Trace> Trace for Mutex<T> {
+    /// Traces the value if it is not locked.
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Ok(val) = self.try_lock() {
+            val.trace(tracer);
+        }
+    }
+}
+
+/// The number of live allocations that were created at one place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiteReport {
+    /// Where the allocations were created.
+    pub site: &'static Location<'static>,
+    /// The type of the allocated values.
+    pub type_name: &'static str,
+    /// The number of allocations that are alive.
+    pub live: usize,
+}
+
+/// A single live allocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Allocation {
+    /// Where the allocation was created.
+    pub site: &'static Location<'static>,
+    /// The type of the allocated value.
+    pub type_name: &'static str,
+}
+
+/// A snapshot of the tracked allocations.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// The live allocations grouped by where they were created, with the largest groups
+    /// first.
+    pub sites: Vec<SiteReport>,
+    /// The allocations that are only reachable from other tracked allocations. These
+    /// belong to reference cycles that can never be dropped.
+    pub unreachable: Vec<Allocation>,
+}
+
+enum Handle {
+    Rc(Rc<dyn Node>),
+    Arc(Arc<dyn Node + Send + Sync>),
+}
+
+impl Handle {
+    fn node(&self) -> &dyn Node {
+        match *self {
+            Handle::Rc(ref node) => &**node,
+            Handle::Arc(ref node) => &**node,
+        }
+    }
+}
+
+/// Reports the live `TrackedRc` allocations of the current thread and every live
+/// `TrackedArc` allocation.
+pub fn report() -> Report {
+    // the registries are copied first, since tracing runs user code that may allocate
+    let mut nodes: Vec<(usize, Allocation, Handle)> = Vec::new();
+    RCS.with(|rcs| {
+        for (&id, entry) in rcs.borrow().iter() {
+            let alloc = Allocation {
+                site: entry.site,
+                type_name: entry.type_name,
+            };
+            nodes.push((id, alloc, Handle::Rc(entry.node.clone())));
+        }
+    });
+    for (&id, entry) in ARCS.lock().unwrap_or_else(|err| err.into_inner()).iter() {
+        let alloc = Allocation {
+            site: entry.site,
+            type_name: entry.type_name,
+        };
+        nodes.push((id, alloc, Handle::Arc(entry.node.clone())));
+    }
+
+    let index: HashMap<usize, usize> = nodes
+        .iter()
+        .enumerate()
+        .map(|(i, &(id, _, _))| (id, i))
+        .collect();
+    // the strong counts are read before tracing, which temporarily adds strong pointers
+    let strong: Vec<usize> = nodes.iter().map(|n| n.2.node().strong_count()).collect();
+    let mut internal = vec![0; nodes.len()];
+    let mut edges = Vec::with_capacity(nodes.len());
+    for node in &nodes {
+        let mut tracer = Tracer { edges: Vec::new() };
+        node.2.node().trace(&mut tracer);
+        let targets: Vec<usize> = tracer
+            .edges
+            .iter()
+            .filter_map(|id| index.get(id).cloned())
+            .collect();
+        for &target in &targets {
+            internal[target] += 1;
+        }
+        edges.push(targets);
+    }
+
+    // anything with a pointer from outside the tracked allocations is a root
+    let mut reachable = vec![false; nodes.len()];
+    let mut stack: Vec<usize> = (0..nodes.len())
+        .filter(|&i| strong[i] > internal[i])
+        .collect();
+    while let Some(i) = stack.pop() {
+        if !reachable[i] {
+            reachable[i] = true;
+            stack.extend(edges[i].iter().cloned());
+        }
+    }
+
+    let mut sites: Vec<SiteReport> = Vec::new();
+    for (_, alloc, _) in &nodes {
+        match sites
+            .iter_mut()
+            .find(|s| s.site == alloc.site && s.type_name == alloc.type_name)
+        {
+            Some(site) => site.live += 1,
+            None => sites.push(SiteReport {
+                site: alloc.site,
+                type_name: alloc.type_name,
+                live: 1,
+            }),
+        }
+    }
+    sites.sort_by_key(|site| Reverse(site.live));
+    let unreachable = nodes
+        .into_iter()
+        .zip(reachable)
+        .filter(|&(_, reachable)| !reachable)
+        .map(|((_, alloc, _), _)| alloc)
+        .collect();
+    Report { sites, unreachable }
+}
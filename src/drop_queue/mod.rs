@@ -0,0 +1,102 @@
+//! A queue for values that should be dropped later.
+//!
+//! Dropping a value can be expensive, for example when it frees a large tree of
+//! allocations. Values taken out of a container while a lock is held or on a hot path can
+//! be parked in a `DropQueue` instead, and then dropped together with `flush` once it is
+//! convenient.
+
+use std::error;
+use std::fmt;
+
+use error::Error;
+
+/// A bounded queue of values waiting to be dropped.
+///
+/// Space for every value is allocated up front, so parking a value never allocates.
+pub struct DropQueue<T> {
+    parked: Vec<T>,
+    capacity: usize,
+}
+
+impl<T> DropQueue<T> {
+    /// Creates a queue that can hold up to `capacity` values.
+    pub fn with_capacity(capacity: usize) -> DropQueue<T> {
+        DropQueue {
+            parked: Vec::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// The maximum number of values that can be parked.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The number of values that are waiting to be dropped.
+    pub fn len(&self) -> usize {
+        self.parked.len()
+    }
+
+    /// Returns true if no values are waiting to be dropped.
+    pub fn is_empty(&self) -> bool {
+        self.parked.is_empty()
+    }
+
+    /// Returns true if no more values can be parked.
+    pub fn is_full(&self) -> bool {
+        self.parked.len() == self.capacity
+    }
+
+    /// Parks a value to be dropped later. If the queue is full, the value is given back.
+    pub fn park(&mut self, val: T) -> Result<(), QueueFullError<T>> {
+        if self.is_full() {
+            return Err(QueueFullError { val });
+        }
+        self.parked.push(val);
+        Ok(())
+    }
+
+    /// Drops every parked value, returning how many were dropped.
+    pub fn flush(&mut self) -> usize {
+        let count = self.parked.len();
+        self.parked.clear();
+        count
+    }
+}
+
+/// The error returned when parking a value in a full queue. This contains the value, so it
+/// can be dropped or parked somewhere else.
+pub struct QueueFullError<T> {
+    val: T,
+}
+
+impl<T> QueueFullError<T> {
+    /// Gets back the value that could not be parked.
+    pub fn into_inner(self) -> T {
+        self.val
+    }
+}
+
+impl<T> fmt::Debug for QueueFullError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Attempted to park a value in a full drop queue.")
+    }
+}
+
+impl<T> fmt::Display for QueueFullError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the drop queue is full")
+    }
+}
+
+impl<T> error::Error for QueueFullError<T> {}
+
+impl<T> From<QueueFullError<T>> for Error {
+    /// Converts the error, dropping the value it contains.
+    fn from(_: QueueFullError<T>) -> Error {
+        Error::BudgetExceeded {
+            requested: 1,
+            available: 0,
+        }
+    }
+}
@@ -0,0 +1,160 @@
+//! A crate wide error type.
+//!
+//! Most types in this crate have their own error types, which often give the value that
+//! caused the error back to the caller. These can all be converted into `Error`, so code
+//! that uses several parts of this crate can handle failures in one place.
+
+use std::error;
+use std::fmt;
+use std::ops::Range;
+
+use cstring::InteriorNulError;
+use might_own::NotOwnedError;
+use pinned::LeaseError;
+use uninitialized::{SparseFormatError, UninitializedError};
+
+/// An error from any part of this crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// A value was required to be initialized, but it was not.
+    Uninitialized {
+        /// The number of slots in the container.
+        len: usize,
+        /// The first slot that was uninitialized, if it is known.
+        index: Option<usize>,
+    },
+    /// An index was outside of a container.
+    OutOfBounds {
+        /// The index that was used.
+        index: usize,
+        /// The number of slots in the container.
+        len: usize,
+    },
+    /// Ownership of a value was required, but the value was borrowed.
+    NotOwned,
+    /// Two ranges that were required to be disjoint overlapped.
+    Overlap {
+        /// The first range.
+        first: Range<usize>,
+        /// The range that overlapped it.
+        second: Range<usize>,
+    },
+    /// An operation needed more space than a container allows.
+    BudgetExceeded {
+        /// The amount that was requested.
+        requested: usize,
+        /// The amount that was available.
+        available: usize,
+    },
+    /// A value could not be changed because it is being borrowed.
+    InUse {
+        /// The number of outstanding borrows.
+        borrows: usize,
+    },
+    /// A slot that can only be written once was written again.
+    AlreadyInitialized {
+        /// The slot that was already initialized.
+        index: usize,
+    },
+    /// Bytes that were required to be free of nul bytes contained one.
+    InteriorNul {
+        /// The position of the nul byte.
+        position: usize,
+    },
+    /// A region of a `PinnedBytes` buffer could not be leased.
+    Lease(LeaseError),
+    /// Sparse bytes could not be decoded.
+    SparseFormat(SparseFormatError),
+}
+
+/// A `Result` using the crate wide error type.
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Uninitialized {
+                len,
+                index: Some(index),
+            } => write!(
+                f,
+                "slot {} of a container of length {} is uninitialized",
+                index, len
+            ),
+            Error::Uninitialized { len, index: None } => write!(
+                f,
+                "a container of length {} contains uninitialized slots",
+                len
+            ),
+            Error::OutOfBounds { index, len } => write!(
+                f,
+                "index {} is out of bounds for a container of length {}",
+                index, len
+            ),
+            Error::NotOwned => write!(f, "the value is not owned"),
+            Error::Overlap {
+                ref first,
+                ref second,
+            } => write!(f, "range {:?} overlaps range {:?}", second, first),
+            Error::BudgetExceeded {
+                requested,
+                available,
+            } => write!(
+                f,
+                "requested {} but only {} is available",
+                requested, available
+            ),
+            Error::InUse { borrows } => {
+                write!(f, "the value is in use by {} borrows", borrows)
+            }
+            Error::AlreadyInitialized { index } => {
+                write!(f, "slot {} is already initialized", index)
+            }
+            Error::InteriorNul { position } => {
+                write!(f, "nul byte found at position {}", position)
+            }
+            Error::Lease(ref err) => err.fmt(f),
+            Error::SparseFormat(ref err) => err.fmt(f),
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl<T> From<UninitializedError<T>> for Error {
+    /// Converts the error, dropping the vector it contains.
+    fn from(err: UninitializedError<T>) -> Error {
+        let index = err.first_uninitialized_index();
+        Error::Uninitialized {
+            len: err.unwrap().len(),
+            index,
+        }
+    }
+}
+
+impl<'a, T: ?Sized> From<NotOwnedError<'a, T>> for Error {
+    fn from(_: NotOwnedError<'a, T>) -> Error {
+        Error::NotOwned
+    }
+}
+
+impl From<InteriorNulError> for Error {
+    fn from(err: InteriorNulError) -> Error {
+        Error::InteriorNul {
+            position: err.nul_position(),
+        }
+    }
+}
+
+impl From<LeaseError> for Error {
+    fn from(err: LeaseError) -> Error {
+        Error::Lease(err)
+    }
+}
+
+impl From<SparseFormatError> for Error {
+    fn from(err: SparseFormatError) -> Error {
+        Error::SparseFormat(err)
+    }
+}
@@ -0,0 +1,90 @@
+//! A tracked vector that remembers when each slot was initialized.
+//!
+//! This turns a `SafeUninitializedVec` into a simple slot cache: every value is stamped
+//! with a time when it is written, and stale values can be dropped in bulk. Times are
+//! plain `u64` ticks chosen by the caller, such as milliseconds since startup or a
+//! generation counter.
+
+use uninitialized::SafeUninitializedVec;
+
+/// A tracked vector with an initialization time for every slot.
+pub struct ExpiringVec<T> {
+    vals: SafeUninitializedVec<T>,
+    // the time each initialized slot was last written or touched
+    stamps: Vec<u64>,
+}
+
+impl<T> ExpiringVec<T> {
+    /// Creates a vector of `len` uninitialized slots.
+    pub fn new(len: usize) -> ExpiringVec<T> {
+        ExpiringVec {
+            vals: SafeUninitializedVec::new(len),
+            stamps: vec![0; len],
+        }
+    }
+
+    /// The number of slots.
+    pub fn len(&self) -> usize {
+        self.stamps.len()
+    }
+
+    /// Returns true if there are no slots.
+    pub fn is_empty(&self) -> bool {
+        self.stamps.is_empty()
+    }
+
+    /// Sets a value, stamping it with `now`. Drops an existing value if present.
+    pub fn set_value(&mut self, i: usize, val: T, now: u64) {
+        self.vals.set_value(i, val);
+        self.stamps[i] = now;
+    }
+
+    /// Gets a reference to a value. Will return none if the value is not initialized.
+    pub fn get_value(&self, i: usize) -> Option<&T> {
+        self.vals.get_value(i)
+    }
+
+    /// Gets a mutable reference to a value. Will return none if the value is not
+    /// initialized. This does not change the stamp of the value.
+    pub fn get_value_mut(&mut self, i: usize) -> Option<&mut T> {
+        self.vals.get_value_mut(i)
+    }
+
+    /// Moves a value out of the vector, marking its slot as uninitialized.
+    pub fn take(&mut self, i: usize) -> Option<T> {
+        self.vals.take(i)
+    }
+
+    /// The time a value was stamped with, or none if it is not initialized.
+    pub fn stamp(&self, i: usize) -> Option<u64> {
+        self.vals.get_value(i).map(|_| self.stamps[i])
+    }
+
+    /// Restamps a value with `now`, returning false if it is not initialized.
+    pub fn touch(&mut self, i: usize, now: u64) -> bool {
+        let initialized = self.vals.get_value(i).is_some();
+        if initialized {
+            self.stamps[i] = now;
+        }
+        initialized
+    }
+
+    /// Drops every value that was stamped before `time`, returning how many were dropped.
+    pub fn expire_older_than(&mut self, time: u64) -> usize {
+        let stale: Vec<usize> = self
+            .vals
+            .mask()
+            .ones()
+            .filter(|&i| self.stamps[i] < time)
+            .collect();
+        for &i in &stale {
+            drop(self.vals.take(i));
+        }
+        stale.len()
+    }
+
+    /// Gets the underlying vector, discarding the stamps.
+    pub fn into_inner(self) -> SafeUninitializedVec<T> {
+        self.vals
+    }
+}
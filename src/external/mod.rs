@@ -0,0 +1,131 @@
+//! Initialization tracking for memory that is owned by something else.
+//!
+//! This is meant for memory mapped from devices, such as mapped GPU buffers. The memory
+//! starts out uninitialized, and the wrapper keeps track of which values have been written
+//! and which ranges need to be flushed when the memory is not coherent.
+
+use std::mem;
+use std::ops::Range;
+use std::ptr;
+use std::slice;
+
+use mask::InitMask;
+
+/// A tracked view of externally owned memory.
+///
+/// Only `Copy` values can be stored, since the memory is not owned by this type and the
+/// values in it are never dropped.
+pub struct ExternalMapped<T: Copy> {
+    ptr: *mut T,
+    initialized: InitMask,
+    // values written since the last flush
+    dirty: InitMask,
+}
+
+impl<T: Copy> ExternalMapped<T> {
+    /// Wraps `len` values of mapped memory starting at `ptr`. Every value starts
+    /// out uninitialized.
+    ///
+    /// # Safety
+    /// `ptr` must be valid for reads and writes of `len` values of `T` and properly aligned
+    /// for as long as the `ExternalMapped` exists, and the memory must not be accessed
+    /// through any other pointer during that time.
+    pub unsafe fn new(ptr: *mut T, len: usize) -> ExternalMapped<T> {
+        ExternalMapped {
+            ptr,
+            initialized: InitMask::new(len),
+            dirty: InitMask::new(len),
+        }
+    }
+
+    /// The number of values in the mapped memory.
+    pub fn len(&self) -> usize {
+        self.initialized.len()
+    }
+
+    /// Returns true if the mapped memory has no values.
+    pub fn is_empty(&self) -> bool {
+        self.initialized.is_empty()
+    }
+
+    /// The pointer the memory was mapped at.
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr
+    }
+
+    /// Gets the mask of which values have been written.
+    pub fn mask(&self) -> &InitMask {
+        &self.initialized
+    }
+
+    /// Writes a value to the mapped memory.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn set_value(&mut self, i: usize, val: T) {
+        assert!(i < self.len(), "index out of bounds");
+        unsafe {
+            ptr::write(self.ptr.add(i), val);
+        }
+        self.initialized.set(i, true);
+        self.dirty.set(i, true);
+    }
+
+    /// Writes a slice of values starting at `offset`.
+    ///
+    /// Panics if the values do not fit in the mapped memory.
+    pub fn write_slice(&mut self, offset: usize, vals: &[T]) {
+        assert!(
+            offset <= self.len() && vals.len() <= self.len() - offset,
+            "slice out of bounds"
+        );
+        let end = offset + vals.len();
+        unsafe {
+            ptr::copy_nonoverlapping(vals.as_ptr(), self.ptr.add(offset), vals.len());
+        }
+        self.initialized.set_range(offset..end, true);
+        self.dirty.set_range(offset..end, true);
+    }
+
+    /// Gets a value from the mapped memory. Will return none if the value
+    /// has not been written.
+    pub fn get_value(&self, i: usize) -> Option<&T> {
+        if self.initialized.is_set(i) {
+            unsafe { Some(&*self.ptr.add(i)) }
+        } else {
+            None
+        }
+    }
+
+    /// Gets the whole mapped memory as a slice, if every value has been written.
+    pub fn as_slice(&self) -> Option<&[T]> {
+        if self.initialized.all() {
+            unsafe { Some(slice::from_raw_parts(self.ptr, self.len())) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if some values have been written since the last flush.
+    pub fn needs_flush(&self) -> bool {
+        self.dirty.any()
+    }
+
+    /// Gets the ranges of values that have been written since the last flush,
+    /// and marks them as flushed.
+    pub fn take_dirty_ranges(&mut self) -> Vec<Range<usize>> {
+        let ranges = self.dirty.runs().collect();
+        self.dirty = InitMask::new(self.len());
+        ranges
+    }
+
+    /// Gets the byte ranges, relative to the start of the mapping, that have been written
+    /// since the last flush, and marks them as flushed. These can be passed directly to
+    /// flush calls for non-coherent memory.
+    pub fn take_flush_ranges(&mut self) -> Vec<Range<usize>> {
+        let size = mem::size_of::<T>();
+        self.take_dirty_ranges()
+            .into_iter()
+            .map(|r| r.start * size..r.end * size)
+            .collect()
+    }
+}
@@ -0,0 +1,152 @@
+//! A tracked vector whose slots carry generations.
+//!
+//! Every time a value is taken out of a slot or overwritten, the generation of that
+//! slot is increased. A `WeakIndex` remembers the generation it was created with, so
+//! it can detect when the slot it points to has been reused by a different value.
+
+use std::fmt;
+
+use uninitialized::SafeUninitializedVec;
+
+/// A `SafeUninitializedVec` that keeps a generation counter for every slot.
+pub struct GenerationalVec<T> {
+    vals: SafeUninitializedVec<T>,
+    generations: Vec<u32>,
+}
+
+/// An index into a `GenerationalVec` that can tell whether the value it was
+/// created for is still present.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct WeakIndex {
+    index: usize,
+    generation: u32,
+}
+
+impl<T> GenerationalVec<T> {
+    /// Creates a new `GenerationalVec` where every slot is uninitialized.
+    pub fn new(len: usize) -> GenerationalVec<T> {
+        GenerationalVec {
+            vals: SafeUninitializedVec::new(len),
+            generations: vec![0; len],
+        }
+    }
+
+    /// Sets the value at `i`, returning a weak index to the new value.
+    ///
+    /// If there was already a value in the slot, it is dropped and every
+    /// existing weak index to it becomes stale.
+    pub fn set_value(&mut self, i: usize, val: T) -> WeakIndex {
+        if self.vals.get_value(i).is_some() {
+            self.bump(i);
+        }
+        self.vals.set_value(i, val);
+        WeakIndex {
+            index: i,
+            generation: self.generations[i],
+        }
+    }
+
+    /// Gets a reference to the value at `i`, if it is initialized.
+    pub fn get_value(&self, i: usize) -> Option<&T> {
+        self.vals.get_value(i)
+    }
+
+    /// Gets a mutable reference to the value at `i`, if it is initialized.
+    pub fn get_value_mut(&mut self, i: usize) -> Option<&mut T> {
+        self.vals.get_value_mut(i)
+    }
+
+    /// Moves the value at `i` out of the vector. Weak indices to it become stale.
+    pub fn take(&mut self, i: usize) -> Option<T> {
+        let val = self.vals.take(i);
+        if val.is_some() {
+            self.bump(i);
+        }
+        val
+    }
+
+    /// Creates a weak index to the value currently stored at `i`.
+    ///
+    /// Returns `None` if the slot is not initialized.
+    pub fn weak_index(&self, i: usize) -> Option<WeakIndex> {
+        self.vals.get_value(i).map(|_| WeakIndex {
+            index: i,
+            generation: self.generations[i],
+        })
+    }
+
+    /// The current generation of the slot at `i`.
+    pub fn generation(&self, i: usize) -> u32 {
+        self.generations[i]
+    }
+
+    /// Gets the value a weak index points to. Fails if the value has been
+    /// taken or replaced since the index was created.
+    pub fn get(&self, index: WeakIndex) -> Option<&T> {
+        if self.is_live(index) {
+            self.vals.get_value(index.index)
+        } else {
+            None
+        }
+    }
+
+    /// Gets a mutable reference to the value a weak index points to.
+    pub fn get_mut(&mut self, index: WeakIndex) -> Option<&mut T> {
+        if self.is_live(index) {
+            self.vals.get_value_mut(index.index)
+        } else {
+            None
+        }
+    }
+
+    /// Takes the value a weak index points to, if it is still present.
+    pub fn take_weak(&mut self, index: WeakIndex) -> Option<T> {
+        if self.is_live(index) {
+            self.take(index.index)
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if the value the weak index was created for is still in the vector.
+    pub fn is_live(&self, index: WeakIndex) -> bool {
+        match self.generations.get(index.index) {
+            Some(&generation) => {
+                generation == index.generation && self.vals.get_value(index.index).is_some()
+            }
+            None => false,
+        }
+    }
+
+    /// Gets the underlying `SafeUninitializedVec`, discarding the generations.
+    pub fn into_inner(self) -> SafeUninitializedVec<T> {
+        self.vals
+    }
+
+    fn bump(&mut self, i: usize) {
+        self.generations[i] = self.generations[i].wrapping_add(1);
+    }
+}
+
+impl WeakIndex {
+    /// The slot this index points to.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The generation of the slot when this index was created.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Gets the value this index points to. Shorthand for `GenerationalVec::get`.
+    pub fn get<'a, T>(&self, vec: &'a GenerationalVec<T>) -> Option<&'a T> {
+        vec.get(*self)
+    }
+}
+
+impl fmt::Debug for WeakIndex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "WeakIndex({}@{})", self.index, self.generation)
+    }
+}
@@ -0,0 +1,154 @@
+//! Global slots that are initialized at runtime and can be torn down during shutdown.
+//!
+//! A `TrackedStatic` can be put in a `static`. It starts out uninitialized, is initialized
+//! exactly once, and can then be read from any thread. Unlike a lazily initialized static,
+//! its value can also be dropped explicitly, for example to flush and close a resource
+//! before the process exits. Reads hold a guard, so the value is never dropped while it
+//! is being read.
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::ptr;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+use error::{Error, Result};
+
+const UNINIT: u8 = 0;
+const INITIALIZING: u8 = 1;
+const INIT: u8 = 2;
+const DROPPING: u8 = 3;
+const DROPPED: u8 = 4;
+
+/// A slot for a global value that is initialized once and can be dropped explicitly.
+pub struct TrackedStatic<T> {
+    val: UnsafeCell<MaybeUninit<T>>,
+    state: AtomicU8,
+    readers: AtomicUsize,
+}
+
+// the value is only written before it is published and only dropped once no one reads it
+unsafe impl<T: Send + Sync> Sync for TrackedStatic<T> {}
+unsafe impl<T: Send> Send for TrackedStatic<T> {}
+
+impl<T> Drop for TrackedStatic<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == INIT {
+            unsafe {
+                ptr::drop_in_place(self.val.get_mut().as_mut_ptr());
+            }
+        }
+    }
+}
+
+impl<T> TrackedStatic<T> {
+    /// Creates an uninitialized slot. This can be used to initialize a `static`.
+    pub const fn new() -> TrackedStatic<T> {
+        TrackedStatic {
+            val: UnsafeCell::new(MaybeUninit::uninit()),
+            state: AtomicU8::new(UNINIT),
+            readers: AtomicUsize::new(0),
+        }
+    }
+
+    /// Initializes the slot. If it has already been initialized, the value is given back.
+    ///
+    /// A slot can only be initialized once, even if its value has since been dropped.
+    pub fn init(&self, val: T) -> ::std::result::Result<(), T> {
+        if self
+            .state
+            .compare_exchange(UNINIT, INITIALIZING, Ordering::Acquire, Ordering::Acquire)
+            .is_err()
+        {
+            return Err(val);
+        }
+        unsafe {
+            (*self.val.get()).as_mut_ptr().write(val);
+        }
+        self.state.store(INIT, Ordering::Release);
+        Ok(())
+    }
+
+    /// Returns true if the slot holds a value.
+    pub fn is_initialized(&self) -> bool {
+        self.state.load(Ordering::Acquire) == INIT
+    }
+
+    /// Returns true if the value has been dropped with `deinit`.
+    pub fn is_dropped(&self) -> bool {
+        self.state.load(Ordering::Acquire) == DROPPED
+    }
+
+    /// Gets a guard that reads the value, or none if the slot does not hold a value.
+    ///
+    /// The value can not be dropped while the guard exists.
+    pub fn get(&self) -> Option<StaticRef<'_, T>> {
+        // registering as a reader before checking the state means that `deinit` either
+        // sees this reader or this sees that the value is being dropped
+        self.readers.fetch_add(1, Ordering::SeqCst);
+        if self.state.load(Ordering::SeqCst) == INIT {
+            Some(StaticRef { slot: self })
+        } else {
+            self.readers.fetch_sub(1, Ordering::SeqCst);
+            None
+        }
+    }
+
+    /// Drops the value, returning true if there was a value to drop.
+    ///
+    /// Fails if the value is currently being read.
+    pub fn deinit(&self) -> Result<bool> {
+        if self
+            .state
+            .compare_exchange(INIT, DROPPING, Ordering::SeqCst, Ordering::SeqCst)
+            .is_err()
+        {
+            return Ok(false);
+        }
+        let borrows = self.readers.load(Ordering::SeqCst);
+        if borrows > 0 {
+            self.state.store(INIT, Ordering::SeqCst);
+            return Err(Error::InUse { borrows });
+        }
+        unsafe {
+            ptr::drop_in_place((*self.val.get()).as_mut_ptr());
+        }
+        self.state.store(DROPPED, Ordering::Release);
+        Ok(true)
+    }
+}
+
+impl<T> Default for TrackedStatic<T> {
+    fn default() -> TrackedStatic<T> {
+        TrackedStatic::new()
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for TrackedStatic<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.get() {
+            Some(val) => f.debug_tuple("TrackedStatic").field(&*val).finish(),
+            None => write!(f, "TrackedStatic(<uninitialized>)"),
+        }
+    }
+}
+
+/// A guard that reads the value of a `TrackedStatic`.
+pub struct StaticRef<'a, T: 'a> {
+    slot: &'a TrackedStatic<T>,
+}
+
+impl<'a, T> Drop for StaticRef<'a, T> {
+    fn drop(&mut self) {
+        self.slot.readers.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl<'a, T> Deref for StaticRef<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*(*self.slot.val.get()).as_ptr() }
+    }
+}
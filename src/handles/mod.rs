@@ -0,0 +1,207 @@
+//! A tracked vector that hands out stable handles to its values.
+//!
+//! Values can be moved around inside the vector (by compacting, swap removal or sorting)
+//! and a `Handle` will keep pointing to the same value. This is done using an indirection
+//! table that maps every handle to the current position of its value.
+
+use std::cmp::Ordering;
+use std::fmt;
+
+use uninitialized::SafeUninitializedVec;
+
+/// A `SafeUninitializedVec` whose values can be referred to by `Handle`s that survive
+/// reorganization of the vector.
+pub struct HandleVec<T> {
+    vals: SafeUninitializedVec<T>,
+    // the entry in the table that owns each position
+    owners: Vec<Option<usize>>,
+    table: Vec<Entry>,
+    free: Vec<usize>,
+}
+
+struct Entry {
+    position: Option<usize>,
+    generation: u32,
+}
+
+/// A stable reference to a value in a `HandleVec`.
+///
+/// A handle stays valid until its value is removed from the vector. Handles are never
+/// confused with each other, even if the table entry is reused.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Handle {
+    id: usize,
+    generation: u32,
+}
+
+impl<T> HandleVec<T> {
+    /// Creates a new `HandleVec` with `len` uninitialized slots.
+    pub fn new(len: usize) -> HandleVec<T> {
+        HandleVec {
+            vals: SafeUninitializedVec::new(len),
+            owners: vec![None; len],
+            table: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// The number of slots in the vector.
+    pub fn len(&self) -> usize {
+        self.owners.len()
+    }
+
+    /// Returns true if the vector has no slots.
+    pub fn is_empty(&self) -> bool {
+        self.owners.is_empty()
+    }
+
+    /// Sets the value at position `i`, returning a handle to it.
+    ///
+    /// If the slot already contains a value, that value is dropped and its handle
+    /// becomes invalid.
+    pub fn set_value(&mut self, i: usize, val: T) -> Handle {
+        self.vals.set_value(i, val);
+        if let Some(id) = self.owners[i].take() {
+            self.release(id);
+        }
+        let id = match self.free.pop() {
+            Some(id) => id,
+            None => {
+                self.table.push(Entry {
+                    position: None,
+                    generation: 0,
+                });
+                self.table.len() - 1
+            }
+        };
+        self.table[id].position = Some(i);
+        self.owners[i] = Some(id);
+        Handle {
+            id,
+            generation: self.table[id].generation,
+        }
+    }
+
+    /// Gets the value at position `i`.
+    pub fn get_value(&self, i: usize) -> Option<&T> {
+        self.vals.get_value(i)
+    }
+
+    /// Gets the handle of the value currently at position `i`.
+    pub fn handle_at(&self, i: usize) -> Option<Handle> {
+        self.owners[i].map(|id| Handle {
+            id,
+            generation: self.table[id].generation,
+        })
+    }
+
+    /// Gets the current position of the value a handle refers to.
+    pub fn position(&self, handle: Handle) -> Option<usize> {
+        match self.table.get(handle.id) {
+            Some(entry) if entry.generation == handle.generation => entry.position,
+            _ => None,
+        }
+    }
+
+    /// Gets the value a handle refers to.
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.position(handle).and_then(|i| self.vals.get_value(i))
+    }
+
+    /// Gets a mutable reference to the value a handle refers to.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        match self.position(handle) {
+            Some(i) => self.vals.get_value_mut(i),
+            None => None,
+        }
+    }
+
+    /// Removes the value a handle refers to, leaving its slot uninitialized.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        self.position(handle).and_then(|i| self.take(i))
+    }
+
+    /// Takes the value at position `i`, invalidating its handle.
+    pub fn take(&mut self, i: usize) -> Option<T> {
+        if let Some(id) = self.owners[i].take() {
+            self.release(id);
+        }
+        self.vals.take(i)
+    }
+
+    /// Swaps the values at two positions. Handles follow their values.
+    pub fn swap(&mut self, x: usize, y: usize) {
+        self.vals.swap(x, y);
+        self.owners.swap(x, y);
+        self.relocate(x);
+        self.relocate(y);
+    }
+
+    /// Takes the value at position `i` and moves the value in the last slot into its place.
+    pub fn swap_remove(&mut self, i: usize) -> Option<T> {
+        let val = self.take(i);
+        let last = self.len() - 1;
+        self.swap(i, last);
+        val
+    }
+
+    /// Moves every value to the front of the vector, preserving their order, and returns
+    /// the number of values. Handles keep pointing to the same values.
+    pub fn compact(&mut self) -> usize {
+        let mut dense = 0;
+        for i in 0..self.len() {
+            if self.owners[i].is_some() {
+                if i != dense {
+                    self.swap(i, dense);
+                }
+                dense += 1;
+            }
+        }
+        dense
+    }
+
+    /// Compacts the vector and sorts the values. Handles keep pointing to the same values.
+    pub fn sort_by<F: FnMut(&T, &T) -> Ordering>(&mut self, mut compare: F) {
+        let dense = self.compact();
+        let mut moved: Vec<(Option<usize>, T)> = (0..dense)
+            .map(|i| (self.owners[i].take(), self.vals.take(i).unwrap()))
+            .collect();
+        moved.sort_by(|a, b| compare(&a.1, &b.1));
+        for (i, (owner, val)) in moved.into_iter().enumerate() {
+            self.vals.set_value(i, val);
+            self.owners[i] = owner;
+            self.relocate(i);
+        }
+    }
+
+    /// Gets the underlying `SafeUninitializedVec`, invalidating all handles.
+    pub fn into_inner(self) -> SafeUninitializedVec<T> {
+        self.vals
+    }
+
+    fn relocate(&mut self, i: usize) {
+        if let Some(id) = self.owners[i] {
+            self.table[id].position = Some(i);
+        }
+    }
+
+    fn release(&mut self, id: usize) {
+        let entry = &mut self.table[id];
+        entry.position = None;
+        entry.generation = entry.generation.wrapping_add(1);
+        self.free.push(id);
+    }
+}
+
+impl<T: Ord> HandleVec<T> {
+    /// Compacts the vector and sorts the values. Handles keep pointing to the same values.
+    pub fn sort(&mut self) {
+        self.sort_by(Ord::cmp)
+    }
+}
+
+impl fmt::Debug for Handle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handle({}@{})", self.id, self.generation)
+    }
+}
@@ -0,0 +1,176 @@
+//! A binary heap whose storage can be filled out of order before the heap is built.
+//!
+//! Producers first claim ranges of slots, then fill the claimed slots in any order.
+//! Claims can be made and filled from several threads at once. Once everything has been
+//! written, `heapify` builds the heap in linear time over the claimed slots, reusing the
+//! storage they were written to.
+
+use std::cell::UnsafeCell;
+use std::collections::BinaryHeap;
+use std::mem::{self, ManuallyDrop, MaybeUninit};
+use std::ops::Range;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use error::{Error, Result};
+use mask::AtomicInitMask;
+
+/// Fixed capacity tracked storage that is turned into a `BinaryHeap`.
+pub struct TrackedHeap<T: Ord> {
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    initialized: AtomicInitMask,
+    claimed: AtomicUsize,
+}
+
+// a slot is only written through the claim that owns it, or through a unique reference
+unsafe impl<T: Ord + Send> Sync for TrackedHeap<T> {}
+
+/// A range of slots of a `TrackedHeap` that has been claimed by one producer.
+///
+/// Claims never overlap, so each one can be filled on its own thread.
+pub struct Claim<'a, T: 'a + Ord> {
+    heap: &'a TrackedHeap<T>,
+    range: Range<usize>,
+}
+
+impl<T: Ord> Drop for TrackedHeap<T> {
+    fn drop(&mut self) {
+        let mut from = 0;
+        while let Some(i) = self.initialized.next_set(from, Ordering::Relaxed) {
+            self.initialized.unset(i, Ordering::Relaxed);
+            from = i + 1;
+            unsafe {
+                ptr::drop_in_place(self.slots[i].get_mut().as_mut_ptr());
+            }
+        }
+    }
+}
+
+impl<T: Ord> TrackedHeap<T> {
+    /// Creates storage for up to `capacity` values.
+    pub fn with_capacity(capacity: usize) -> TrackedHeap<T> {
+        TrackedHeap {
+            slots: new_slots(capacity),
+            initialized: AtomicInitMask::new(capacity),
+            claimed: AtomicUsize::new(0),
+        }
+    }
+
+    /// The maximum number of values.
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// The number of slots that have been claimed.
+    pub fn claimed(&self) -> usize {
+        self.claimed.load(Ordering::Relaxed)
+    }
+
+    /// Claims the next `count` slots.
+    ///
+    /// Several producers can claim at once, and each gets its own slots. Fails if there
+    /// are not enough unclaimed slots left.
+    pub fn claim(&self, count: usize) -> Result<Claim<'_, T>> {
+        let capacity = self.capacity();
+        let claimed = self
+            .claimed
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |claimed| {
+                if count <= capacity - claimed {
+                    Some(claimed + count)
+                } else {
+                    None
+                }
+            });
+        match claimed {
+            Ok(start) => Ok(Claim {
+                heap: self,
+                range: start..start + count,
+            }),
+            Err(claimed) => Err(Error::BudgetExceeded {
+                requested: count,
+                available: capacity - claimed,
+            }),
+        }
+    }
+
+    /// Writes a value into a claimed slot, dropping any value already there.
+    ///
+    /// Panics if the slot has not been claimed.
+    pub fn set_value(&mut self, i: usize, val: T) {
+        assert!(i < self.claimed(), "slot {} has not been claimed", i);
+        // no claims can be alive, so nothing else can write to the slot
+        unsafe { self.write(i, val) }
+    }
+
+    /// Gets the value in a slot, if it has been written.
+    pub fn get_value(&mut self, i: usize) -> Option<&T> {
+        if i < self.claimed() && self.initialized.is_set(i, Ordering::Relaxed) {
+            unsafe { Some(&*self.slots[i].get_mut().as_ptr()) }
+        } else {
+            None
+        }
+    }
+
+    /// Builds a heap from every claimed slot.
+    ///
+    /// Fails without changing anything if any claimed slot has not been written.
+    /// Otherwise, the storage is handed to the heap as it is and heapified in place, and
+    /// the heap gets new storage so that it can be reused.
+    pub fn heapify(&mut self) -> Result<BinaryHeap<T>> {
+        let claimed = self.claimed();
+        let hole = self.initialized.next_unset(0, Ordering::Relaxed);
+        if let Some(index) = hole.filter(|&i| i < claimed) {
+            return Err(Error::Uninitialized {
+                len: claimed,
+                index: Some(index),
+            });
+        }
+        let capacity = self.capacity();
+        let mut slots = ManuallyDrop::new(mem::replace(&mut self.slots, new_slots(capacity)));
+        self.initialized = AtomicInitMask::new(capacity);
+        self.claimed = AtomicUsize::new(0);
+        // UnsafeCell and MaybeUninit have the same layout as T, and every slot before
+        // `claimed` holds a value, while the slots after it were never written
+        let vals = unsafe { Vec::from_raw_parts(slots.as_mut_ptr() as *mut T, claimed, capacity) };
+        Ok(BinaryHeap::from(vals))
+    }
+
+    // writes a value to slot `i`, which nothing else may be accessing
+    unsafe fn write(&self, i: usize, val: T) {
+        let slot = self.slots[i].get() as *mut T;
+        if self.initialized.is_set(i, Ordering::Relaxed) {
+            // the new value is in place before the old one is dropped, in case that panics
+            drop(ptr::replace(slot, val));
+        } else {
+            ptr::write(slot, val);
+            self.initialized.set(i, Ordering::Relaxed);
+        }
+    }
+}
+
+impl<'a, T: Ord> Claim<'a, T> {
+    /// The indices of the claimed slots.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Writes a value into one of the claimed slots, dropping any value already there.
+    ///
+    /// Panics if `i` is not in the claimed range.
+    pub fn set_value(&mut self, i: usize, val: T) {
+        assert!(
+            self.range.contains(&i),
+            "slot {} is not in the claim {:?}",
+            i,
+            self.range
+        );
+        // only this claim can access its slots
+        unsafe { self.heap.write(i, val) }
+    }
+}
+
+fn new_slots<T>(len: usize) -> Box<[UnsafeCell<MaybeUninit<T>>]> {
+    (0..len)
+        .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+        .collect()
+}
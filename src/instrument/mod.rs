@@ -0,0 +1,75 @@
+//! Reports the state of tracked containers through the `metrics` facade.
+//!
+//! With the `metrics` feature enabled, `SafeUninitializedVec` emits these metrics:
+//!
+//! * `tracked_mem_live_containers` (gauge): the number of vectors that exist.
+//! * `tracked_mem_tracked_bytes` (gauge): the size of the tracked slots of every vector.
+//! * `tracked_mem_initialized_slots` (gauge): the number of initialized slots.
+//! * `tracked_mem_fills_total` (counter): the number of times a slot was initialized.
+//!   A fill rate can be derived from this counter.
+//!
+//! Without the feature, every function here does nothing. Every place that changes the
+//! number of vectors, their tracked length, or their number of initialized slots must
+//! report the change. Vectors do this through their private `report_*` methods, which
+//! also keep the `registry` up to date.
+
+#[cfg(feature = "metrics")]
+mod report {
+    use metrics::{counter, gauge};
+
+    pub fn container_created() {
+        gauge!("tracked_mem_live_containers").increment(1.0);
+    }
+
+    pub fn container_dropped() {
+        gauge!("tracked_mem_live_containers").decrement(1.0);
+    }
+
+    pub fn bytes_tracked(bytes: usize) {
+        if bytes > 0 {
+            gauge!("tracked_mem_tracked_bytes").increment(bytes as f64);
+        }
+    }
+
+    pub fn bytes_untracked(bytes: usize) {
+        if bytes > 0 {
+            gauge!("tracked_mem_tracked_bytes").decrement(bytes as f64);
+        }
+    }
+
+    pub fn slots_initialized(count: usize) {
+        if count > 0 {
+            gauge!("tracked_mem_initialized_slots").increment(count as f64);
+            counter!("tracked_mem_fills_total").increment(count as u64);
+        }
+    }
+
+    pub fn slots_deinitialized(count: usize) {
+        if count > 0 {
+            gauge!("tracked_mem_initialized_slots").decrement(count as f64);
+        }
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+mod report {
+    #[inline(always)]
+    pub fn container_created() {}
+
+    #[inline(always)]
+    pub fn container_dropped() {}
+
+    #[inline(always)]
+    pub fn bytes_tracked(_bytes: usize) {}
+
+    #[inline(always)]
+    pub fn bytes_untracked(_bytes: usize) {}
+
+    #[inline(always)]
+    pub fn slots_initialized(_count: usize) {}
+
+    #[inline(always)]
+    pub fn slots_deinitialized(_count: usize) {}
+}
+
+pub use self::report::*;
@@ -7,11 +7,108 @@
 //! that would fit in this library that would be helpful. Any contributions
 //! or suggestions are welcome.
 
+#[cfg(feature = "arrow")]
+extern crate arrow_array;
+#[cfg(feature = "arrow")]
+extern crate arrow_buffer;
+#[cfg(feature = "bitvec")]
+extern crate bitvec;
+#[cfg(feature = "bytes")]
+extern crate bytes;
+#[cfg(feature = "metrics")]
+extern crate metrics;
+// the pyo3 macros refer to `::core`, which needs to be declared in a 2015 edition crate
+#[cfg(feature = "python")]
+extern crate core;
+#[cfg(feature = "python")]
+extern crate pyo3;
+#[cfg(feature = "rand")]
+extern crate rand;
+#[cfg(feature = "rayon")]
+extern crate rayon;
+#[cfg(feature = "rkyv")]
+extern crate rkyv;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(test)]
+extern crate serde_json;
+#[cfg(feature = "zeroize")]
+extern crate zeroize;
+
+#[macro_use]
+pub mod soa;
+
 #[cfg(test)]
 mod tests;
 
+mod instrument;
+
+#[cfg(feature = "registry")]
+pub mod registry;
+#[cfg(feature = "registry")]
+pub use registry::report;
+
+pub mod mask;
+pub use mask::{AtomicInitMask, InitMask};
+
+pub mod alloc;
+pub use alloc::{Global, RawAlloc};
+
 pub mod uninitialized;
-pub use uninitialized::SafeUninitializedVec;
+pub use uninitialized::{OnceVec, SafeUninitializedVec, SequentialVec, TrackedSlice};
 
 pub mod might_own;
-pub use might_own::MightOwn;
+pub use might_own::{MightOwn, MightOwnSlice};
+
+pub mod generational;
+pub use generational::GenerationalVec;
+
+pub mod handles;
+pub use handles::HandleVec;
+
+pub mod cow;
+pub use cow::CowUninitVec;
+
+pub mod arena;
+pub use arena::PinArena;
+
+pub mod external;
+pub use external::ExternalMapped;
+
+pub mod pinned;
+pub use pinned::PinnedBytes;
+
+pub mod error;
+pub use error::Error;
+
+pub mod heap;
+pub use heap::TrackedHeap;
+
+pub mod buffer;
+pub use buffer::TrackedBytes;
+
+pub mod drop_queue;
+pub use drop_queue::DropQueue;
+
+pub mod ordered;
+pub use ordered::OrderedDrops;
+
+pub mod counted;
+pub use counted::{CountedDrop, DropCounter};
+
+pub mod global;
+pub use global::TrackedStatic;
+
+pub mod cstring;
+pub use cstring::TrackedCStringBuf;
+
+pub mod diagnostics;
+
+pub mod expiry;
+pub use expiry::ExpiringVec;
+
+pub mod once;
+pub use once::OnceArray;
+
+pub mod pool;
+pub use pool::BlockPool;
@@ -0,0 +1,156 @@
+//! A mask whose bits can be set from several threads at once.
+
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::InitMask;
+
+const WORD_BITS: usize = 64;
+
+/// A fixed length set of bits that can be changed through a shared reference.
+///
+/// Bits are packed into atomic words, so setting a bit never blocks.
+pub struct AtomicInitMask {
+    words: Box<[AtomicU64]>,
+    len: usize,
+}
+
+impl AtomicInitMask {
+    /// Creates a mask of `len` bits that are all unset.
+    pub fn new(len: usize) -> AtomicInitMask {
+        AtomicInitMask {
+            words: (0..len.div_ceil(WORD_BITS))
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+            len,
+        }
+    }
+
+    /// The number of bits in the mask.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the mask has no bits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns true if bit `i` is set.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn is_set(&self, i: usize, order: Ordering) -> bool {
+        let (word, bit) = self.locate(i);
+        self.words[word].load(order) & bit != 0
+    }
+
+    /// Sets bit `i`, returning its previous value.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn set(&self, i: usize, order: Ordering) -> bool {
+        let (word, bit) = self.locate(i);
+        self.words[word].fetch_or(bit, order) & bit != 0
+    }
+
+    /// Unsets bit `i`, returning its previous value.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn unset(&self, i: usize, order: Ordering) -> bool {
+        let (word, bit) = self.locate(i);
+        self.words[word].fetch_and(!bit, order) & bit != 0
+    }
+
+    /// Sets the first unset bit, returning its index, or none if every bit is set.
+    ///
+    /// Each bit is only ever claimed by one caller, even if several call this at once.
+    pub fn claim_first_unset(&self, order: Ordering) -> Option<usize> {
+        for (w, word) in self.words.iter().enumerate() {
+            let mut current = word.load(Ordering::Relaxed);
+            loop {
+                let free = !current;
+                let i = w * WORD_BITS + free.trailing_zeros() as usize;
+                if free == 0 || i >= self.len {
+                    break;
+                }
+                let bit = free & free.wrapping_neg();
+                match word.compare_exchange_weak(current, current | bit, order, Ordering::Relaxed) {
+                    Ok(_) => return Some(i),
+                    Err(actual) => current = actual,
+                }
+            }
+        }
+        None
+    }
+
+    /// The index of the first set bit at or after `from`, if there is one.
+    pub fn next_set(&self, from: usize, order: Ordering) -> Option<usize> {
+        self.next_matching(from, true, order)
+    }
+
+    /// The index of the first unset bit at or after `from`, if there is one.
+    pub fn next_unset(&self, from: usize, order: Ordering) -> Option<usize> {
+        self.next_matching(from, false, order)
+    }
+
+    // checks a word at a time, starting with the rest of the word holding `from`
+    fn next_matching(&self, from: usize, value: bool, order: Ordering) -> Option<usize> {
+        if from >= self.len {
+            return None;
+        }
+        let flip = if value { 0 } else { !0 };
+        let first = from / WORD_BITS;
+        for (w, word) in self.words.iter().enumerate().skip(first) {
+            let mut bits = word.load(order) ^ flip;
+            if w == first {
+                bits &= !0 << (from % WORD_BITS);
+            }
+            if bits != 0 {
+                let i = w * WORD_BITS + bits.trailing_zeros() as usize;
+                // unset bits past the end look like matches when searching for unset bits
+                return if i < self.len { Some(i) } else { None };
+            }
+        }
+        None
+    }
+
+    /// The number of set bits. This is not a consistent snapshot if bits are changed
+    /// while counting.
+    pub fn count_ones(&self, order: Ordering) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.load(order).count_ones() as usize)
+            .sum()
+    }
+
+    /// Copies the current bits into an `InitMask`. Each word is read once, so bits changed
+    /// while copying may or may not be included.
+    pub fn load(&self, order: Ordering) -> InitMask {
+        let mut mask = InitMask::new(self.len);
+        for (w, word) in self.words.iter().enumerate() {
+            let mut bits = word.load(order);
+            while bits != 0 {
+                let i = w * WORD_BITS + bits.trailing_zeros() as usize;
+                mask.set(i, true);
+                bits &= bits - 1;
+            }
+        }
+        mask
+    }
+
+    fn locate(&self, i: usize) -> (usize, u64) {
+        assert!(
+            i < self.len,
+            "index {} out of bounds for mask of length {}",
+            i,
+            self.len
+        );
+        (i / WORD_BITS, 1 << (i % WORD_BITS))
+    }
+}
+
+impl fmt::Debug for AtomicInitMask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Atomic")?;
+        fmt::Debug::fmt(&self.load(Ordering::Relaxed), f)
+    }
+}
@@ -0,0 +1,118 @@
+//! Ranges of a mask's bits that can be borrowed separately.
+
+use std::cmp;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::{word_count, word_mask, WORD_BITS};
+
+// a range of the bits of an `InitMask`, borrowed so that it can be changed independently
+// of the rest of the mask
+// the words at the edges of the range can be shared with neighbouring ranges, so every
+// change is made with a single atomic operation on its word
+pub(crate) struct BitsMut<'a> {
+    words: &'a [AtomicU64],
+    range: Range<usize>,
+}
+
+impl<'a> BitsMut<'a> {
+    pub(crate) fn new(words: &'a [AtomicU64], range: Range<usize>) -> BitsMut<'a> {
+        BitsMut { words, range }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    fn locate(&self, i: usize) -> (&AtomicU64, u64) {
+        assert!(
+            i < self.len(),
+            "index {} out of bounds for length {}",
+            i,
+            self.len()
+        );
+        let i = self.range.start + i;
+        (&self.words[i / WORD_BITS], 1 << (i % WORD_BITS))
+    }
+
+    pub(crate) fn is_set(&self, i: usize) -> bool {
+        let (word, bit) = self.locate(i);
+        word.load(Ordering::Relaxed) & bit != 0
+    }
+
+    // sets bit `i` to `value`, returning its previous value
+    pub(crate) fn set(&mut self, i: usize, value: bool) -> bool {
+        let (word, bit) = self.locate(i);
+        let old = if value {
+            word.fetch_or(bit, Ordering::Relaxed)
+        } else {
+            word.fetch_and(!bit, Ordering::Relaxed)
+        };
+        old & bit != 0
+    }
+
+    pub(crate) fn swap(&mut self, x: usize, y: usize) {
+        let a = self.is_set(x);
+        let b = self.set(y, a);
+        self.set(x, b);
+    }
+
+    pub(crate) fn count_ones(&self) -> usize {
+        self.words_from(0)
+            .map(|(_, word, mask)| (word & mask).count_ones() as usize)
+            .sum()
+    }
+
+    // the first unset bit at or after `from`, relative to the start of the range
+    pub(crate) fn next_zero(&self, from: usize) -> Option<usize> {
+        self.next_matching(from, false)
+    }
+
+    // the first set bit at or after `from`, relative to the start of the range
+    pub(crate) fn next_one(&self, from: usize) -> Option<usize> {
+        self.next_matching(from, true)
+    }
+
+    fn next_matching(&self, from: usize, value: bool) -> Option<usize> {
+        if from >= self.len() {
+            return None;
+        }
+        let flip = if value { 0 } else { !0 };
+        self.words_from(from).find_map(|(base, word, mask)| {
+            let matches = (word ^ flip) & mask;
+            if matches != 0 {
+                Some(base + matches.trailing_zeros() as usize - self.range.start)
+            } else {
+                None
+            }
+        })
+    }
+
+    // the words holding the bits of the range from `from` on, along with the index of the
+    // first bit of each word and a mask of the bits of the word that are in the range
+    fn words_from(&self, from: usize) -> impl Iterator<Item = (usize, u64, u64)> + '_ {
+        let start = self.range.start + from;
+        let end = self.range.end;
+        (start / WORD_BITS..word_count(end)).map(move |w| {
+            let base = w * WORD_BITS;
+            let mask = word_mask(start.saturating_sub(base), cmp::min(end - base, WORD_BITS));
+            (base, self.words[w].load(Ordering::Relaxed), mask)
+        })
+    }
+
+    // a range of these bits, relative to the start of this range
+    // callers make sure that the ranges they hand out never overlap
+    pub(crate) fn range(&self, range: Range<usize>) -> BitsMut<'a> {
+        assert!(range.start <= range.end && range.end <= self.len());
+        let start = self.range.start;
+        BitsMut {
+            words: self.words,
+            range: start + range.start..start + range.end,
+        }
+    }
+
+    pub(crate) fn split_at(self, mid: usize) -> (BitsMut<'a>, BitsMut<'a>) {
+        let len = self.len();
+        (self.range(0..mid), self.range(mid..len))
+    }
+}
@@ -0,0 +1,165 @@
+//! The interval representation of an `InitMask`.
+
+use std::cmp;
+use std::ops::Range;
+
+// the set bits of a mask as a sorted list of ranges, for masks whose bits are set and
+// unset in large contiguous chunks
+// the ranges are never empty and never touch each other
+#[derive(Clone, Default)]
+pub(super) struct Intervals {
+    runs: Vec<Range<usize>>,
+}
+
+impl Intervals {
+    pub(super) fn runs(&self) -> &[Range<usize>] {
+        &self.runs
+    }
+
+    // the index of the first run that ends after `i`
+    fn first_ending_after(&self, i: usize) -> usize {
+        self.runs.partition_point(|run| run.end <= i)
+    }
+
+    pub(super) fn is_set(&self, i: usize) -> bool {
+        match self.runs.get(self.first_ending_after(i)) {
+            Some(run) => run.start <= i,
+            None => false,
+        }
+    }
+
+    pub(super) fn count_ones(&self) -> usize {
+        self.runs.iter().map(|run| run.len()).sum()
+    }
+
+    // the number of set bits in `range`
+    pub(super) fn count_in(&self, range: Range<usize>) -> usize {
+        self.runs[self.first_ending_after(range.start)..]
+            .iter()
+            .take_while(|run| run.start < range.end)
+            .map(|run| cmp::min(run.end, range.end) - cmp::max(run.start, range.start))
+            .sum()
+    }
+
+    // sets every bit in `range` to `value`, returning the number of bits that changed
+    pub(super) fn set_range(&mut self, range: Range<usize>, value: bool) -> usize {
+        if range.is_empty() {
+            return 0;
+        }
+        let ones = self.count_in(range.clone());
+        // the runs that overlap or touch the range
+        let first = self.runs.partition_point(|run| run.end < range.start);
+        let last = self.runs.partition_point(|run| run.start <= range.end);
+        if value {
+            let mut merged = range.clone();
+            if first < last {
+                merged.start = cmp::min(merged.start, self.runs[first].start);
+                merged.end = cmp::max(merged.end, self.runs[last - 1].end);
+            }
+            self.runs.splice(first..last, Some(merged));
+            range.len() - ones
+        } else {
+            let mut kept = Vec::new();
+            if first < last {
+                kept.push(self.runs[first].start..range.start);
+                kept.push(range.end..self.runs[last - 1].end);
+            }
+            self.runs
+                .splice(first..last, kept.into_iter().filter(|run| !run.is_empty()));
+            ones
+        }
+    }
+
+    // the first index at or after `from` and before `len` whose bit is `value`
+    pub(super) fn next_matching(&self, from: usize, value: bool, len: usize) -> Option<usize> {
+        let run = self.runs.get(self.first_ending_after(from));
+        let i = match (run, value) {
+            (Some(run), true) => cmp::max(run.start, from),
+            (None, true) => return None,
+            // runs never touch, so the end of a run is always unset
+            (Some(run), false) if run.start <= from => run.end,
+            (_, false) => from,
+        };
+        if i < len {
+            Some(i)
+        } else {
+            None
+        }
+    }
+
+    // the index of the `n`th set bit
+    pub(super) fn select(&self, mut n: usize) -> Option<usize> {
+        for run in &self.runs {
+            if n < run.len() {
+                return Some(run.start + n);
+            }
+            n -= run.len();
+        }
+        None
+    }
+
+    // inserts a bit at `i`, shifting the bits after it up
+    pub(super) fn insert(&mut self, i: usize, value: bool) {
+        for run in &mut self.runs {
+            if run.start >= i {
+                run.start += 1;
+                run.end += 1;
+            } else if run.end > i {
+                run.end += 1;
+            }
+        }
+        // a run that held `i` now covers the new bit too
+        self.set_range(i..i + 1, false);
+        self.set_range(i..i + 1, value);
+    }
+
+    // removes bit `i`, shifting the bits after it down
+    pub(super) fn remove(&mut self, i: usize) -> bool {
+        let value = self.set_range(i..i + 1, false) == 1;
+        for run in &mut self.runs {
+            if run.start > i {
+                run.start -= 1;
+                run.end -= 1;
+            }
+        }
+        // the runs on either side of the removed bit can now touch
+        let k = self.runs.partition_point(|run| run.end < i);
+        if k + 1 < self.runs.len() && self.runs[k].end == self.runs[k + 1].start {
+            self.runs[k].end = self.runs.remove(k + 1).end;
+        }
+        value
+    }
+
+    // unsets every bit from `len` on
+    pub(super) fn truncate(&mut self, len: usize) {
+        let k = self.runs.partition_point(|run| run.start < len);
+        self.runs.truncate(k);
+        if let Some(run) = self.runs.last_mut() {
+            run.end = cmp::min(run.end, len);
+        }
+    }
+
+    // flips every bit before `len`
+    pub(super) fn invert(&mut self, len: usize) {
+        let mut gaps = Vec::with_capacity(self.runs.len() + 1);
+        let mut pos = 0;
+        for run in &self.runs {
+            if run.start > pos {
+                gaps.push(pos..run.start);
+            }
+            pos = run.end;
+        }
+        if pos < len {
+            gaps.push(pos..len);
+        }
+        self.runs = gaps;
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.runs.clear();
+    }
+
+    pub(super) fn shrink_to_fit(&mut self) {
+        self.runs.shrink_to_fit();
+    }
+}
@@ -0,0 +1,925 @@
+//! The initialization mask used by the tracked containers in this crate.
+//!
+//! An `InitMask` stores one bit for every slot of a container, which is set if the
+//! slot is initialized. It can also be used on its own as a simple bitset.
+//!
+//! The bits are packed into 64 bit words, with a summary of which words are full and
+//! which are empty. Searches for the next set or unset bit use the summary to skip 4096
+//! slots at a time, so finding a hole stays fast in masks of millions of bits.
+//!
+//! A mask created with `InitMask::new_intervals` instead stores the ranges of set bits,
+//! which takes much less space when slots are initialized in large contiguous chunks.
+
+use std::cmp;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not, Range};
+use std::slice;
+use std::sync::atomic::AtomicU64;
+
+mod atomic;
+mod bits;
+mod intervals;
+mod summary;
+
+pub use self::atomic::AtomicInitMask;
+pub(crate) use self::bits::BitsMut;
+use self::intervals::Intervals;
+use self::summary::Summary;
+
+const WORD_BITS: usize = 64;
+
+/// A set of bits recording which slots of a container are initialized.
+///
+/// The bits are packed into 64 bit words, so counting and searching look at 64 slots
+/// at a time, and searches skip over runs of full or empty words using a summary level.
+///
+/// A mask can also track its set bits as a list of ranges, see `new_intervals`. Both
+/// kinds of mask have the same behavior, and masks with the same bits are equal no matter
+/// how they are stored.
+#[derive(Default)]
+pub struct InitMask {
+    // the words are atomic so that `BitsMut` ranges can share the words at their edges,
+    // but the mask itself only touches them through `words` and `words_mut`
+    // every bit past `len` is unset
+    words: Vec<AtomicU64>,
+    len: usize,
+    summary: Summary,
+    // the set bits as ranges, if the mask is in interval mode, in which case there are no
+    // words
+    // boxed to keep masks in bit mode small
+    intervals: Option<Box<Intervals>>,
+}
+
+// the number of words needed to hold `len` bits
+pub(crate) const fn word_count(len: usize) -> usize {
+    len.div_ceil(WORD_BITS)
+}
+
+// a word with the bits at positions `from..to` set
+fn word_mask(from: usize, to: usize) -> u64 {
+    let high = if to == WORD_BITS { !0 } else { (1 << to) - 1 };
+    high & !((1 << from) - 1)
+}
+
+impl InitMask {
+    /// Creates a mask of `len` bits that are all unset.
+    pub fn new(len: usize) -> InitMask {
+        InitMask::repeat(false, len)
+    }
+
+    /// Creates a mask of `len` bits that are all set to `value`.
+    pub fn repeat(value: bool, len: usize) -> InitMask {
+        let word = if value { !0 } else { 0 };
+        InitMask::from_words(
+            (0..word_count(len)).map(|_| AtomicU64::new(word)).collect(),
+            len,
+        )
+    }
+
+    /// Creates a mask of `len` bits that are all unset, which stores the ranges of set
+    /// bits instead of one bit per slot.
+    ///
+    /// This uses space for every run of set bits rather than for every bit, so it suits
+    /// containers that are filled in large contiguous chunks. Looking up or changing a
+    /// single bit takes time logarithmic in the number of runs, and inserting or removing
+    /// a bit takes time linear in it.
+    pub fn new_intervals(len: usize) -> InitMask {
+        InitMask {
+            words: Vec::new(),
+            len,
+            summary: Summary::default(),
+            intervals: Some(Box::default()),
+        }
+    }
+
+    /// Returns true if the mask stores ranges of set bits instead of one bit per slot.
+    pub fn uses_intervals(&self) -> bool {
+        self.intervals.is_some()
+    }
+
+    fn from_words(words: Vec<AtomicU64>, len: usize) -> InitMask {
+        let mut mask = InitMask {
+            words,
+            len,
+            summary: Summary::default(),
+            intervals: None,
+        };
+        mask.clear_unused();
+        mask.update_summary(0);
+        mask
+    }
+
+    // creates a mask from packed words, with bit `i` of the mask at bit `i % 64` of word
+    // `i / 64`
+    #[cfg(feature = "arrow")]
+    pub(crate) fn from_u64s(words: Vec<u64>, len: usize) -> InitMask {
+        let mut words: Vec<AtomicU64> = words.into_iter().map(AtomicU64::new).collect();
+        words.truncate(word_count(len));
+        assert!(
+            words.len() == word_count(len),
+            "not enough words for {} bits",
+            len
+        );
+        InitMask::from_words(words, len)
+    }
+
+    // the packed words of the mask, laid out like they are taken by `from_u64s`
+    #[cfg(feature = "arrow")]
+    pub(crate) fn into_u64s(mut self) -> Vec<u64> {
+        self.use_words();
+        let InitMask { words, .. } = self;
+        words.into_iter().map(AtomicU64::into_inner).collect()
+    }
+
+    // no one else can hold a reference to the words while the mask is borrowed, and an
+    // atomic word has the same size as a plain one and at least the same alignment
+    fn words(&self) -> &[u64] {
+        unsafe { slice::from_raw_parts(self.words.as_ptr() as *const u64, self.words.len()) }
+    }
+
+    // the packed words of the mask, switching an interval mask to one bit per slot first
+    #[cfg(feature = "bitvec")]
+    pub(crate) fn as_u64s(&mut self) -> &[u64] {
+        self.use_words();
+        self.words()
+    }
+
+    fn words_mut(&mut self) -> &mut [u64] {
+        unsafe { slice::from_raw_parts_mut(self.words.as_mut_ptr() as *mut u64, self.words.len()) }
+    }
+
+    // unsets the bits of the last word that are past the end of the mask
+    fn clear_unused(&mut self) {
+        let used = self.len % WORD_BITS;
+        if let (true, Some(last)) = (used != 0, self.words_mut().last_mut()) {
+            *last &= word_mask(0, used);
+        }
+    }
+
+    // switches an interval mask to one bit per slot
+    fn use_words(&mut self) {
+        if let Some(intervals) = self.intervals.take() {
+            let mut mask = InitMask::new(self.len);
+            for run in intervals.runs() {
+                mask.set_range(run.clone(), true);
+            }
+            *self = mask;
+        }
+    }
+
+    // brings the summary of every word from the one holding bit `from` on up to date,
+    // after the length of the mask or those words changed
+    fn update_summary(&mut self, from: usize) {
+        self.summary.resize(self.words.len());
+        self.summarize(from / WORD_BITS..self.words.len());
+    }
+
+    fn summarize(&mut self, words: Range<usize>) {
+        for w in words {
+            let (full, nonempty) = self.word_state(w);
+            self.summary.update(w, full, nonempty);
+        }
+    }
+
+    // whether word `w` is full and whether it has any bit set
+    fn word_state(&self, w: usize) -> (bool, bool) {
+        let word = self.words()[w];
+        let used = cmp::min(self.len - w * WORD_BITS, WORD_BITS);
+        (word == word_mask(0, used), word != 0)
+    }
+
+    // the summary, rebuilt first if words were changed through a `BitsMut`
+    fn fresh_summary(&self) -> &Summary {
+        if self.summary.is_stale() {
+            for w in 0..self.words.len() {
+                let (full, nonempty) = self.word_state(w);
+                self.summary.update_shared(w, full, nonempty);
+            }
+            self.summary.mark_fresh();
+        }
+        &self.summary
+    }
+
+    fn locate(&self, i: usize) -> (usize, u64) {
+        assert!(
+            i < self.len,
+            "index {} out of bounds for mask of length {}",
+            i,
+            self.len
+        );
+        (i / WORD_BITS, 1 << (i % WORD_BITS))
+    }
+
+    /// The number of bits in the mask.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns true if the mask has no bits.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns true if bit `i` is set.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn is_set(&self, i: usize) -> bool {
+        let (word, bit) = self.locate(i);
+        match self.intervals {
+            Some(ref intervals) => intervals.is_set(i),
+            None => self.words()[word] & bit != 0,
+        }
+    }
+
+    /// Sets bit `i` to `value`, returning its previous value.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn set(&mut self, i: usize, value: bool) -> bool {
+        let (w, bit) = self.locate(i);
+        if let Some(ref mut intervals) = self.intervals {
+            return (intervals.set_range(i..i + 1, value) == 1) != value;
+        }
+        let word = &mut self.words_mut()[w];
+        let old = *word & bit != 0;
+        if value {
+            *word |= bit;
+        } else {
+            *word &= !bit;
+        }
+        self.summarize(w..w + 1);
+        old
+    }
+
+    // sets a bit without checking that it is in bounds
+    pub(crate) unsafe fn set_unchecked(&mut self, i: usize, value: bool) {
+        if let Some(ref mut intervals) = self.intervals {
+            intervals.set_range(i..i + 1, value);
+            return;
+        }
+        let (w, bit) = (i / WORD_BITS, 1 << (i % WORD_BITS));
+        let word = self.words_mut().get_unchecked_mut(w);
+        if value {
+            *word |= bit;
+        } else {
+            *word &= !bit;
+        }
+        self.summarize(w..w + 1);
+    }
+
+    // every bit of the mask, as a range that views can split between each other
+    // an interval mask is switched to one bit per slot first
+    pub(crate) fn bits_mut(&mut self) -> BitsMut<'_> {
+        self.use_words();
+        self.summary.mark_stale();
+        BitsMut::new(&self.words, 0..self.len)
+    }
+
+    /// Sets every bit in `range` to `value`, returning the number of bits that changed.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn set_range(&mut self, range: Range<usize>, value: bool) -> usize {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "range {:?} out of bounds for mask of length {}",
+            range,
+            self.len
+        );
+        if let Some(ref mut intervals) = self.intervals {
+            return intervals.set_range(range, value);
+        }
+        let mut changed = 0;
+        let mut i = range.start;
+        while i < range.end {
+            let w = i / WORD_BITS;
+            let to = (range.end - w * WORD_BITS).min(WORD_BITS);
+            let mask = word_mask(i % WORD_BITS, to);
+            let word = &mut self.words_mut()[w];
+            let new = if value { *word | mask } else { *word & !mask };
+            changed += (*word ^ new).count_ones() as usize;
+            *word = new;
+            i = (w + 1) * WORD_BITS;
+        }
+        self.summarize(range.start / WORD_BITS..word_count(range.end));
+        changed
+    }
+
+    /// Swaps two bits.
+    ///
+    /// Panics if either index is out of bounds.
+    pub fn swap(&mut self, x: usize, y: usize) {
+        let a = self.is_set(x);
+        let b = self.set(y, a);
+        self.set(x, b);
+    }
+
+    /// Adds a bit to the end of the mask.
+    pub fn push(&mut self, value: bool) {
+        if self.intervals.is_none() && self.len.is_multiple_of(WORD_BITS) {
+            self.words.push(AtomicU64::new(0));
+        }
+        self.len += 1;
+        self.update_summary(self.len - 1);
+        self.set(self.len - 1, value);
+    }
+
+    /// Removes the last bit of the mask.
+    pub fn pop(&mut self) -> Option<bool> {
+        if self.len == 0 {
+            return None;
+        }
+        let value = self.set(self.len - 1, false);
+        self.len -= 1;
+        self.words.truncate(word_count(self.len));
+        self.update_summary(self.len);
+        Some(value)
+    }
+
+    /// Inserts a bit at `i`, shifting the bits after it up.
+    ///
+    /// Panics if `i` is greater than the length of the mask.
+    pub fn insert(&mut self, i: usize, value: bool) {
+        assert!(
+            i <= self.len,
+            "insertion index {} is out of bounds for mask of length {}",
+            i,
+            self.len
+        );
+        if let Some(ref mut intervals) = self.intervals {
+            intervals.insert(i, value);
+            self.len += 1;
+            return;
+        }
+        self.push(false);
+        let first = i / WORD_BITS;
+        let words = self.words_mut();
+        for w in (first + 1..words.len()).rev() {
+            words[w] = (words[w] << 1) | (words[w - 1] >> (WORD_BITS - 1));
+        }
+        let low = word_mask(0, i % WORD_BITS);
+        words[first] = (words[first] & low) | ((words[first] & !low) << 1);
+        self.update_summary(i);
+        self.set(i, value);
+    }
+
+    /// Removes bit `i`, shifting the bits after it down. Returns the removed bit.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn remove(&mut self, i: usize) -> bool {
+        let value = self.is_set(i);
+        if let Some(ref mut intervals) = self.intervals {
+            self.len -= 1;
+            return intervals.remove(i);
+        }
+        let first = i / WORD_BITS;
+        let words = self.words_mut();
+        let low = word_mask(0, i % WORD_BITS);
+        words[first] = (words[first] & low) | ((words[first] >> 1) & !low);
+        for w in first + 1..words.len() {
+            words[w - 1] |= words[w] << (WORD_BITS - 1);
+            words[w] >>= 1;
+        }
+        self.len -= 1;
+        self.words.truncate(word_count(self.len));
+        self.update_summary(i);
+        value
+    }
+
+    /// Reserves space for at least `additional` more bits.
+    pub fn reserve(&mut self, additional: usize) {
+        if self.intervals.is_some() {
+            return;
+        }
+        let needed = word_count(self.len + additional) - self.words.len();
+        self.words.reserve(needed);
+    }
+
+    /// Reserves space for exactly `additional` more bits.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        if self.intervals.is_some() {
+            return;
+        }
+        let needed = word_count(self.len + additional) - self.words.len();
+        self.words.reserve_exact(needed);
+    }
+
+    /// Frees as much unused space as possible.
+    pub fn shrink_to_fit(&mut self) {
+        self.words.shrink_to_fit();
+        if let Some(ref mut intervals) = self.intervals {
+            intervals.shrink_to_fit();
+        }
+    }
+
+    /// Changes the length of the mask, setting any new bits to `value`.
+    pub fn resize(&mut self, len: usize, value: bool) {
+        if len <= self.len {
+            self.truncate(len);
+            return;
+        }
+        let old = self.len;
+        if self.intervals.is_none() {
+            self.words
+                .resize_with(word_count(len), || AtomicU64::new(0));
+        }
+        self.len = len;
+        self.update_summary(old);
+        if value {
+            self.set_range(old..len, true);
+        }
+    }
+
+    /// Shortens the mask to `len` bits.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            self.len = len;
+            self.words.truncate(word_count(len));
+            self.clear_unused();
+            self.update_summary(len);
+            if let Some(ref mut intervals) = self.intervals {
+                intervals.truncate(len);
+            }
+        }
+    }
+
+    /// Removes every bit from the mask.
+    pub fn clear(&mut self) {
+        self.words.clear();
+        self.len = 0;
+        self.update_summary(0);
+        if let Some(ref mut intervals) = self.intervals {
+            intervals.clear();
+        }
+    }
+
+    /// Splits the mask in two at `at`, returning the bits from `at` on. The new mask is
+    /// stored the same way as this one.
+    ///
+    /// Panics if `at` is greater than the length of the mask.
+    pub fn split_off(&mut self, at: usize) -> InitMask {
+        assert!(
+            at <= self.len,
+            "index {} out of bounds for mask of length {}",
+            at,
+            self.len
+        );
+        let len = self.len - at;
+        let mut rest = if self.uses_intervals() {
+            InitMask::new_intervals(len)
+        } else {
+            InitMask::new(len)
+        };
+        let mut from = at;
+        while let Some(start) = self.next_one(from) {
+            let end = self.next_zero(start).unwrap_or(self.len);
+            rest.set_range(start - at..end - at, true);
+            from = end;
+        }
+        self.truncate(at);
+        rest
+    }
+
+    /// The number of set bits.
+    pub fn count_ones(&self) -> usize {
+        if let Some(ref intervals) = self.intervals {
+            return intervals.count_ones();
+        }
+        self.words()
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// The number of set bits in `range`.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn count_ones_in(&self, range: Range<usize>) -> usize {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "range {:?} out of bounds for mask of length {}",
+            range,
+            self.len
+        );
+        if let Some(ref intervals) = self.intervals {
+            return intervals.count_in(range);
+        }
+        let words = self.words();
+        (range.start / WORD_BITS..word_count(range.end))
+            .map(|w| {
+                let base = w * WORD_BITS;
+                let bits = word_mask(
+                    range.start.saturating_sub(base),
+                    cmp::min(range.end - base, WORD_BITS),
+                );
+                (words[w] & bits).count_ones() as usize
+            })
+            .sum()
+    }
+
+    /// The number of unset bits.
+    pub fn count_zeros(&self) -> usize {
+        self.len() - self.count_ones()
+    }
+
+    /// Returns true if every bit is set.
+    pub fn all(&self) -> bool {
+        self.next_zero(0).is_none()
+    }
+
+    /// Returns true if any bit is set.
+    pub fn any(&self) -> bool {
+        self.next_one(0).is_some()
+    }
+
+    /// The index of the first unset bit at or after `from`, if there is one.
+    pub fn next_zero(&self, from: usize) -> Option<usize> {
+        self.next_matching(from, false)
+    }
+
+    /// The index of the first set bit at or after `from`, if there is one.
+    pub fn next_one(&self, from: usize) -> Option<usize> {
+        self.next_matching(from, true)
+    }
+
+    // checks the rest of the word holding `from`, then uses the summary to find the next
+    // word with a bit that is `value`
+    fn next_matching(&self, from: usize, value: bool) -> Option<usize> {
+        if from >= self.len {
+            return None;
+        }
+        if let Some(ref intervals) = self.intervals {
+            return intervals.next_matching(from, value, self.len);
+        }
+        let words = self.words();
+        let flip = if value { 0 } else { !0 };
+        let mut w = from / WORD_BITS;
+        let mut word = (words[w] ^ flip) & !word_mask(0, from % WORD_BITS);
+        if word == 0 {
+            w = self.fresh_summary().next_word(w + 1, value)?;
+            word = *words.get(w)? ^ flip;
+        }
+        let i = w * WORD_BITS + word.trailing_zeros() as usize;
+        // unset bits past the end look like matches when searching for zeros
+        if i < self.len {
+            Some(i)
+        } else {
+            None
+        }
+    }
+
+    /// The number of set bits before index `i`.
+    ///
+    /// Panics if `i` is greater than the length of the mask.
+    pub fn rank(&self, i: usize) -> usize {
+        assert!(
+            i <= self.len,
+            "index {} out of bounds for mask of length {}",
+            i,
+            self.len
+        );
+        if let Some(ref intervals) = self.intervals {
+            return intervals.count_in(0..i);
+        }
+        let words = self.words();
+        let full: usize = words[..i / WORD_BITS]
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum();
+        let partial = match words.get(i / WORD_BITS) {
+            Some(word) => (word & word_mask(0, i % WORD_BITS)).count_ones() as usize,
+            None => 0,
+        };
+        full + partial
+    }
+
+    /// The index of the `n`th set bit, counting from zero.
+    pub fn select(&self, mut n: usize) -> Option<usize> {
+        if let Some(ref intervals) = self.intervals {
+            return intervals.select(n);
+        }
+        for (w, &word) in self.words().iter().enumerate() {
+            let ones = word.count_ones() as usize;
+            if n < ones {
+                let mut word = word;
+                for _ in 0..n {
+                    word &= word - 1;
+                }
+                return Some(w * WORD_BITS + word.trailing_zeros() as usize);
+            }
+            n -= ones;
+        }
+        None
+    }
+
+    /// An iterator over every bit in the mask.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            mask: self,
+            range: 0..self.len,
+        }
+    }
+    /// An iterator over the indices of set bits.
+    pub fn ones(&self) -> Indices<'_> {
+        Indices {
+            mask: self,
+            pos: 0,
+            value: true,
+        }
+    }
+
+    /// An iterator over the indices of unset bits.
+    pub fn zeros(&self) -> Indices<'_> {
+        Indices {
+            mask: self,
+            pos: 0,
+            value: false,
+        }
+    }
+
+    /// An iterator over every maximal range of set bits.
+    pub fn runs(&self) -> Runs<'_> {
+        Runs {
+            mask: self,
+            pos: 0,
+            value: true,
+        }
+    }
+
+    /// An iterator over every maximal range of unset bits.
+    pub fn gaps(&self) -> Runs<'_> {
+        Runs {
+            mask: self,
+            pos: 0,
+            value: false,
+        }
+    }
+
+    /// A mask with the bits that are set in both masks.
+    ///
+    /// Panics if the masks have different lengths.
+    pub fn intersection(&self, other: &InitMask) -> InitMask {
+        let mut mask = self.clone();
+        mask.intersect_with(other);
+        mask
+    }
+
+    /// A mask with the bits that are set in either mask.
+    ///
+    /// Panics if the masks have different lengths.
+    pub fn union(&self, other: &InitMask) -> InitMask {
+        let mut mask = self.clone();
+        mask.union_with(other);
+        mask
+    }
+
+    /// A mask with the bits that are set in this mask but not in `other`.
+    ///
+    /// Panics if the masks have different lengths.
+    pub fn difference(&self, other: &InitMask) -> InitMask {
+        let mut mask = self.clone();
+        mask.difference_with(other);
+        mask
+    }
+
+    /// Unsets every bit that is not set in `other`.
+    pub fn intersect_with(&mut self, other: &InitMask) {
+        self.combine(other, |a, b| a & b, other.gaps(), false);
+    }
+
+    /// Sets every bit that is set in `other`.
+    pub fn union_with(&mut self, other: &InitMask) {
+        self.combine(other, |a, b| a | b, other.runs(), true);
+    }
+
+    /// Unsets every bit that is set in `other`.
+    pub fn difference_with(&mut self, other: &InitMask) {
+        self.combine(other, |a, b| a & !b, other.runs(), false);
+    }
+
+    /// Flips every bit in the mask.
+    pub fn invert(&mut self) {
+        if let Some(ref mut intervals) = self.intervals {
+            intervals.invert(self.len);
+            return;
+        }
+        for word in self.words_mut() {
+            *word = !*word;
+        }
+        self.clear_unused();
+        self.update_summary(0);
+    }
+
+    /// Returns true if every bit set in this mask is also set in `other`.
+    pub fn is_subset(&self, other: &InitMask) -> bool {
+        assert_eq!(self.len(), other.len(), "masks have different lengths");
+        if self.uses_intervals() || other.uses_intervals() {
+            return !self.difference(other).any();
+        }
+        self.words()
+            .iter()
+            .zip(other.words())
+            .all(|(&a, &b)| a & !b == 0)
+    }
+
+    // applies `op` to every pair of words, which must leave the bits past the end unset
+    // if either mask stores intervals, every range of `ranges` is set to `value` instead
+    fn combine<F>(&mut self, other: &InitMask, op: F, ranges: Runs, value: bool)
+    where
+        F: Fn(u64, u64) -> u64,
+    {
+        assert_eq!(self.len(), other.len(), "masks have different lengths");
+        if self.uses_intervals() || other.uses_intervals() {
+            for range in ranges {
+                self.set_range(range, value);
+            }
+            return;
+        }
+        for (a, &b) in self.words_mut().iter_mut().zip(other.words()) {
+            *a = op(*a, b);
+        }
+        self.update_summary(0);
+    }
+}
+
+impl Clone for InitMask {
+    fn clone(&self) -> InitMask {
+        if let Some(ref intervals) = self.intervals {
+            return InitMask {
+                words: Vec::new(),
+                len: self.len,
+                summary: Summary::default(),
+                intervals: Some(intervals.clone()),
+            };
+        }
+        let words = self
+            .words()
+            .iter()
+            .map(|&word| AtomicU64::new(word))
+            .collect();
+        InitMask::from_words(words, self.len)
+    }
+}
+
+impl PartialEq for InitMask {
+    fn eq(&self, other: &InitMask) -> bool {
+        if self.uses_intervals() || other.uses_intervals() {
+            return self.len == other.len && self.runs().eq(other.runs());
+        }
+        self.len == other.len && self.words() == other.words()
+    }
+}
+
+impl Eq for InitMask {}
+
+impl Hash for InitMask {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // hashes the runs, which are the same however the bits are stored
+        self.len.hash(state);
+        for run in self.runs() {
+            run.hash(state);
+        }
+    }
+}
+
+impl From<Vec<bool>> for InitMask {
+    fn from(bits: Vec<bool>) -> InitMask {
+        bits.into_iter().collect()
+    }
+}
+
+impl From<InitMask> for Vec<bool> {
+    fn from(mask: InitMask) -> Vec<bool> {
+        mask.iter().collect()
+    }
+}
+
+impl FromIterator<bool> for InitMask {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> InitMask {
+        let mut mask = InitMask::new(0);
+        mask.extend(iter);
+        mask
+    }
+}
+
+impl Extend<bool> for InitMask {
+    fn extend<I: IntoIterator<Item = bool>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for bit in iter {
+            self.push(bit);
+        }
+    }
+}
+
+impl BitAnd for &InitMask {
+    type Output = InitMask;
+
+    fn bitand(self, other: &InitMask) -> InitMask {
+        self.intersection(other)
+    }
+}
+
+impl BitOr for &InitMask {
+    type Output = InitMask;
+
+    fn bitor(self, other: &InitMask) -> InitMask {
+        self.union(other)
+    }
+}
+
+impl Not for &InitMask {
+    type Output = InitMask;
+
+    fn not(self) -> InitMask {
+        let mut mask = self.clone();
+        mask.invert();
+        mask
+    }
+}
+
+impl Not for InitMask {
+    type Output = InitMask;
+
+    fn not(mut self) -> InitMask {
+        self.invert();
+        self
+    }
+}
+
+impl BitAndAssign<&InitMask> for InitMask {
+    fn bitand_assign(&mut self, other: &InitMask) {
+        self.intersect_with(other);
+    }
+}
+
+impl BitOrAssign<&InitMask> for InitMask {
+    fn bitor_assign(&mut self, other: &InitMask) {
+        self.union_with(other);
+    }
+}
+
+impl fmt::Debug for InitMask {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "InitMask(")?;
+        for bit in self.iter() {
+            write!(f, "{}", if bit { '1' } else { '0' })?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// An iterator over the bits of an `InitMask`.
+pub struct Iter<'a> {
+    mask: &'a InitMask,
+    range: Range<usize>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = bool;
+
+    fn next(&mut self) -> Option<bool> {
+        self.range.next().map(|i| self.mask.is_set(i))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.range.size_hint()
+    }
+}
+
+/// An iterator over the indices of set or unset bits in an `InitMask`.
+pub struct Indices<'a> {
+    mask: &'a InitMask,
+    pos: usize,
+    value: bool,
+}
+
+impl<'a> Iterator for Indices<'a> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let i = self.mask.next_matching(self.pos, self.value)?;
+        self.pos = i + 1;
+        Some(i)
+    }
+}
+
+/// An iterator over maximal ranges of set or unset bits in an `InitMask`.
+pub struct Runs<'a> {
+    mask: &'a InitMask,
+    pos: usize,
+    value: bool,
+}
+
+impl<'a> Iterator for Runs<'a> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Range<usize>> {
+        let start = self.mask.next_matching(self.pos, self.value)?;
+        self.pos = self
+            .mask
+            .next_matching(start, !self.value)
+            .unwrap_or(self.mask.len);
+        Some(start..self.pos)
+    }
+}
@@ -0,0 +1,87 @@
+//! The summary level of an `InitMask`.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use super::{word_count, word_mask, WORD_BITS};
+
+// the positions of the two summary bit sets in each entry
+const FULL: usize = 0;
+const NONEMPTY: usize = 1;
+
+// two bits for every word of a mask, recording whether the word is full and whether it has
+// any bit set, so that searches can skip 64 words at a time
+// the bits are atomic so that a stale summary can be rebuilt through a shared reference
+#[derive(Default)]
+pub(super) struct Summary {
+    // the full and nonempty bits of 64 words at a time
+    entries: Vec<[AtomicU64; 2]>,
+    // set while the words may have been changed without updating the summary
+    stale: AtomicBool,
+}
+
+impl Summary {
+    // changes the number of words summarized, unsetting the bits of the words past the end
+    pub(super) fn resize(&mut self, words: usize) {
+        self.entries
+            .resize_with(word_count(words), || [AtomicU64::new(0), AtomicU64::new(0)]);
+        if !words.is_multiple_of(WORD_BITS) {
+            for bits in self.entries.last_mut().unwrap() {
+                *bits.get_mut() &= word_mask(0, words % WORD_BITS);
+            }
+        }
+    }
+
+    pub(super) fn update(&mut self, w: usize, full: bool, nonempty: bool) {
+        let bit = 1 << (w % WORD_BITS);
+        let entry = &mut self.entries[w / WORD_BITS];
+        for (bits, value) in entry.iter_mut().zip([full, nonempty]) {
+            let bits = bits.get_mut();
+            if value {
+                *bits |= bit;
+            } else {
+                *bits &= !bit;
+            }
+        }
+    }
+
+    // like `update`, but through a shared reference
+    // each bit is changed on its own, so a rebuild running on another thread never sees
+    // a bit go back to a stale value
+    pub(super) fn update_shared(&self, w: usize, full: bool, nonempty: bool) {
+        let bit = 1 << (w % WORD_BITS);
+        let entry = &self.entries[w / WORD_BITS];
+        for (bits, value) in entry.iter().zip([full, nonempty]) {
+            if value {
+                bits.fetch_or(bit, Ordering::Relaxed);
+            } else {
+                bits.fetch_and(!bit, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub(super) fn mark_stale(&mut self) {
+        *self.stale.get_mut() = true;
+    }
+
+    pub(super) fn is_stale(&self) -> bool {
+        self.stale.load(Ordering::Acquire)
+    }
+
+    pub(super) fn mark_fresh(&self) {
+        self.stale.store(false, Ordering::Release);
+    }
+
+    // the first word at or after `w` that can have a bit set to `value`
+    // when looking for unset bits, this can be past the last word
+    pub(super) fn next_word(&self, w: usize, value: bool) -> Option<usize> {
+        let (level, flip) = if value { (NONEMPTY, 0) } else { (FULL, !0) };
+        let load = |s: usize| Some(self.entries.get(s)?[level].load(Ordering::Relaxed) ^ flip);
+        let mut s = w / WORD_BITS;
+        let mut bits = load(s)? & !word_mask(0, w % WORD_BITS);
+        while bits == 0 {
+            s += 1;
+            bits = load(s)?;
+        }
+        Some(s * WORD_BITS + bits.trailing_zeros() as usize)
+    }
+}
@@ -5,24 +5,59 @@
 //! of rusts ownership rules.
 
 use std::boxed::Box;
+use std::cell::Cell;
+use std::error;
 use std::fmt;
 use std::marker::PhantomData;
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+mod os;
+mod path;
+mod slice;
+
+#[cfg(unix)]
+pub use self::os::MightOwnFd;
+#[cfg(windows)]
+pub use self::os::MightOwnHandle;
+pub use self::slice::MightOwnSlice;
 
 /// This acts as a box type that may or may not own the data it points to.
 /// The destructor for this type will check if the value is owned or not,
-/// and will decide whether to drop it. 
+/// and will decide whether to drop it.
 pub struct MightOwn<'a, T: ?Sized + 'a> {
     ptr: *mut T,
     owned: bool,
+    // only allocated once the value is watched
+    alive: Cell<Option<Arc<AtomicBool>>>,
     phantom: PhantomData<&'a T>,
 }
 
+/// A handle that can check whether the value of a `MightOwn` is still reachable through it.
+///
+/// Pointers derived from a `MightOwn` are only valid while it exists, whether or not the
+/// value is owned. A cache that keeps such pointers can keep a `Watch` next to them, and
+/// check it before using them.
+#[derive(Clone, Debug)]
+pub struct Watch {
+    alive: Arc<AtomicBool>,
+}
+
+impl Watch {
+    /// Returns true if the `MightOwn` this was created from still exists.
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::Acquire)
+    }
+}
+
 impl<'a, T: ?Sized + 'a> Drop for MightOwn<'a, T> {
     fn drop(&mut self) {
+        self.release_watches();
         if self.owned {
             unsafe {
                 // drops the pointer and the heap allocated memory allocated to it
-                Box::from_raw(self.ptr);
+                drop(Box::from_raw(self.ptr));
             }
         }
         // if the value is not owned, then the drop will be managed by is owner
@@ -39,6 +74,7 @@ impl<'a, T: ?Sized> MightOwn<'a, T> {
         MightOwn {
             ptr: Box::into_raw(val),
             owned: true,
+            alive: Cell::new(None),
             phantom: PhantomData,
         }
     }
@@ -48,10 +84,33 @@ impl<'a, T: ?Sized> MightOwn<'a, T> {
         MightOwn {
             ptr: val as *mut T,
             owned: false,
+            alive: Cell::new(None),
             phantom: PhantomData,
         }
     }
 
+    /// Returns true if the value will be dropped along with this.
+    pub fn is_owned(&self) -> bool {
+        self.owned
+    }
+
+    /// Creates a handle that reports whether this still exists.
+    pub fn watch(&self) -> Watch {
+        let alive = self
+            .alive
+            .take()
+            .unwrap_or_else(|| Arc::new(AtomicBool::new(true)));
+        self.alive.set(Some(alive.clone()));
+        Watch { alive }
+    }
+
+    // tells every watch that the value can no longer be reached through this
+    fn release_watches(&self) {
+        if let Some(alive) = self.alive.take() {
+            alive.store(false, Ordering::Release);
+        }
+    }
+
     /// Get a mutable reference to the data pointed to by this value.
     pub fn get_mut(&mut self) -> &'a mut T {
         unsafe { &mut *self.ptr }
@@ -66,7 +125,11 @@ impl<'a, T: ?Sized> MightOwn<'a, T> {
     /// If the value is not owned, then this will fail.
     pub fn get_owned(self) -> Result<Box<T>, NotOwnedError<'a, T>> {
         if self.owned {
-            unsafe { Ok(Box::from_raw(self.ptr)) }
+            let ptr = self.ptr;
+            self.release_watches();
+            // the box now owns the value, so it must not be dropped again
+            mem::forget(self);
+            unsafe { Ok(Box::from_raw(ptr)) }
         } else {
             Err(NotOwnedError { val: self })
         }
@@ -74,7 +137,7 @@ impl<'a, T: ?Sized> MightOwn<'a, T> {
 }
 
 /// An error type for MightOwn. This contains the object
-/// so it can be used after a failure. 
+/// so it can be used after a failure.
 pub struct NotOwnedError<'a, T: ?Sized + 'a> {
     val: MightOwn<'a, T>,
 }
@@ -94,4 +157,12 @@ impl<'a, T: ?Sized + 'a> fmt::Debug for NotOwnedError<'a, T> {
     }
 }
 
+impl<'a, T: ?Sized + 'a> fmt::Display for NotOwnedError<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "the value is not owned")
+    }
+}
+
+impl<'a, T: ?Sized + 'a> error::Error for NotOwnedError<'a, T> {}
+
 // TODO: Implement traits for MightOwn
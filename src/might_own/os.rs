@@ -0,0 +1,202 @@
+//! OS resources that may or may not be owned.
+//!
+//! These work like `MightOwn`, but for file descriptors and handles instead of heap
+//! memory. An owned resource is closed when it is dropped, while a borrowed one is left
+//! open for its owner to close.
+
+use std::fmt;
+use std::marker::PhantomData;
+use std::mem;
+
+#[cfg(unix)]
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+#[cfg(windows)]
+use std::os::windows::io::{
+    AsHandle, AsRawHandle, BorrowedHandle, FromRawHandle, IntoRawHandle, OwnedHandle, RawHandle,
+};
+
+/// A file descriptor that may or may not be owned. It is only closed on drop if it is owned.
+#[cfg(unix)]
+pub struct MightOwnFd<'a> {
+    fd: RawFd,
+    owned: bool,
+    phantom: PhantomData<BorrowedFd<'a>>,
+}
+
+#[cfg(unix)]
+impl<'a> Drop for MightOwnFd<'a> {
+    fn drop(&mut self) {
+        if self.owned {
+            // closes the descriptor
+            drop(unsafe { OwnedFd::from_raw_fd(self.fd) });
+        }
+    }
+}
+
+#[cfg(unix)]
+impl<'a> MightOwnFd<'a> {
+    /// Creates a descriptor that will be closed when this is dropped.
+    pub fn owned(fd: OwnedFd) -> MightOwnFd<'a> {
+        MightOwnFd {
+            fd: fd.into_raw_fd(),
+            owned: true,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates a descriptor that is borrowed from its owner and will not be closed.
+    pub fn unowned(fd: BorrowedFd<'a>) -> MightOwnFd<'a> {
+        MightOwnFd {
+            fd: fd.as_raw_fd(),
+            owned: false,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns true if the descriptor will be closed when this is dropped.
+    pub fn is_owned(&self) -> bool {
+        self.owned
+    }
+
+    /// Gets the owned descriptor. If it is borrowed, then this fails and returns self.
+    pub fn get_owned(self) -> Result<OwnedFd, MightOwnFd<'a>> {
+        if self.owned {
+            let fd = self.fd;
+            mem::forget(self);
+            Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+#[cfg(unix)]
+impl<'a> fmt::Debug for MightOwnFd<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MightOwnFd")
+            .field("fd", &self.fd)
+            .field("owned", &self.owned)
+            .finish()
+    }
+}
+
+#[cfg(unix)]
+impl<'a> AsFd for MightOwnFd<'a> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        unsafe { BorrowedFd::borrow_raw(self.fd) }
+    }
+}
+
+#[cfg(unix)]
+impl<'a> AsRawFd for MightOwnFd<'a> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+#[cfg(unix)]
+impl<'a> From<OwnedFd> for MightOwnFd<'a> {
+    fn from(fd: OwnedFd) -> MightOwnFd<'a> {
+        MightOwnFd::owned(fd)
+    }
+}
+
+#[cfg(unix)]
+impl<'a> From<BorrowedFd<'a>> for MightOwnFd<'a> {
+    fn from(fd: BorrowedFd<'a>) -> MightOwnFd<'a> {
+        MightOwnFd::unowned(fd)
+    }
+}
+
+/// A Windows handle that may or may not be owned. It is only closed on drop if it is owned.
+#[cfg(windows)]
+pub struct MightOwnHandle<'a> {
+    handle: RawHandle,
+    owned: bool,
+    phantom: PhantomData<BorrowedHandle<'a>>,
+}
+
+#[cfg(windows)]
+impl<'a> Drop for MightOwnHandle<'a> {
+    fn drop(&mut self) {
+        if self.owned {
+            // closes the handle
+            drop(unsafe { OwnedHandle::from_raw_handle(self.handle) });
+        }
+    }
+}
+
+#[cfg(windows)]
+impl<'a> MightOwnHandle<'a> {
+    /// Creates a handle that will be closed when this is dropped.
+    pub fn owned(handle: OwnedHandle) -> MightOwnHandle<'a> {
+        MightOwnHandle {
+            handle: handle.into_raw_handle(),
+            owned: true,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates a handle that is borrowed from its owner and will not be closed.
+    pub fn unowned(handle: BorrowedHandle<'a>) -> MightOwnHandle<'a> {
+        MightOwnHandle {
+            handle: handle.as_raw_handle(),
+            owned: false,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns true if the handle will be closed when this is dropped.
+    pub fn is_owned(&self) -> bool {
+        self.owned
+    }
+
+    /// Gets the owned handle. If it is borrowed, then this fails and returns self.
+    pub fn get_owned(self) -> Result<OwnedHandle, MightOwnHandle<'a>> {
+        if self.owned {
+            let handle = self.handle;
+            mem::forget(self);
+            Ok(unsafe { OwnedHandle::from_raw_handle(handle) })
+        } else {
+            Err(self)
+        }
+    }
+}
+
+#[cfg(windows)]
+impl<'a> fmt::Debug for MightOwnHandle<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MightOwnHandle")
+            .field("handle", &self.handle)
+            .field("owned", &self.owned)
+            .finish()
+    }
+}
+
+#[cfg(windows)]
+impl<'a> AsHandle for MightOwnHandle<'a> {
+    fn as_handle(&self) -> BorrowedHandle<'_> {
+        unsafe { BorrowedHandle::borrow_raw(self.handle) }
+    }
+}
+
+#[cfg(windows)]
+impl<'a> AsRawHandle for MightOwnHandle<'a> {
+    fn as_raw_handle(&self) -> RawHandle {
+        self.handle
+    }
+}
+
+#[cfg(windows)]
+impl<'a> From<OwnedHandle> for MightOwnHandle<'a> {
+    fn from(handle: OwnedHandle) -> MightOwnHandle<'a> {
+        MightOwnHandle::owned(handle)
+    }
+}
+
+#[cfg(windows)]
+impl<'a> From<BorrowedHandle<'a>> for MightOwnHandle<'a> {
+    fn from(handle: BorrowedHandle<'a>) -> MightOwnHandle<'a> {
+        MightOwnHandle::unowned(handle)
+    }
+}
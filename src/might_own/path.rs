@@ -0,0 +1,79 @@
+//! Maybe-owned paths and OS strings, for filesystem APIs that sometimes need to take
+//! ownership of a path and sometimes only borrow it.
+
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
+
+use super::MightOwn;
+
+impl<'a> From<PathBuf> for MightOwn<'a, Path> {
+    fn from(path: PathBuf) -> MightOwn<'a, Path> {
+        MightOwn::owned(path.into_boxed_path())
+    }
+}
+
+impl<'a> From<&'a mut Path> for MightOwn<'a, Path> {
+    fn from(path: &'a mut Path) -> MightOwn<'a, Path> {
+        MightOwn::unowned(path)
+    }
+}
+
+impl<'a> MightOwn<'a, Path> {
+    /// Gets the path.
+    pub fn as_path(&self) -> &Path {
+        self.get_const()
+    }
+
+    /// Converts into a `PathBuf`. This only copies the path if it is not owned.
+    pub fn into_path_buf(self) -> PathBuf {
+        match self.get_owned() {
+            Ok(path) => path.into_path_buf(),
+            Err(err) => err.get().as_path().to_path_buf(),
+        }
+    }
+}
+
+impl<'a> AsRef<Path> for MightOwn<'a, Path> {
+    fn as_ref(&self) -> &Path {
+        self.as_path()
+    }
+}
+
+impl<'a> From<OsString> for MightOwn<'a, OsStr> {
+    fn from(string: OsString) -> MightOwn<'a, OsStr> {
+        MightOwn::owned(string.into_boxed_os_str())
+    }
+}
+
+impl<'a> From<&'a mut OsStr> for MightOwn<'a, OsStr> {
+    fn from(string: &'a mut OsStr) -> MightOwn<'a, OsStr> {
+        MightOwn::unowned(string)
+    }
+}
+
+impl<'a> MightOwn<'a, OsStr> {
+    /// Gets the string.
+    pub fn as_os_str(&self) -> &OsStr {
+        self.get_const()
+    }
+
+    /// Converts into an `OsString`. This only copies the string if it is not owned.
+    pub fn into_os_string(self) -> OsString {
+        match self.get_owned() {
+            Ok(string) => string.into_os_string(),
+            Err(err) => err.get().as_os_str().to_os_string(),
+        }
+    }
+}
+
+impl<'a> AsRef<OsStr> for MightOwn<'a, OsStr> {
+    fn as_ref(&self) -> &OsStr {
+        self.as_os_str()
+    }
+}
+
+impl<'a> AsRef<Path> for MightOwn<'a, OsStr> {
+    fn as_ref(&self) -> &Path {
+        Path::new(self.as_os_str())
+    }
+}
@@ -0,0 +1,181 @@
+//! A logical buffer made of segments that are each either owned or borrowed.
+//!
+//! This is meant for zero-copy parsers, which mostly refer to the input, but sometimes
+//! need to replace part of it with data they allocated themselves.
+
+use std::cmp;
+use std::fmt;
+use std::mem;
+use std::ops::Range;
+
+use super::MightOwn;
+
+/// A buffer whose subranges may or may not be owned.
+///
+/// Indexing works across segments as if they were one slice. When the buffer is dropped,
+/// only the owned segments are dropped.
+pub struct MightOwnSlice<'a, T: 'a> {
+    segments: Vec<MightOwn<'a, [T]>>,
+    // the end of each segment in the logical buffer
+    ends: Vec<usize>,
+}
+
+impl<'a, T: 'a> MightOwnSlice<'a, T> {
+    /// Creates an empty buffer.
+    pub fn new() -> MightOwnSlice<'a, T> {
+        MightOwnSlice {
+            segments: Vec::new(),
+            ends: Vec::new(),
+        }
+    }
+
+    /// The total length of every segment.
+    pub fn len(&self) -> usize {
+        self.ends.last().cloned().unwrap_or(0)
+    }
+
+    /// Returns true if the buffer has a length of 0.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Appends an owned segment.
+    pub fn push_owned(&mut self, vals: Vec<T>) {
+        self.push(MightOwn::owned(vals.into_boxed_slice()));
+    }
+
+    /// Appends a segment that is borrowed from the caller.
+    pub fn push_borrowed(&mut self, vals: &'a mut [T]) {
+        self.push(MightOwn::unowned(vals));
+    }
+
+    /// Gets a reference to the value at `i`, or none if it is out of bounds.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        let (segment, offset) = self.locate(i)?;
+        Some(&self.segments[segment].get_const()[offset])
+    }
+
+    /// Gets a mutable reference to the value at `i`, or none if it is out of bounds.
+    pub fn get_mut(&mut self, i: usize) -> Option<&mut T> {
+        let (segment, offset) = self.locate(i)?;
+        Some(&mut self.segments[segment].get_mut()[offset])
+    }
+
+    /// Returns true if the value at `i` is in an owned segment.
+    pub fn is_owned(&self, i: usize) -> Option<bool> {
+        let (segment, _) = self.locate(i)?;
+        Some(self.segments[segment].is_owned())
+    }
+
+    /// Iterates over the segments, along with whether each one is owned.
+    pub fn segments(&self) -> impl Iterator<Item = (&[T], bool)> {
+        self.segments
+            .iter()
+            .map(|segment| (segment.get_const(), segment.is_owned()))
+    }
+
+    /// Iterates over every value in order.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.segments.iter().flat_map(|segment| segment.get_const())
+    }
+
+    /// Replaces a range of the buffer with an owned segment, which may have a different
+    /// length.
+    ///
+    /// Borrowed segments that are partly replaced are split without copying. Owned
+    /// segments that are partly replaced are split into new allocations.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn patch(&mut self, range: Range<usize>, replacement: Vec<T>) {
+        assert!(
+            range.start <= range.end && range.end <= self.len(),
+            "patch range {:?} is out of bounds for a buffer of length {}",
+            range,
+            self.len()
+        );
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+        let mut pos = 0;
+        for segment in mem::take(&mut self.segments) {
+            let start = pos;
+            pos += segment.get_const().len();
+            if pos <= range.start {
+                before.push(segment);
+            } else if start >= range.end {
+                after.push(segment);
+            } else {
+                let head_len = range.start.saturating_sub(start);
+                let (head, rest) = split(segment, head_len);
+                let replaced = cmp::min(range.end, pos) - cmp::max(range.start, start);
+                // the replaced part is dropped here if it is owned
+                let (_, tail) = split(rest, replaced);
+                before.push(head);
+                after.push(tail);
+            }
+        }
+        self.ends.clear();
+        for segment in before {
+            self.push(segment);
+        }
+        self.push_owned(replacement);
+        for segment in after {
+            self.push(segment);
+        }
+    }
+
+    fn push(&mut self, segment: MightOwn<'a, [T]>) {
+        let len = segment.get_const().len();
+        if len > 0 {
+            let end = self.len() + len;
+            self.segments.push(segment);
+            self.ends.push(end);
+        }
+    }
+
+    // finds the segment that contains an index, and the offset within that segment
+    fn locate(&self, i: usize) -> Option<(usize, usize)> {
+        let segment = self.ends.partition_point(|&end| end <= i);
+        if segment == self.segments.len() {
+            return None;
+        }
+        let start = if segment == 0 {
+            0
+        } else {
+            self.ends[segment - 1]
+        };
+        Some((segment, i - start))
+    }
+}
+
+// splits a segment in two, keeping the ownership of each half
+fn split<'a, T: 'a>(
+    segment: MightOwn<'a, [T]>,
+    at: usize,
+) -> (MightOwn<'a, [T]>, MightOwn<'a, [T]>) {
+    match segment.get_owned() {
+        Ok(vals) => {
+            let mut head = vals.into_vec();
+            let tail = head.split_off(at);
+            (
+                MightOwn::owned(head.into_boxed_slice()),
+                MightOwn::owned(tail.into_boxed_slice()),
+            )
+        }
+        Err(err) => {
+            let (head, tail) = err.get().get_mut().split_at_mut(at);
+            (MightOwn::unowned(head), MightOwn::unowned(tail))
+        }
+    }
+}
+
+impl<'a, T: 'a> Default for MightOwnSlice<'a, T> {
+    fn default() -> MightOwnSlice<'a, T> {
+        MightOwnSlice::new()
+    }
+}
+
+impl<'a, T: fmt::Debug + 'a> fmt::Debug for MightOwnSlice<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
@@ -0,0 +1,167 @@
+//! An array of write-once slots that can be filled from several threads.
+//!
+//! Each slot of a `OnceArray` works like a `OnceCell`: it can be set exactly once, and
+//! after that it can be read without locking. Which slots have been set is tracked with
+//! an `AtomicInitMask`.
+
+use std::cell::UnsafeCell;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::atomic::Ordering;
+
+use mask::{AtomicInitMask, Indices, InitMask};
+
+/// A fixed size array of slots that can each be initialized once, from any thread.
+pub struct OnceArray<T> {
+    vals: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    // slots that a writer has started writing to
+    claimed: AtomicInitMask,
+    // slots whose values have been written and can be read
+    ready: AtomicInitMask,
+}
+
+// a slot is only written by the thread that claimed it, and only read once it is ready
+unsafe impl<T: Send + Sync> Sync for OnceArray<T> {}
+unsafe impl<T: Send> Send for OnceArray<T> {}
+
+impl<T> Drop for OnceArray<T> {
+    fn drop(&mut self) {
+        for i in 0..self.vals.len() {
+            if self.ready.is_set(i, Ordering::Relaxed) {
+                unsafe {
+                    ptr::drop_in_place(self.vals[i].get_mut().as_mut_ptr());
+                }
+            }
+        }
+    }
+}
+
+impl<T> OnceArray<T> {
+    /// Creates an array of `len` uninitialized slots.
+    pub fn new(len: usize) -> OnceArray<T> {
+        OnceArray {
+            vals: (0..len)
+                .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+                .collect(),
+            claimed: AtomicInitMask::new(len),
+            ready: AtomicInitMask::new(len),
+        }
+    }
+
+    /// The number of slots.
+    pub fn len(&self) -> usize {
+        self.vals.len()
+    }
+
+    /// Returns true if there are no slots.
+    pub fn is_empty(&self) -> bool {
+        self.vals.is_empty()
+    }
+
+    /// Initializes slot `i`. If the slot has already been set, or another thread is
+    /// setting it, the value is given back.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn set(&self, i: usize, val: T) -> Result<(), T> {
+        if self.claimed.set(i, Ordering::Acquire) {
+            return Err(val);
+        }
+        unsafe {
+            (*self.vals[i].get()).as_mut_ptr().write(val);
+        }
+        self.ready.set(i, Ordering::Release);
+        Ok(())
+    }
+
+    /// Gets a reference to the value in slot `i`, or none if it has not been set yet.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        if self.ready.is_set(i, Ordering::Acquire) {
+            unsafe { Some(&*(*self.vals[i].get()).as_ptr()) }
+        } else {
+            None
+        }
+    }
+
+    /// Returns true if slot `i` has been set.
+    pub fn is_set(&self, i: usize) -> bool {
+        self.ready.is_set(i, Ordering::Acquire)
+    }
+
+    /// The number of slots that have been set.
+    pub fn count_set(&self) -> usize {
+        self.ready.count_ones(Ordering::Acquire)
+    }
+
+    /// Takes a snapshot of which slots are set. Slots that are set afterwards are not
+    /// visible through the snapshot, so readers see a consistent set of values while
+    /// writers keep filling the array.
+    pub fn read_snapshot(&self) -> ReadSnapshot<'_, T> {
+        ReadSnapshot {
+            array: self,
+            mask: self.ready.load(Ordering::Acquire),
+        }
+    }
+
+    /// Moves the value out of slot `i`, so that it can be set again.
+    pub fn take(&mut self, i: usize) -> Option<T> {
+        if self.ready.unset(i, Ordering::Relaxed) {
+            self.claimed.unset(i, Ordering::Relaxed);
+            unsafe { Some(self.vals[i].get_mut().as_ptr().read()) }
+        } else {
+            None
+        }
+    }
+}
+
+/// The slots of a `OnceArray` that were set when the snapshot was taken.
+pub struct ReadSnapshot<'a, T: 'a> {
+    array: &'a OnceArray<T>,
+    mask: InitMask,
+}
+
+impl<'a, T> ReadSnapshot<'a, T> {
+    /// The slots that were set.
+    pub fn mask(&self) -> &InitMask {
+        &self.mask
+    }
+
+    /// The number of slots that were set.
+    pub fn count_set(&self) -> usize {
+        self.mask.count_ones()
+    }
+
+    /// Gets the value in slot `i`, or none if it was not set when the snapshot was taken.
+    pub fn get(&self, i: usize) -> Option<&'a T> {
+        if self.mask.is_set(i) {
+            // values are never removed while the array is shared
+            unsafe { Some(&*(*self.array.vals[i].get()).as_ptr()) }
+        } else {
+            None
+        }
+    }
+
+    /// Iterates over the slots that were set, along with their indices.
+    pub fn iter(&self) -> SnapshotIter<'_, 'a, T> {
+        SnapshotIter {
+            snapshot: self,
+            ones: self.mask.ones(),
+        }
+    }
+}
+
+/// An iterator over the values in a `ReadSnapshot`.
+pub struct SnapshotIter<'s, 'a: 's, T: 'a> {
+    snapshot: &'s ReadSnapshot<'a, T>,
+    ones: Indices<'s>,
+}
+
+impl<'s, 'a, T> Iterator for SnapshotIter<'s, 'a, T> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<(usize, &'a T)> {
+        let i = self.ones.next()?;
+        self.snapshot.get(i).map(|val| (i, val))
+    }
+}
@@ -0,0 +1,89 @@
+//! A container that drops its values in a chosen order.
+//!
+//! Some resources have to be torn down in a strict order, for example a device that must
+//! outlive every buffer created from it. Rust drops struct fields and vector elements in a
+//! fixed order, which is easy to break by accident. `OrderedDrops` instead drops its
+//! values by an explicit priority.
+
+use std::mem;
+
+use error::{Error, Result};
+use uninitialized::SafeUninitializedVec;
+
+/// Fixed capacity storage whose values are dropped in order of priority.
+///
+/// Values with a lower priority are dropped first. Values with the same priority are
+/// dropped in the order they were registered.
+pub struct OrderedDrops<T> {
+    slots: SafeUninitializedVec<T>,
+    // the priority of every registered slot, in registration order
+    priorities: Vec<(u64, usize)>,
+}
+
+impl<T> Drop for OrderedDrops<T> {
+    fn drop(&mut self) {
+        let mut priorities = mem::take(&mut self.priorities);
+        // the sort is stable, so ties stay in registration order
+        priorities.sort_by_key(|&(priority, _)| priority);
+        for (_, key) in priorities {
+            drop(self.slots.take(key));
+        }
+        debug_assert_eq!(self.slots.count_initialized(), 0);
+    }
+}
+
+impl<T> OrderedDrops<T> {
+    /// Creates a container that can hold up to `capacity` values.
+    pub fn with_capacity(capacity: usize) -> OrderedDrops<T> {
+        OrderedDrops {
+            slots: SafeUninitializedVec::new(capacity),
+            priorities: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// The maximum number of values that can be registered.
+    pub fn capacity(&self) -> usize {
+        self.slots.mask().len()
+    }
+
+    /// The number of values that are still held.
+    pub fn len(&self) -> usize {
+        self.slots.count_initialized()
+    }
+
+    /// Returns true if no values are held.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Registers a value to be dropped with the given priority, returning its key.
+    ///
+    /// Keys are never reused, so this fails once `capacity` values have been registered.
+    pub fn register(&mut self, priority: u64, val: T) -> Result<usize> {
+        let key = self.priorities.len();
+        if key == self.capacity() {
+            return Err(Error::BudgetExceeded {
+                requested: 1,
+                available: 0,
+            });
+        }
+        self.slots.set_value(key, val);
+        self.priorities.push((priority, key));
+        Ok(key)
+    }
+
+    /// Gets a reference to a value, or none if it has been removed.
+    pub fn get(&self, key: usize) -> Option<&T> {
+        self.slots.get_value(key)
+    }
+
+    /// Gets a mutable reference to a value, or none if it has been removed.
+    pub fn get_mut(&mut self, key: usize) -> Option<&mut T> {
+        self.slots.get_value_mut(key)
+    }
+
+    /// Removes a value, so it is no longer dropped with the others.
+    pub fn remove(&mut self, key: usize) -> Option<T> {
+        self.slots.take(key)
+    }
+}
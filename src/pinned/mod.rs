@@ -0,0 +1,237 @@
+//! A byte buffer with a stable address whose uninitialized regions can be lent out.
+//!
+//! This is designed for completion based I/O (such as io_uring or overlapped I/O), where
+//! a region of memory is handed to the kernel and only written to later. A region is
+//! leased out as a raw pointer and length, and once the operation completes, the lease
+//! is committed with the number of bytes that were actually written.
+
+use std::error;
+use std::fmt;
+use std::mem::{self, MaybeUninit};
+use std::ops::Range;
+use std::slice;
+
+use mask::InitMask;
+
+/// A fixed size byte buffer that never moves and tracks which bytes are initialized.
+pub struct PinnedBytes {
+    buf: Box<[MaybeUninit<u8>]>,
+    initialized: InitMask,
+    leased: InitMask,
+    leases: usize,
+}
+
+/// A region of a `PinnedBytes` buffer that has been lent out.
+///
+/// The region stays reserved until the lease is committed or cancelled. If the buffer
+/// is dropped while leases are still outstanding, its memory is leaked instead of freed,
+/// since it may still be written to.
+#[must_use]
+pub struct Lease {
+    ptr: *mut u8,
+    range: Range<usize>,
+}
+
+// the lease only holds a pointer into memory that is reserved for it
+unsafe impl Send for Lease {}
+
+/// The error returned when a region can not be leased.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LeaseError {
+    /// The region is outside of the buffer.
+    OutOfBounds,
+    /// Part of the region is already initialized.
+    Initialized,
+    /// Part of the region is already leased.
+    Leased,
+}
+
+impl Drop for PinnedBytes {
+    fn drop(&mut self) {
+        if self.leases > 0 {
+            // something may still write into the buffer, so it must never be freed
+            mem::forget(mem::take(&mut self.buf));
+        }
+    }
+}
+
+impl PinnedBytes {
+    /// Allocates a buffer of `len` uninitialized bytes.
+    pub fn new(len: usize) -> PinnedBytes {
+        let mut buf = Vec::with_capacity(len);
+        buf.resize(len, MaybeUninit::uninit());
+        PinnedBytes {
+            buf: buf.into_boxed_slice(),
+            initialized: InitMask::new(len),
+            leased: InitMask::new(len),
+            leases: 0,
+        }
+    }
+
+    /// The number of bytes in the buffer.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns true if the buffer has no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    /// A pointer to the start of the buffer. This never changes.
+    pub fn as_ptr(&self) -> *const u8 {
+        self.buf.as_ptr() as *const u8
+    }
+
+    /// Gets the mask of which bytes are initialized.
+    pub fn mask(&self) -> &InitMask {
+        &self.initialized
+    }
+
+    /// The number of leases that have not been committed or cancelled.
+    pub fn outstanding_leases(&self) -> usize {
+        self.leases
+    }
+
+    /// Lends out a region of uninitialized bytes.
+    pub fn lease(&mut self, range: Range<usize>) -> Result<Lease, LeaseError> {
+        if range.start > range.end || range.end > self.len() {
+            return Err(LeaseError::OutOfBounds);
+        }
+        if any_set(&self.leased, &range) {
+            return Err(LeaseError::Leased);
+        }
+        if any_set(&self.initialized, &range) {
+            return Err(LeaseError::Initialized);
+        }
+        self.leased.set_range(range.clone(), true);
+        self.leases += 1;
+        Ok(Lease {
+            ptr: unsafe { (self.buf.as_mut_ptr() as *mut u8).add(range.start) },
+            range,
+        })
+    }
+
+    /// Lends out the first region of uninitialized bytes that is not leased,
+    /// up to `max_len` bytes long.
+    pub fn lease_next(&mut self, max_len: usize) -> Option<Lease> {
+        // skip forward until a byte is neither initialized nor leased
+        let mut start = 0;
+        loop {
+            start = self.initialized.next_zero(start)?;
+            let free = self.leased.next_zero(start)?;
+            if free == start {
+                break;
+            }
+            start = free;
+        }
+        let mut end = start.saturating_add(max_len).min(self.len());
+        for mask in &[&self.initialized, &self.leased] {
+            end = mask.next_one(start).map_or(end, |i| end.min(i));
+        }
+        self.lease(start..end).ok()
+    }
+
+    /// Ends a lease, marking the first `written` bytes of its region as initialized.
+    ///
+    /// Panics if the lease was not created by this buffer or if `written` is larger
+    /// than the leased region.
+    pub fn commit(&mut self, lease: Lease, written: usize) {
+        assert!(written <= lease.len(), "more bytes written than leased");
+        let start = lease.range.start;
+        self.release(&lease);
+        self.initialized.set_range(start..start + written, true);
+    }
+
+    /// Ends a lease without initializing any bytes.
+    ///
+    /// Panics if the lease was not created by this buffer.
+    pub fn cancel(&mut self, lease: Lease) {
+        self.release(&lease);
+    }
+
+    /// Gets a range of bytes, if they are all initialized.
+    pub fn get(&self, range: Range<usize>) -> Option<&[u8]> {
+        if range.start > range.end || range.end > self.len() {
+            return None;
+        }
+        if self
+            .initialized
+            .next_zero(range.start)
+            .is_some_and(|i| i < range.end)
+        {
+            return None;
+        }
+        unsafe {
+            Some(slice::from_raw_parts(
+                self.as_ptr().add(range.start),
+                range.end - range.start,
+            ))
+        }
+    }
+
+    /// Marks a range of bytes as uninitialized so it can be leased again.
+    ///
+    /// Leased bytes in the range are not affected.
+    pub fn discard(&mut self, range: Range<usize>) {
+        let mut discarded = InitMask::new(self.len());
+        discarded.set_range(range, true);
+        discarded.difference_with(&self.leased);
+        self.initialized.difference_with(&discarded);
+    }
+
+    fn release(&mut self, lease: &Lease) {
+        let base = self.buf.as_mut_ptr() as *mut u8;
+        assert!(
+            lease.range.end <= self.len() && lease.ptr == base.wrapping_add(lease.range.start),
+            "lease does not belong to this buffer"
+        );
+        self.leased.set_range(lease.range.clone(), false);
+        self.leases -= 1;
+    }
+}
+
+// returns true if any bit in `range` is set
+fn any_set(mask: &InitMask, range: &Range<usize>) -> bool {
+    mask.next_one(range.start).is_some_and(|i| i < range.end)
+}
+
+impl Lease {
+    /// A pointer to the start of the leased region.
+    pub fn as_mut_ptr(&self) -> *mut u8 {
+        self.ptr
+    }
+
+    /// The number of bytes in the leased region.
+    pub fn len(&self) -> usize {
+        self.range.end - self.range.start
+    }
+
+    /// Returns true if the leased region is empty.
+    pub fn is_empty(&self) -> bool {
+        self.range.start == self.range.end
+    }
+
+    /// The position of the leased region in the buffer.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+}
+
+impl fmt::Debug for Lease {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Lease({:?})", self.range)
+    }
+}
+
+impl fmt::Display for LeaseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LeaseError::OutOfBounds => write!(f, "leased region is out of bounds"),
+            LeaseError::Initialized => write!(f, "leased region is already initialized"),
+            LeaseError::Leased => write!(f, "leased region is already leased"),
+        }
+    }
+}
+
+impl error::Error for LeaseError {}
@@ -0,0 +1,145 @@
+//! A pool of fixed size blocks, tracked with an occupancy bitmap.
+//!
+//! A `BlockPool` allocates all of its blocks up front. Acquiring a block moves a value into
+//! a free block and returns a guard for it, and dropping the guard drops the value and
+//! frees the block. This works like an allocator for a single type, with the same tracking
+//! as a `SafeUninitializedVec`.
+
+use std::cell::UnsafeCell;
+use std::fmt;
+use std::mem::{self, MaybeUninit};
+use std::ops::{Deref, DerefMut};
+use std::ptr;
+use std::sync::atomic::Ordering;
+use std::thread;
+
+use mask::AtomicInitMask;
+
+/// A fixed number of blocks that each hold one value.
+///
+/// Blocks can be acquired from several threads at once. Blocks whose guards were leaked
+/// are still dropped with the pool, but dropping a pool with leaked blocks panics, so the
+/// leak is noticed.
+pub struct BlockPool<T> {
+    blocks: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    occupied: AtomicInitMask,
+}
+
+// a block is only accessed through the guard of the thread that acquired it
+unsafe impl<T: Send> Sync for BlockPool<T> {}
+unsafe impl<T: Send> Send for BlockPool<T> {}
+
+impl<T> Drop for BlockPool<T> {
+    fn drop(&mut self) {
+        let mut leaked = 0;
+        for i in 0..self.blocks.len() {
+            if self.occupied.unset(i, Ordering::Acquire) {
+                leaked += 1;
+                unsafe {
+                    ptr::drop_in_place(self.blocks[i].get_mut().as_mut_ptr());
+                }
+            }
+        }
+        if !thread::panicking() {
+            assert_eq!(leaked, 0, "{} blocks were leaked from a block pool", leaked);
+        }
+    }
+}
+
+impl<T> BlockPool<T> {
+    /// Creates a pool of `len` free blocks.
+    pub fn new(len: usize) -> BlockPool<T> {
+        BlockPool {
+            blocks: (0..len)
+                .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+                .collect(),
+            occupied: AtomicInitMask::new(len),
+        }
+    }
+
+    /// The number of blocks in the pool.
+    pub fn capacity(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// The number of blocks that are in use.
+    pub fn in_use(&self) -> usize {
+        self.occupied.count_ones(Ordering::Relaxed)
+    }
+
+    /// Moves a value into a free block. If every block is in use, the value is given back.
+    pub fn acquire(&self, val: T) -> Result<Block<'_, T>, T> {
+        match self.occupied.claim_first_unset(Ordering::Acquire) {
+            Some(index) => {
+                let ptr = self.blocks[index].get() as *mut T;
+                unsafe {
+                    ptr.write(val);
+                }
+                Ok(Block {
+                    pool: self,
+                    index,
+                    ptr,
+                })
+            }
+            None => Err(val),
+        }
+    }
+
+    /// Moves the value out of a block and frees the block.
+    pub fn release(block: Block<'_, T>) -> T {
+        let val = unsafe { block.ptr.read() };
+        block.pool.occupied.unset(block.index, Ordering::Release);
+        mem::forget(block);
+        val
+    }
+}
+
+/// A block that has been acquired from a `BlockPool`. The value is dropped and the block
+/// is freed when this is dropped.
+pub struct Block<'a, T: 'a> {
+    pool: &'a BlockPool<T>,
+    index: usize,
+    ptr: *mut T,
+}
+
+unsafe impl<'a, T: Send> Send for Block<'a, T> {}
+unsafe impl<'a, T: Sync> Sync for Block<'a, T> {}
+
+impl<'a, T> Block<'a, T> {
+    /// The index of the block in the pool.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<'a, T> Drop for Block<'a, T> {
+    fn drop(&mut self) {
+        unsafe {
+            ptr::drop_in_place(self.ptr);
+        }
+        self.pool.occupied.unset(self.index, Ordering::Release);
+    }
+}
+
+impl<'a, T> Deref for Block<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<'a, T> DerefMut for Block<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.ptr }
+    }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for Block<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Block")
+            .field("index", &self.index)
+            .field("value", &**self)
+            .finish()
+    }
+}
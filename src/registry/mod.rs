@@ -0,0 +1,242 @@
+//! A global registry of live tracked vectors, for inspecting memory at runtime.
+//!
+//! With the `registry` feature enabled, every `SafeUninitializedVec` registers itself
+//! when it is created and removes itself when it is dropped. This includes the vectors
+//! that other containers in this crate are built on. `report` takes a snapshot of every
+//! registered vector, which can be written out as JSON.
+//!
+//! Registering takes a global lock whenever a vector is created or dropped, so this is
+//! meant for debugging and monitoring rather than for hot paths.
+//!
+//! Vectors can be tagged with a region, such as "render" or "net", to attribute memory to
+//! the subsystems of an application. A vector is tagged either directly with
+//! `set_region`, or by creating it inside `in_region`, which also tags the vectors that
+//! other containers create internally. `region_totals` sums up every region.
+
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+static LIVE: Mutex<BTreeMap<usize, Arc<Stats>>> = Mutex::new(BTreeMap::new());
+
+thread_local! {
+    // the region given to vectors created on this thread
+    static CURRENT_REGION: Cell<Option<&'static str>> = const { Cell::new(None) };
+}
+
+struct Stats {
+    type_name: &'static str,
+    elem_size: usize,
+    len: AtomicUsize,
+    initialized: AtomicUsize,
+    region: Mutex<Option<&'static str>>,
+}
+
+// the registration of one vector, which is removed when it is dropped
+pub(crate) struct Registration {
+    id: usize,
+    stats: Arc<Stats>,
+}
+
+impl Registration {
+    pub(crate) fn new<T>() -> Registration {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let stats = Arc::new(Stats {
+            type_name: ::std::any::type_name::<T>(),
+            elem_size: ::std::mem::size_of::<T>(),
+            len: AtomicUsize::new(0),
+            initialized: AtomicUsize::new(0),
+            region: Mutex::new(CURRENT_REGION.with(Cell::get)),
+        });
+        lock().insert(id, stats.clone());
+        Registration { id, stats }
+    }
+
+    pub(crate) fn tracked(&self, len: usize) {
+        self.stats.len.fetch_add(len, Ordering::Relaxed);
+    }
+
+    pub(crate) fn untracked(&self, len: usize) {
+        self.stats.len.fetch_sub(len, Ordering::Relaxed);
+    }
+
+    pub(crate) fn initialized(&self, count: usize) {
+        self.stats.initialized.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub(crate) fn deinitialized(&self, count: usize) {
+        self.stats.initialized.fetch_sub(count, Ordering::Relaxed);
+    }
+
+    pub(crate) fn region(&self) -> Option<&'static str> {
+        *self.stats.region()
+    }
+
+    pub(crate) fn set_region(&self, region: &'static str) {
+        *self.stats.region() = Some(region);
+    }
+}
+
+impl Stats {
+    fn region(&self) -> ::std::sync::MutexGuard<'_, Option<&'static str>> {
+        self.region.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// Runs `f`, tagging every vector created by it on this thread with `region`.
+pub fn in_region<R, F: FnOnce() -> R>(region: &'static str, f: F) -> R {
+    struct Restore(Option<&'static str>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CURRENT_REGION.with(|current| current.set(self.0));
+        }
+    }
+
+    let _restore = Restore(CURRENT_REGION.with(|current| current.replace(Some(region))));
+    f()
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        lock().remove(&self.id);
+    }
+}
+
+fn lock() -> ::std::sync::MutexGuard<'static, BTreeMap<usize, Arc<Stats>>> {
+    LIVE.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// The state of one live vector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContainerReport {
+    /// The type of the values in the vector.
+    pub type_name: &'static str,
+    /// The number of tracked slots.
+    pub len: usize,
+    /// The number of initialized slots.
+    pub initialized: usize,
+    /// The size of the tracked slots in bytes.
+    pub bytes: usize,
+    /// The region the vector is tagged with.
+    pub region: Option<&'static str>,
+}
+
+/// The totals of every vector tagged with one region.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegionReport {
+    /// The region, or none for vectors that are not tagged.
+    pub region: Option<&'static str>,
+    /// The number of live vectors.
+    pub containers: usize,
+    /// The number of tracked slots.
+    pub len: usize,
+    /// The number of initialized slots.
+    pub initialized: usize,
+    /// The size of the tracked slots in bytes.
+    pub bytes: usize,
+}
+
+/// A snapshot of every live vector, in the order they were created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// The live vectors.
+    pub containers: Vec<ContainerReport>,
+}
+
+impl Report {
+    /// The size of the tracked slots of every vector in bytes.
+    pub fn total_bytes(&self) -> usize {
+        self.containers.iter().map(|c| c.bytes).sum()
+    }
+
+    /// Writes the report as a JSON object.
+    pub fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        write!(
+            out,
+            "{{\"total_bytes\":{},\"containers\":[",
+            self.total_bytes()
+        )?;
+        for (i, c) in self.containers.iter().enumerate() {
+            if i > 0 {
+                write!(out, ",")?;
+            }
+            write!(out, "{{\"type\":")?;
+            write_json_string(out, c.type_name)?;
+            write!(
+                out,
+                ",\"len\":{},\"initialized\":{},\"bytes\":{},\"region\":",
+                c.len, c.initialized, c.bytes
+            )?;
+            match c.region {
+                Some(region) => write_json_string(out, region)?,
+                None => write!(out, "null")?,
+            }
+            write!(out, "}}")?;
+        }
+        write!(out, "]}}")
+    }
+
+    /// Gets the report as a JSON string.
+    pub fn to_json(&self) -> String {
+        let mut out = Vec::new();
+        self.write_json(&mut out)
+            .expect("writing to a Vec does not fail");
+        String::from_utf8(out).expect("the report is valid UTF-8")
+    }
+}
+
+fn write_json_string<W: Write>(out: &mut W, s: &str) -> io::Result<()> {
+    write!(out, "\"")?;
+    for ch in s.chars() {
+        match ch {
+            '"' => write!(out, "\\\"")?,
+            '\\' => write!(out, "\\\\")?,
+            ch if (ch as u32) < 0x20 => write!(out, "\\u{:04x}", ch as u32)?,
+            _ => write!(out, "{}", ch)?,
+        }
+    }
+    write!(out, "\"")
+}
+
+/// Takes a snapshot of every live tracked vector.
+pub fn report() -> Report {
+    let containers = lock()
+        .values()
+        .map(|stats| {
+            let len = stats.len.load(Ordering::Relaxed);
+            ContainerReport {
+                type_name: stats.type_name,
+                len,
+                initialized: stats.initialized.load(Ordering::Relaxed),
+                bytes: len * stats.elem_size,
+                region: *stats.region(),
+            }
+        })
+        .collect();
+    Report { containers }
+}
+
+/// Sums up the live vectors of every region, in order of region name. Vectors that are
+/// not tagged are summed up first.
+pub fn region_totals() -> Vec<RegionReport> {
+    let mut totals: BTreeMap<Option<&'static str>, RegionReport> = BTreeMap::new();
+    for c in report().containers {
+        let total = totals.entry(c.region).or_insert(RegionReport {
+            region: c.region,
+            containers: 0,
+            len: 0,
+            initialized: 0,
+            bytes: 0,
+        });
+        total.containers += 1;
+        total.len += c.len;
+        total.initialized += c.initialized;
+        total.bytes += c.bytes;
+    }
+    totals.into_values().collect()
+}
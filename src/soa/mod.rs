@@ -0,0 +1,120 @@
+//! Struct-of-arrays storage where every field has its own initialization tracking.
+//!
+//! The `tracked_soa!` macro takes a struct definition and derives a column container
+//! for it. Each field of the struct is stored in its own `SafeUninitializedVec`, so fields
+//! can be initialized separately, and a single column can be iterated without touching
+//! the others.
+//!
+//! ```
+//! #[macro_use]
+//! extern crate tracked_mem;
+//! use tracked_mem::soa::TrackedSoA;
+//!
+//! tracked_soa! {
+//!     #[derive(Debug, PartialEq)]
+//!     pub struct Particle {
+//!         pub pos: f32,
+//!         pub vel: f32,
+//!     }
+//!     pub struct ParticleColumns;
+//! }
+//!
+//! # fn main() {
+//! let mut particles = ParticleColumns::with_len(4);
+//! particles.set_row(0, Particle { pos: 1.0, vel: 0.5 });
+//! particles.pos.set_value(1, 2.0);
+//! assert!(particles.is_row_initialized(0));
+//! assert!(!particles.is_row_initialized(1));
+//! assert_eq!(particles.take_row(0), Some(Particle { pos: 1.0, vel: 0.5 }));
+//! # }
+//! ```
+
+/// A container that stores a struct as one tracked column per field.
+///
+/// This is implemented by the column types generated with `tracked_soa!`.
+pub trait TrackedSoA {
+    /// The struct stored in each row.
+    type Item;
+
+    /// Creates a container with `len` rows where every field is uninitialized.
+    fn with_len(len: usize) -> Self;
+
+    /// The number of rows.
+    fn len(&self) -> usize;
+
+    /// Returns true if there are no rows.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Sets every field of a row, dropping any fields that were already initialized.
+    fn set_row(&mut self, i: usize, item: Self::Item);
+
+    /// Returns true if every field of a row is initialized.
+    fn is_row_initialized(&self, i: usize) -> bool;
+
+    /// Moves a row out of the container. This only succeeds if every field of the row
+    /// is initialized. Otherwise nothing is moved and `None` is returned.
+    fn take_row(&mut self, i: usize) -> Option<Self::Item>;
+}
+
+/// Defines a struct along with a `TrackedSoA` column container for it.
+///
+/// The first item is the struct definition, which is emitted unchanged. The second item
+/// names the container type. The container has one public `SafeUninitializedVec`
+/// field per struct field, with the same name. Generic structs are not supported.
+#[macro_export]
+macro_rules! tracked_soa {
+    (
+        $(#[$attr:meta])*
+        $vis:vis struct $name:ident {
+            $( $(#[$fattr:meta])* $fvis:vis $field:ident : $fty:ty ),* $(,)*
+        }
+        $(#[$cattr:meta])*
+        $cvis:vis struct $cols:ident;
+    ) => {
+        $(#[$attr])*
+        $vis struct $name {
+            $( $(#[$fattr])* $fvis $field: $fty ),*
+        }
+
+        $(#[$cattr])*
+        $cvis struct $cols {
+            $( pub $field: $crate::uninitialized::SafeUninitializedVec<$fty>, )*
+            soa_len: usize,
+        }
+
+        impl $crate::soa::TrackedSoA for $cols {
+            type Item = $name;
+
+            fn with_len(len: usize) -> $cols {
+                $cols {
+                    $( $field: $crate::uninitialized::SafeUninitializedVec::new(len), )*
+                    soa_len: len,
+                }
+            }
+
+            fn len(&self) -> usize {
+                self.soa_len
+            }
+
+            fn set_row(&mut self, i: usize, item: $name) {
+                $( self.$field.set_value(i, item.$field); )*
+            }
+
+            fn is_row_initialized(&self, i: usize) -> bool {
+                true $( && self.$field.get_value(i).is_some() )*
+            }
+
+            fn take_row(&mut self, i: usize) -> Option<$name> {
+                if self.is_row_initialized(i) {
+                    Some($name {
+                        $( $field: self.$field.take(i).unwrap(), )*
+                    })
+                } else {
+                    None
+                }
+            }
+        }
+    };
+}
@@ -24,7 +24,7 @@ fn test_uninit_vec_fail() {
     let mut vec = SafeUninitializedVec::new(4);
     vec.set_value(3, 2);
     vec.set_value(1, 5);
-    if let Ok(_) = vec.into_vec() {
+    if vec.into_vec().is_ok() {
         panic!("Retured a value that contains unintialized data!");
     }
 }
@@ -33,7 +33,7 @@ fn test_uninit_vec_fail() {
 fn test_uninit_vec_take() {
     let mut vec = SafeUninitializedVec::from_vec(vec![1, 2, 3, 4, 5, 6], 4);
     let _three = vec.take(2);
-    if let Ok(_) = vec.into_vec() {
+    if vec.into_vec().is_ok() {
         panic!("Returned a value that contains unintialized data!");
     }
 }
@@ -53,7 +53,7 @@ fn test_uninit_vec_overcapacity() {
     assert_eq!(vec.into_vec().unwrap(), vec![1, 2, 3, 4, 5, 6]);
     let mut vec = SafeUninitializedVec::from_vec(vec![1, 2, 3, 4, 5, 6], 4);
     let _three = vec.take(2);
-    if let Ok(_) = vec.into_vec() {
+    if vec.into_vec().is_ok() {
         panic!("Returned a value that contains unintialized data");
     }
     let mut vec =
@@ -61,3 +61,2785 @@ fn test_uninit_vec_overcapacity() {
     let _take = vec.take(1);
     // drop the vec
 }
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_uninit_vec_choose() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut vec = SafeUninitializedVec::new(6);
+    assert_eq!(vec.choose_initialized(&mut rng), None);
+    vec.set_value(1, 10);
+    vec.set_value(4, 40);
+    for _ in 0..20 {
+        let val = *vec.choose_initialized(&mut rng).unwrap();
+        assert!(val == 10 || val == 40);
+    }
+    let mut sample = vec.sample_initialized(&mut rng, 5);
+    sample.sort();
+    assert_eq!(sample, vec![&10, &40]);
+    assert_eq!(vec.sample_initialized(&mut rng, 1).len(), 1);
+
+    // the values past the tracked length are sampled too
+    let mut vec = SafeUninitializedVec::from_vec(vec![1, 2, 3, 4, 5, 6], 4);
+    vec.take(0);
+    vec.take(2);
+    let mut sample = vec.sample_initialized(&mut rng, 6);
+    sample.sort();
+    assert_eq!(sample, vec![&2, &4, &5, &6]);
+    let mut seen = [false; 7];
+    for _ in 0..100 {
+        seen[*vec.choose_initialized(&mut rng).unwrap()] = true;
+    }
+    assert_eq!(seen, [false, false, true, false, true, true, true]);
+}
+
+#[test]
+fn test_uninit_vec_fill_guard() {
+    let mut vec = SafeUninitializedVec::new(4);
+    vec.set_value(1, String::from("one"));
+    {
+        let mut fill = vec.begin_fill(0..3);
+        fill.set_value(0, String::from("zero"));
+        fill.set_value(1, String::from("uno"));
+        assert_eq!(fill.get_value(1).map(|s| s.as_str()), Some("uno"));
+        // dropped without committing
+    }
+    assert_eq!(vec.get_value(0), None);
+    assert_eq!(vec.get_value(1).map(|s| s.as_str()), Some("one"));
+
+    let mut fill = vec.begin_fill(2..4);
+    fill.set_value(2, String::from("two"));
+    fill.set_value(3, String::from("three"));
+    assert!(fill.is_complete());
+    fill.commit();
+    assert_eq!(vec.get_value(3).map(|s| s.as_str()), Some("three"));
+}
+
+#[test]
+fn test_uninit_vec_fill_guard_panic() {
+    use std::panic;
+
+    let mut vec = SafeUninitializedVec::new(3);
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        let mut fill = vec.begin_fill(0..3);
+        fill.set_value(0, vec![1]);
+        fill.set_value(1, vec![2]);
+        panic!("fill failed");
+    }));
+    assert!(result.is_err());
+    assert_eq!(vec.get_value(0), None);
+    assert_eq!(vec.get_value(1), None);
+}
+
+#[test]
+fn test_generational_vec() {
+    use GenerationalVec;
+
+    let mut vec = GenerationalVec::new(3);
+    let first = vec.set_value(0, "first");
+    assert_eq!(first.get(&vec), Some(&"first"));
+    assert_eq!(vec.take(0), Some("first"));
+    assert_eq!(vec.get(first), None);
+
+    let second = vec.set_value(0, "second");
+    assert_eq!(vec.get(first), None);
+    assert_eq!(vec.get(second), Some(&"second"));
+    assert_ne!(first.generation(), second.generation());
+
+    vec.set_value(0, "third");
+    assert!(!vec.is_live(second));
+    let third = vec.weak_index(0).unwrap();
+    assert_eq!(vec.take_weak(second), None);
+    assert_eq!(vec.take_weak(third), Some("third"));
+    assert_eq!(vec.weak_index(0), None);
+}
+
+#[test]
+fn test_handle_vec() {
+    use HandleVec;
+
+    let mut vec = HandleVec::new(5);
+    let c = vec.set_value(4, 3);
+    let a = vec.set_value(1, 1);
+    let b = vec.set_value(3, 2);
+    assert_eq!(vec.compact(), 3);
+    assert_eq!(vec.position(c), Some(2));
+    assert_eq!(vec.get(a), Some(&1));
+
+    vec.sort();
+    assert_eq!(vec.position(a), Some(0));
+    assert_eq!(vec.position(b), Some(1));
+    assert_eq!(vec.get(c), Some(&3));
+
+    vec.set_value(4, 9);
+    assert_eq!(vec.swap_remove(0), Some(1));
+    assert_eq!(vec.get(a), None);
+    assert_eq!(vec.get_value(0), Some(&9));
+    assert_eq!(vec.get(b), Some(&2));
+
+    let d = vec.set_value(3, 4);
+    assert_ne!(d, a);
+    assert_eq!(vec.remove(d), Some(4));
+    assert_eq!(vec.get(d), None);
+}
+
+#[test]
+fn test_cow_vec() {
+    use CowUninitVec;
+
+    let mut vec = CowUninitVec::with_chunk_size(10, 4);
+    vec.set_value(1, String::from("a"));
+    vec.set_value(9, String::from("b"));
+    let copy = vec.clone();
+    assert!(vec.is_shared(1) && vec.is_shared(9));
+
+    vec.set_value(2, String::from("c"));
+    assert!(!vec.is_shared(1));
+    assert!(vec.is_shared(9));
+    assert_eq!(copy.get_value(2), None);
+    assert_eq!(vec.get_value(2).map(|s| s.as_str()), Some("c"));
+
+    assert_eq!(vec.take(9), Some(String::from("b")));
+    assert_eq!(copy.get_value(9).map(|s| s.as_str()), Some("b"));
+    assert_eq!(vec.get_value(9), None);
+}
+
+#[test]
+fn test_cow_vec_snapshot() {
+    use CowUninitVec;
+
+    let mut vec = CowUninitVec::with_chunk_size(8, 2);
+    vec.set_value(0, 1);
+    let snapshot = vec.snapshot();
+    let shared = snapshot.clone();
+    vec.set_value(0, 2);
+    vec.set_value(5, 3);
+    assert_eq!(snapshot.get_value(0), Some(&1));
+    assert_eq!(shared.get_value(5), None);
+    assert!(!vec.is_shared(5));
+
+    let mut live = snapshot.to_live();
+    live.set_value(1, 4);
+    assert_eq!(snapshot.get_value(1), None);
+    assert_eq!(live.get_value(0), Some(&1));
+}
+
+#[test]
+fn test_pin_arena() {
+    use std::marker::PhantomPinned;
+    use PinArena;
+
+    struct Pinned {
+        val: u32,
+        _pin: PhantomPinned,
+    }
+
+    let mut arena = PinArena::with_chunk_size(2);
+    let slots: Vec<usize> = (0..5)
+        .map(|val| {
+            arena.insert(Pinned {
+                val,
+                _pin: PhantomPinned,
+            })
+        })
+        .collect();
+    assert_eq!(slots, vec![0, 1, 2, 3, 4]);
+    let addr = arena.get(0).unwrap() as *const Pinned;
+    assert!(arena.remove(3));
+    assert!(!arena.remove(3));
+    assert_eq!(arena.get(0).unwrap() as *const Pinned, addr);
+    assert_eq!(arena.get_pin(4).map(|p| p.val), Some(4));
+
+    let mut strings = PinArena::new();
+    let i = strings.insert(String::from("unpin"));
+    strings.get_mut(i).unwrap().push('!');
+    assert_eq!(strings.take(i), Some(String::from("unpin!")));
+    assert_eq!(strings.take(i), None);
+    strings.insert(String::from("dropped with the arena"));
+}
+
+#[test]
+fn test_pin_arena_panicking_drop() {
+    use std::panic;
+    use std::rc::Rc;
+    use PinArena;
+
+    struct Bomb {
+        explode: bool,
+        _count: Rc<()>,
+    }
+
+    impl Drop for Bomb {
+        fn drop(&mut self) {
+            if self.explode {
+                panic!("dropped");
+            }
+        }
+    }
+
+    let count = Rc::new(());
+    let mut arena = PinArena::with_chunk_size(2);
+    for i in 0..5 {
+        arena.insert(Bomb {
+            explode: i == 1,
+            _count: count.clone(),
+        });
+    }
+    // the values after the one that panicked are still dropped
+    assert!(panic::catch_unwind(panic::AssertUnwindSafe(|| drop(arena))).is_err());
+    assert_eq!(Rc::strong_count(&count), 1);
+}
+
+tracked_soa! {
+    #[derive(Debug, PartialEq)]
+    struct Record {
+        id: u64,
+        name: String,
+    }
+    struct RecordColumns;
+}
+
+#[test]
+fn test_tracked_soa() {
+    use soa::TrackedSoA;
+
+    let mut records = RecordColumns::with_len(3);
+    assert_eq!(records.len(), 3);
+    records.set_row(
+        0,
+        Record {
+            id: 1,
+            name: String::from("one"),
+        },
+    );
+    records.id.set_value(2, 3);
+    assert!(records.is_row_initialized(0));
+    assert!(!records.is_row_initialized(2));
+    assert_eq!(records.take_row(2), None);
+    assert_eq!(records.id.get_value(2), Some(&3));
+    assert_eq!(
+        records.take_row(0),
+        Some(Record {
+            id: 1,
+            name: String::from("one"),
+        })
+    );
+    assert_eq!(records.id.get_value(0), None);
+}
+
+#[cfg(feature = "arrow")]
+#[test]
+fn test_uninit_vec_arrow() {
+    use arrow_array::types::Int32Type;
+    use arrow_array::{Array, PrimitiveArray};
+
+    let mut vec = SafeUninitializedVec::new(4);
+    vec.set_value(0, 7);
+    vec.set_value(2, 9);
+    let array: PrimitiveArray<Int32Type> = vec.into_arrow();
+    assert_eq!(array.len(), 4);
+    assert_eq!(array.null_count(), 2);
+    assert!(array.is_valid(2) && array.is_null(3));
+    assert_eq!(array.value(2), 9);
+
+    let back = SafeUninitializedVec::from_arrow(array);
+    assert_eq!(back.get_value(0), Some(&7));
+    assert_eq!(back.get_value(1), None);
+
+    // the values are moved into the array and back without copying
+    let mut vec = SafeUninitializedVec::from_vec((0..200).collect(), 200);
+    vec.take(130);
+    let ptr = vec.get_value(0).unwrap() as *const i32;
+    let array: PrimitiveArray<Int32Type> = vec.into_arrow();
+    assert_eq!(array.values().as_ptr(), ptr);
+    assert!(array.is_null(130) && array.is_valid(131));
+    let back = SafeUninitializedVec::from_arrow(array);
+    assert_eq!(back.get_value(0).unwrap() as *const i32, ptr);
+    assert_eq!(back.count_initialized(), 199);
+    assert_eq!(back.uninitialized_indices().collect::<Vec<_>>(), vec![130]);
+
+    // sliced validity bitmaps are shifted into place
+    let array: PrimitiveArray<Int32Type> =
+        (0..100).map(|i| Some(i).filter(|i| i % 3 != 0)).collect();
+    let vec = SafeUninitializedVec::from_arrow(array.slice(5, 90));
+    assert!((0..90).all(|i| vec.get_value(i).is_some() == ((i + 5) % 3 != 0)));
+
+    let full = PrimitiveArray::<Int32Type>::from(vec![1, 2, 3]);
+    let vec = SafeUninitializedVec::from_arrow(full.slice(1, 2));
+    assert_eq!(vec.into_vec().unwrap(), vec![2, 3]);
+}
+
+#[cfg(feature = "bitvec")]
+#[test]
+fn test_uninit_vec_bitvec() {
+    use bitvec::prelude::*;
+    use std::mem::MaybeUninit;
+
+    let mut vec = SafeUninitializedVec::from_bitvec(
+        vec![String::from("a"), String::from("b"), String::from("c")],
+        bits![1, 0, 1],
+    );
+    assert_eq!(vec.get_value(1), None);
+    assert_eq!(vec.as_bitslice(), bits![1, 0, 1]);
+
+    let mut storage = vec![MaybeUninit::uninit(); 4];
+    storage[2] = MaybeUninit::new(5u32);
+    let mut vec = unsafe { SafeUninitializedVec::from_uninit_bitvec(storage, bits![0, 0, 1]) };
+    assert_eq!(vec.get_value(2), Some(&5));
+    vec.set_value(3, 6);
+    assert_eq!(vec.as_bitslice().count_ones(), 2);
+
+    // the untracked tail is exported as initialized
+    let mut vec = SafeUninitializedVec::from_vec((0..70).collect(), 2);
+    vec.take(1);
+    let mask = vec.as_bitslice();
+    assert_eq!(mask.len(), 70);
+    assert_eq!(mask.count_ones(), 69);
+    assert_eq!(mask.first_zero(), Some(1));
+}
+
+#[test]
+fn test_init_mask() {
+    use InitMask;
+
+    let a: InitMask = vec![true, true, false, true, false, false].into();
+    let b: InitMask = vec![false, true, true, true, false, true].into();
+    assert_eq!(&a & &b, vec![false, true, false, true, false, false].into());
+    assert_eq!((&a | &b).count_zeros(), 1);
+    assert_eq!(!&a, vec![false, false, true, false, true, true].into());
+    assert!(a.intersection(&b).is_subset(&a));
+    assert_eq!(a.difference(&b).ones().collect::<Vec<_>>(), vec![0]);
+
+    assert_eq!(a.rank(4), 3);
+    assert_eq!(a.select(2), Some(3));
+    assert_eq!(a.select(3), None);
+    assert_eq!(a.runs().collect::<Vec<_>>(), vec![0..2, 3..4]);
+    assert_eq!(a.gaps().collect::<Vec<_>>(), vec![2..3, 4..6]);
+
+    let mut vec = SafeUninitializedVec::new(3);
+    vec.set_value(1, 'x');
+    assert_eq!(vec.mask().ones().collect::<Vec<_>>(), vec![1]);
+}
+
+#[cfg(feature = "python")]
+#[test]
+fn test_python_buffer() {
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+    use std::ffi::CString;
+    use uninitialized::PyTrackedBuffer;
+
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let buf = Py::new(py, PyTrackedBuffer::new(SafeUninitializedVec::new(6))).unwrap();
+        let locals = PyDict::new(py);
+        locals.set_item("buf", &buf).unwrap();
+        let code = CString::new(
+            "with memoryview(buf) as m:\n    m[1:4] = b'abc'\nbuf.commit(1, 4)\n\
+             ranges = buf.initialized_ranges()",
+        )
+        .unwrap();
+        py.run(&code, None, Some(&locals)).unwrap();
+        let ranges: Vec<(usize, usize)> = locals
+            .get_item("ranges")
+            .unwrap()
+            .unwrap()
+            .extract()
+            .unwrap();
+        assert_eq!(ranges, vec![(1, 4)]);
+
+        let vec = buf.borrow_mut(py).take_buffer().unwrap();
+        assert_eq!(vec.get_value(0), None);
+        assert_eq!(vec.get_value(2), Some(&b'b'));
+    });
+}
+
+#[test]
+fn test_external_mapped() {
+    use std::mem::MaybeUninit;
+    use std::panic;
+    use ExternalMapped;
+
+    let mut memory = [MaybeUninit::<u32>::uninit(); 8];
+    let mut mapped = unsafe { ExternalMapped::new(memory.as_mut_ptr() as *mut u32, 8) };
+    assert!(!mapped.needs_flush());
+    mapped.set_value(1, 5);
+    mapped.write_slice(2, &[6, 7]);
+    mapped.set_value(6, 8);
+    assert_eq!(mapped.get_value(3), Some(&7));
+    assert_eq!(mapped.get_value(4), None);
+    assert_eq!(mapped.as_slice(), None);
+    assert_eq!(mapped.take_flush_ranges(), vec![4..16, 24..28]);
+    assert!(!mapped.needs_flush());
+    mapped.set_value(0, 4);
+    assert_eq!(mapped.take_dirty_ranges(), vec![0..1]);
+    assert_eq!(mapped.mask().count_ones(), 5);
+
+    // an offset that overflows is out of bounds rather than wrapping around
+    let write = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        mapped.write_slice(usize::MAX, &[1, 2])
+    }));
+    assert!(write.is_err());
+    assert_eq!(mapped.mask().count_ones(), 5);
+}
+
+#[test]
+fn test_pinned_bytes() {
+    use pinned::LeaseError;
+    use std::ptr;
+    use PinnedBytes;
+
+    let mut buf = PinnedBytes::new(16);
+    let first = buf.lease(0..8).unwrap();
+    assert_eq!(buf.lease(4..12).err(), Some(LeaseError::Leased));
+    let second = buf.lease_next(16).unwrap();
+    assert_eq!(second.range(), 8..16);
+    assert_eq!(buf.outstanding_leases(), 2);
+
+    // pretend the kernel wrote 3 bytes
+    unsafe { ptr::copy_nonoverlapping(b"abc".as_ptr(), first.as_mut_ptr(), 3) };
+    buf.commit(first, 3);
+    buf.cancel(second);
+    assert_eq!(buf.get(0..3), Some(&b"abc"[..]));
+    assert_eq!(buf.get(0..4), None);
+    assert_eq!(buf.lease(2..4).err(), Some(LeaseError::Initialized));
+
+    buf.discard(0..3);
+    assert_eq!(buf.mask().count_ones(), 0);
+    let leaked = buf.lease_next(4).unwrap();
+    assert_eq!(leaked.range(), 0..4);
+    assert_eq!(buf.lease_next(usize::MAX).unwrap().range(), 4..16);
+    // reversed ranges are rejected instead of underflowing
+    let (start, end) = (17, 16);
+    assert_eq!(buf.get(start..end), None);
+    assert_eq!(buf.get(start - 14..end - 14), None);
+    // dropping the buffer with an outstanding lease leaks its memory
+}
+
+#[test]
+fn test_uninit_vec_read_vectored() {
+    use std::io::ErrorKind;
+
+    let mut vec = SafeUninitializedVec::new(10);
+    vec.set_value(4, b'-');
+    let mut input: &[u8] = b"abcdefg";
+    let read = vec.read_vectored_into(&mut input, &[6..10, 0..4]).unwrap();
+    assert_eq!(read, 7);
+    assert_eq!(vec.get_value(6), Some(&b'a'));
+    assert_eq!(vec.get_value(0), Some(&b'e'));
+    assert_eq!(vec.get_value(2), Some(&b'g'));
+    assert_eq!(vec.get_value(3), None);
+
+    let mut input: &[u8] = b"xyz";
+    let err = vec
+        .read_vectored_into(&mut input, &[5..6, 3..5])
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    let err = vec
+        .read_vectored_into(&mut input, &[3..4, 3..4])
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    assert_eq!(
+        vec.read_vectored_into(&mut input, &[3..4, 5..6]).unwrap(),
+        2
+    );
+    assert_eq!(vec.into_vec().unwrap(), b"efgx-yabcd".to_vec());
+}
+
+#[test]
+fn test_uninit_vec_hash_initialized() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::Hasher;
+
+    let hash = |vec: &SafeUninitializedVec<u8>| {
+        let mut hasher = DefaultHasher::new();
+        vec.hash_initialized(&mut hasher);
+        hasher.finish()
+    };
+    let mut a = SafeUninitializedVec::new(4);
+    let mut b = SafeUninitializedVec::new(4);
+    a.set_value(1, 7);
+    b.set_value(1, 7);
+    assert_eq!(hash(&a), hash(&b));
+    assert_eq!(a.initialized_digest(), b.initialized_digest());
+    b.set_value(2, 0);
+    assert_ne!(hash(&a), hash(&b));
+    assert_ne!(a.initialized_digest(), b.initialized_digest());
+    b.take(2);
+    assert_eq!(a.initialized_digest(), b.initialized_digest());
+    assert_ne!(
+        a.initialized_digest(),
+        SafeUninitializedVec::new(4).initialized_digest()
+    );
+
+    // the values past the end of the mask are hashed too
+    let a = SafeUninitializedVec::from_vec(vec![1, 2, 3, 4], 2);
+    let b = SafeUninitializedVec::from_vec(vec![1, 2, 3, 5], 2);
+    assert_ne!(hash(&a), hash(&b));
+    assert_ne!(a.initialized_digest(), b.initialized_digest());
+    // and it does not matter whether they are tracked
+    let c = SafeUninitializedVec::from_vec(vec![1, 2, 3, 4], 4);
+    assert_eq!(hash(&a), hash(&c));
+    assert_eq!(a.initialized_digest(), c.initialized_digest());
+}
+
+#[test]
+fn test_uninit_vec_sparse_bytes() {
+    use uninitialized::SparseFormatError;
+
+    let mut vec = SafeUninitializedVec::new(100_000);
+    vec.set_value(10, 1u32);
+    vec.set_value(11, 2);
+    vec.set_value(99_999, 3);
+    let bytes = vec.to_sparse_bytes();
+    assert!(bytes.len() < 32);
+    let decoded = SafeUninitializedVec::<u32>::from_sparse_bytes(&bytes).unwrap();
+    assert_eq!(decoded.get_value(11), Some(&2));
+    assert_eq!(decoded.get_value(12), None);
+    assert_eq!(decoded.get_value(99_999), Some(&3));
+    assert_eq!(decoded.mask(), vec.mask());
+
+    let vec = SafeUninitializedVec::from_vec(vec![1i16, 2, 3], 2);
+    let decoded = SafeUninitializedVec::<i16>::from_sparse_bytes(&vec.to_sparse_bytes());
+    assert_eq!(decoded.unwrap().into_vec().unwrap(), vec![1, 2, 3]);
+
+    let err = SafeUninitializedVec::<u32>::from_sparse_bytes(&bytes[..bytes.len() - 1]);
+    assert_eq!(err.err(), Some(SparseFormatError::UnexpectedEnd));
+    let err = SafeUninitializedVec::<u32>::from_sparse_bytes(&[2, 1, 1, 2, 0, 0]);
+    assert_eq!(err.err(), Some(SparseFormatError::RunOutOfBounds));
+    // a length that can not be allocated is an error rather than an abort
+    let huge = [
+        0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 0,
+    ];
+    let err = SafeUninitializedVec::<u32>::from_sparse_bytes(&huge);
+    assert_eq!(err.err(), Some(SparseFormatError::TooLarge));
+}
+
+#[test]
+fn test_tracked_slice() {
+    use std::mem::MaybeUninit;
+    use std::rc::Rc;
+    use std::sync::atomic::AtomicU64;
+    use TrackedSlice;
+
+    let counter = Rc::new(());
+    let mut storage: Vec<MaybeUninit<Rc<()>>> = (0..4).map(|_| MaybeUninit::uninit()).collect();
+    // the flags are cleared when the slice is created
+    let mut flags = [AtomicU64::new(!0)];
+    {
+        let mut slice = TrackedSlice::new(&mut storage, &mut flags);
+        slice.set_value(0, counter.clone());
+        slice.set_value(2, counter.clone());
+        slice.set_value(2, counter.clone());
+        assert_eq!(Rc::strong_count(&counter), 3);
+        assert!(slice.take(0).is_some());
+        assert_eq!(slice.count_initialized(), 1);
+        // the value left in slot 2 is dropped with the slice
+    }
+    assert_eq!(Rc::strong_count(&counter), 1);
+
+    let mut storage = [MaybeUninit::<u8>::uninit(); 2];
+    let mut slice = TrackedSlice::new(&mut storage, &mut flags);
+    slice.set_value(1, 2);
+    let mut slice = slice.into_initialized().err().unwrap();
+    slice.set_value(0, 1);
+    assert_eq!(slice.into_initialized().ok().unwrap(), &mut [1, 2]);
+
+    // slots past the first word are tracked by the following words
+    let mut storage: Vec<MaybeUninit<u32>> = (0..100).map(|_| MaybeUninit::uninit()).collect();
+    let mut flags: Vec<AtomicU64> = (0..TrackedSlice::<u32>::flag_words(100))
+        .map(|_| AtomicU64::new(0))
+        .collect();
+    let mut slice = TrackedSlice::new(&mut storage, &mut flags);
+    slice.set_value(70, 7);
+    slice.set_value(99, 9);
+    slice.swap(70, 3);
+    assert_eq!(slice.count_initialized(), 2);
+    assert_eq!(slice.get_value(3), Some(&7));
+    assert_eq!(slice.take(99), Some(9));
+    assert!(!slice.is_initialized(99));
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn test_uninit_vec_metrics() {
+    use metrics::{
+        with_local_recorder, Counter, CounterFn, Gauge, GaugeFn, Histogram, Key, KeyName, Metadata,
+        Recorder, SharedString, Unit,
+    };
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    type Values = Arc<Mutex<HashMap<String, f64>>>;
+
+    struct Handle(Values, String);
+
+    impl CounterFn for Handle {
+        fn increment(&self, value: u64) {
+            *self.0.lock().unwrap().entry(self.1.clone()).or_insert(0.0) += value as f64;
+        }
+
+        fn absolute(&self, value: u64) {
+            self.0.lock().unwrap().insert(self.1.clone(), value as f64);
+        }
+    }
+
+    impl GaugeFn for Handle {
+        fn increment(&self, value: f64) {
+            *self.0.lock().unwrap().entry(self.1.clone()).or_insert(0.0) += value;
+        }
+
+        fn decrement(&self, value: f64) {
+            *self.0.lock().unwrap().entry(self.1.clone()).or_insert(0.0) -= value;
+        }
+
+        fn set(&self, value: f64) {
+            self.0.lock().unwrap().insert(self.1.clone(), value);
+        }
+    }
+
+    struct TestRecorder(Values);
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata) -> Counter {
+            Counter::from_arc(Arc::new(Handle(self.0.clone(), key.name().to_string())))
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata) -> Gauge {
+            Gauge::from_arc(Arc::new(Handle(self.0.clone(), key.name().to_string())))
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    let values: Values = Arc::new(Mutex::new(HashMap::new()));
+    let recorder = TestRecorder(values.clone());
+    let get = |name: &str| values.lock().unwrap().get(name).cloned().unwrap_or(0.0);
+    with_local_recorder(&recorder, || {
+        let mut vec = SafeUninitializedVec::<u32>::new(4);
+        vec.set_value(0, 1);
+        vec.set_value(0, 2);
+        vec.set_value(3, 3);
+        assert_eq!(get("tracked_mem_live_containers"), 1.0);
+        assert_eq!(get("tracked_mem_tracked_bytes"), 16.0);
+        assert_eq!(get("tracked_mem_initialized_slots"), 2.0);
+        vec.take(3);
+        assert_eq!(get("tracked_mem_initialized_slots"), 1.0);
+        assert_eq!(get("tracked_mem_fills_total"), 2.0);
+        drop(vec);
+        let vec = SafeUninitializedVec::from_vec(vec![1u8, 2], 2);
+        vec.into_vec().unwrap();
+    });
+    assert_eq!(get("tracked_mem_live_containers"), 0.0);
+    assert_eq!(get("tracked_mem_tracked_bytes"), 0.0);
+    assert_eq!(get("tracked_mem_initialized_slots"), 0.0);
+}
+
+#[test]
+fn test_crate_error() {
+    use error;
+    use pinned::LeaseError;
+    use uninitialized::SparseFormatError;
+    use Error;
+    use {DropQueue, MightOwn, PinnedBytes, TrackedCStringBuf};
+
+    fn finish(vec: SafeUninitializedVec<u8>) -> error::Result<Vec<u8>> {
+        Ok(vec.into_vec()?)
+    }
+
+    let mut vec = SafeUninitializedVec::new(3);
+    vec.set_value(0, 1);
+    let err = finish(vec).unwrap_err();
+    assert_eq!(
+        err,
+        Error::Uninitialized {
+            len: 3,
+            index: Some(1)
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "slot 1 of a container of length 3 is uninitialized"
+    );
+
+    let mut val = 5;
+    let err: Error = MightOwn::unowned(&mut val).get_owned().unwrap_err().into();
+    assert_eq!(err, Error::NotOwned);
+
+    // the error of every part of the crate converts, and can be boxed as a std error
+    fn boxed<E: ::std::error::Error + 'static>(err: E) -> Box<dyn (::std::error::Error)> {
+        Box::new(err)
+    }
+
+    let mut buf = PinnedBytes::new(4);
+    let lease = buf.lease(0..2).unwrap();
+    let err = buf.lease(1..3).unwrap_err();
+    assert_eq!(
+        boxed(err.clone()).to_string(),
+        "leased region is already leased"
+    );
+    assert_eq!(Error::from(err), Error::Lease(LeaseError::Leased));
+    buf.cancel(lease);
+
+    let err = SafeUninitializedVec::<u32>::from_sparse_bytes(&[]).unwrap_err();
+    assert_eq!(boxed(err).to_string(), "unexpected end of sparse bytes");
+    assert_eq!(
+        Error::from(err),
+        Error::SparseFormat(SparseFormatError::UnexpectedEnd)
+    );
+
+    let mut queue = DropQueue::with_capacity(0);
+    let err = queue.park(1).unwrap_err();
+    assert_eq!(boxed(err).to_string(), "the drop queue is full");
+
+    let err = TrackedCStringBuf::new().push_bytes(b"a\0").unwrap_err();
+    assert_eq!(Error::from(err), Error::InteriorNul { position: 1 });
+    assert_eq!(Error::from(err).to_string(), boxed(err).to_string());
+}
+
+#[test]
+fn test_tracked_heap() {
+    use std::thread;
+    use Error;
+    use TrackedHeap;
+
+    let mut heap = TrackedHeap::with_capacity(5);
+    {
+        let mut first = heap.claim(2).unwrap();
+        let mut second = heap.claim(2).unwrap();
+        assert_eq!(
+            heap.claim(2).err(),
+            Some(Error::BudgetExceeded {
+                requested: 2,
+                available: 1
+            })
+        );
+        second.set_value(2, 7);
+        first.set_value(0, 3);
+        second.set_value(3, 9);
+    }
+    assert_eq!(
+        heap.heapify().unwrap_err(),
+        Error::Uninitialized {
+            len: 4,
+            index: Some(1)
+        }
+    );
+    heap.set_value(1, 5);
+    assert_eq!(heap.get_value(1), Some(&5));
+    let built = heap.heapify().unwrap();
+    assert_eq!(built.into_sorted_vec(), vec![3, 5, 7, 9]);
+    assert_eq!(heap.claimed(), 0);
+    assert_eq!(heap.get_value(1), None);
+
+    // producers on several threads fill their own claims
+    let mut heap = TrackedHeap::with_capacity(100);
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            let heap = &heap;
+            scope.spawn(move || {
+                let mut claim = heap.claim(25).unwrap();
+                for i in claim.range() {
+                    claim.set_value(i, i.to_string());
+                }
+            });
+        }
+    });
+    let built = heap.heapify().unwrap();
+    assert_eq!(built.len(), 100);
+    assert_eq!(built.peek(), Some(&"99".to_string()));
+    // values left in the storage are dropped with it
+    heap.claim(1)
+        .unwrap()
+        .set_value(0, String::from("left behind"));
+}
+
+#[test]
+fn test_uninit_vec_copy_from() {
+    use uninitialized::CopyPolicy;
+
+    let mut src = SafeUninitializedVec::new(6);
+    for &i in &[0, 1, 2, 4] {
+        src.set_value(i, i as u32 * 10);
+    }
+    let mut dest = SafeUninitializedVec::new(5);
+    dest.set_value(1, 99);
+    dest.set_value(3, 33);
+    assert_eq!(dest.copy_from(&src, CopyPolicy::SkipInitialized), 3);
+    assert_eq!(dest.get_value(1), Some(&99));
+    assert_eq!(dest.get_value(2), Some(&20));
+    assert_eq!(dest.get_value(3), Some(&33));
+
+    assert_eq!(dest.copy_from(&src, CopyPolicy::Overwrite), 4);
+    assert_eq!(dest.into_vec().unwrap(), vec![0, 10, 20, 33, 40]);
+
+    // the untracked tails of both vectors hold values
+    let src = SafeUninitializedVec::from_vec(vec![1, 2, 3, 4], 2);
+    let mut dest = SafeUninitializedVec::from_vec(vec![5, 6, 7], 1);
+    dest.take(1);
+    assert_eq!(dest.copy_from(&src, CopyPolicy::SkipInitialized), 1);
+    assert_eq!(dest.get_value(1), Some(&2));
+    assert_eq!(dest.get_value(2), Some(&7));
+    assert_eq!(dest.copy_from(&src, CopyPolicy::Overwrite), 3);
+    assert_eq!(dest.into_vec().unwrap(), vec![1, 2, 3]);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_uninit_vec_par_sort() {
+    let mut vec = SafeUninitializedVec::new(6);
+    vec.set_value(2, 5);
+    vec.set_value(3, 1);
+    vec.set_value(4, 3);
+    assert_eq!(vec.par_sort_initialized(), 2..5);
+    assert_eq!(vec.get_value(2), Some(&1));
+    assert_eq!(vec.get_value(4), Some(&5));
+
+    vec.set_value(0, 4);
+    assert_eq!(vec.par_sort_by(|a, b| b.cmp(a)), 0..4);
+    let sorted: Vec<_> = (0..6).map(|i| vec.get_value(i).cloned()).collect();
+    assert_eq!(sorted, vec![Some(5), Some(4), Some(3), Some(1), None, None]);
+
+    // the untracked tail is sorted with the rest
+    let mut vec = SafeUninitializedVec::from_vec(vec![3, 1, 2, 0], 2);
+    vec.take(0);
+    assert_eq!(vec.par_sort_initialized(), 1..4);
+    let sorted: Vec<_> = (0..4).map(|i| vec.get_value(i).cloned()).collect();
+    assert_eq!(sorted, vec![None, Some(0), Some(1), Some(2)]);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_uninit_vec_par_collect() {
+    let mut vec = SafeUninitializedVec::new(1000);
+    for i in (0..1000).filter(|i| i % 3 == 0) {
+        vec.set_value(i, i.to_string());
+    }
+    let collected = vec.par_collect_initialized();
+    assert_eq!(collected.len(), 334);
+    assert_eq!(collected[1], (3, String::from("3")));
+    assert!(collected.iter().all(|&(i, ref s)| *s == i.to_string()));
+    assert_eq!(vec.mask().count_ones(), 0);
+
+    // the untracked tail is collected too
+    let mut vec = SafeUninitializedVec::from_vec(vec![1, 2, 3], 1);
+    assert_eq!(vec.par_collect_initialized(), vec![(0, 1), (1, 2), (2, 3)]);
+    assert_eq!(vec.count_initialized(), 0);
+    assert_eq!(vec.len(), 3);
+}
+
+#[test]
+fn test_tracked_bytes() {
+    use std::io::Cursor;
+    use TrackedBytes;
+
+    let mut buf = TrackedBytes::with_capacity(8);
+    buf.put_slice(b"ab");
+    let mut reader = Cursor::new(b"cdefghijkl".to_vec());
+    assert_eq!(buf.fill_from_read(&mut reader).unwrap(), 6);
+    assert_eq!(buf.as_slice(), b"abcdefgh");
+    assert_eq!(buf.fill_from_read(&mut reader).unwrap(), 0);
+
+    let front = buf.split_to(3);
+    assert_eq!(front.as_slice(), b"abc");
+    assert_eq!(buf.as_slice(), b"defgh");
+    assert_eq!(buf.fill_from_read(&mut reader).unwrap(), 3);
+    assert_eq!(buf.as_slice(), b"defghijk");
+    buf.put_u8(b'!');
+    assert_eq!(buf.as_slice(), b"defghijk!");
+
+    // splitting leaves the rest in place, and the room in front is reclaimed when needed
+    let front = buf.split_to(4);
+    assert_eq!(front.into_vec(), b"defg".to_vec());
+    let capacity = buf.capacity();
+    assert_eq!(buf.remaining(), capacity - 5);
+    buf.reserve(capacity);
+    assert!(buf.remaining() >= capacity);
+    let mut reader = Cursor::new(b"?".to_vec());
+    assert_eq!(buf.fill_from_read(&mut reader).unwrap(), 1);
+    assert_eq!(buf.into_vec(), b"hijk!?".to_vec());
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn test_tracked_bytes_freeze() {
+    use TrackedBytes;
+
+    let mut buf = TrackedBytes::with_capacity(16);
+    buf.put_slice(b"frozen");
+    assert_eq!(&buf.freeze()[..], b"frozen");
+}
+
+#[cfg(feature = "rkyv")]
+#[test]
+fn test_uninit_vec_rkyv() {
+    use rkyv::rancor;
+    use uninitialized::ArchivedSafeUninitializedVec;
+
+    let mut vec = SafeUninitializedVec::from_vec(vec![1u32, 2], 12);
+    vec.set_value(9, 10);
+    vec.take(1);
+    let bytes = rkyv::to_bytes::<rancor::Error>(&vec).unwrap();
+
+    let archived =
+        rkyv::access::<ArchivedSafeUninitializedVec<u32>, rancor::Error>(&bytes).unwrap();
+    assert_eq!(archived.len(), 12);
+    assert_eq!(archived.count_initialized(), 2);
+    assert_eq!(archived.get_value(9).map(|v| v.to_native()), Some(10));
+    assert!(archived.get_value(1).is_none());
+
+    let restored: SafeUninitializedVec<u32> =
+        rkyv::deserialize::<_, rancor::Error>(archived).unwrap();
+    assert_eq!(restored.mask(), vec.mask());
+    assert_eq!(restored.get_value(0), Some(&1));
+    assert_eq!(restored.get_value(9), Some(&10));
+}
+
+#[test]
+fn test_uninit_vec_chunks() {
+    use error::Error;
+
+    let mut vec = SafeUninitializedVec::from_vec(vec![0u16, 1, 2, 3, 4, 5], 8);
+    assert_eq!(vec.get_chunk::<3>(1), Some(&[1, 2, 3]));
+    assert!(vec.get_chunk::<3>(5).is_none());
+
+    assert_eq!(vec.take_chunk::<2>(2).unwrap(), [2, 3]);
+    assert_eq!(
+        vec.take_chunk::<4>(0).unwrap_err(),
+        Error::Uninitialized {
+            len: 8,
+            index: Some(2)
+        }
+    );
+    assert_eq!(
+        vec.take_chunk::<4>(6).unwrap_err(),
+        Error::OutOfBounds { index: 9, len: 8 }
+    );
+    assert_eq!(vec.get_value(0), Some(&0));
+    assert!(vec.get_value(3).is_none());
+
+    // the chunk extends past the tracked length
+    let mut vec = SafeUninitializedVec::from_vec(vec![0u16, 1, 2, 3, 4, 5], 4);
+    assert_eq!(vec.take_chunk::<2>(3).unwrap(), [3, 4]);
+    assert_eq!(vec.mask().len(), 6);
+    assert_eq!(vec.take(5), Some(5));
+}
+
+#[test]
+fn test_uninit_vec_map_in_place() {
+    use std::panic;
+    use std::rc::Rc;
+
+    let mut vec = SafeUninitializedVec::from_vec(vec![1u32, 2, 3], 5);
+    vec.take(1);
+    let ptr = vec.get_value(0).unwrap() as *const u32 as usize;
+    let mapped = vec.map_in_place(|x| x as f32 * 0.5);
+    assert_eq!(mapped.get_value(0).unwrap() as *const f32 as usize, ptr);
+    assert_eq!(mapped.get_value(2), Some(&1.5));
+    assert!(mapped.get_value(1).is_none());
+
+    // a panic drops the values that were converted and the ones that were not
+    let rc = Rc::new(());
+    let mut vec = SafeUninitializedVec::new(4);
+    for i in 0..4 {
+        vec.set_value(i, Some(rc.clone()));
+    }
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+        vec.map_in_place(|x| {
+            drop(x);
+            if Rc::strong_count(&rc) == 3 {
+                panic!("conversion failed");
+            }
+            Some(Box::new(0u8))
+        })
+    }));
+    assert!(result.is_err());
+    assert_eq!(Rc::strong_count(&rc), 1);
+}
+
+#[test]
+fn test_uninit_vec_recycle() {
+    use uninitialized::recycle;
+
+    let vec = vec![String::from("dropped"); 4];
+    let ptr = vec.as_ptr() as usize;
+    let mut recycled = recycle::<[usize; 3], _>(vec);
+    assert_eq!(recycled.mask().len(), 4);
+    assert_eq!(recycled.mask().count_ones(), 0);
+    recycled.set_value(3, [1, 2, 3]);
+    assert_eq!(
+        recycled.get_value(3).unwrap() as *const _ as usize,
+        ptr + 72
+    );
+
+    // the alignment differs, so a new allocation is made
+    let recycled = recycle::<u16, u64>(Vec::with_capacity(3));
+    assert_eq!(recycled.mask().len(), 12);
+}
+
+#[test]
+fn test_uninit_vec_raw_parts() {
+    let mut vec = SafeUninitializedVec::from_vec(vec![String::from("a"), String::from("b")], 1);
+    vec.set_value(0, String::from("c"));
+    let (ptr, len, capacity, mask) = vec.into_raw_parts();
+    assert_eq!(len, 2);
+    assert_eq!(mask.count_ones(), 2);
+    let mut vec = unsafe { SafeUninitializedVec::from_raw_parts(ptr, len, capacity, mask) };
+    assert_eq!(vec.take(1), Some(String::from("b")));
+    assert_eq!(vec.get_value(0).map(|s| s.as_str()), Some("c"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_might_own_fd() {
+    use might_own::MightOwnFd;
+    use std::fs::File;
+    use std::io::Write;
+    use std::os::unix::io::{AsFd, AsRawFd, OwnedFd};
+
+    let mut file = File::create("/dev/null").unwrap();
+    let borrowed = MightOwnFd::unowned(file.as_fd());
+    assert!(!borrowed.is_owned());
+    assert_eq!(borrowed.as_raw_fd(), file.as_raw_fd());
+    let borrowed = borrowed.get_owned().unwrap_err();
+    drop(borrowed);
+    // the borrowed descriptor was not closed
+    file.write_all(b"still open").unwrap();
+
+    let owned = MightOwnFd::from(OwnedFd::from(file));
+    let mut file = File::from(owned.get_owned().unwrap());
+    file.write_all(b"still open").unwrap();
+}
+
+#[test]
+fn test_might_own_path() {
+    use std::ffi::{OsStr, OsString};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use MightOwn;
+
+    fn exists<P: AsRef<Path>>(path: P) -> bool {
+        fs::metadata(path).is_ok()
+    }
+
+    let owned: MightOwn<Path> = PathBuf::from("/").into();
+    assert!(exists(&owned));
+    assert_eq!(owned.into_path_buf(), PathBuf::from("/"));
+
+    let mut buf = PathBuf::from("/definitely/not/here");
+    let borrowed = MightOwn::from(&mut *buf);
+    assert!(!exists(&borrowed));
+    assert_eq!(borrowed.into_path_buf(), Path::new("/definitely/not/here"));
+
+    let string: MightOwn<OsStr> = OsString::from("name").into();
+    assert_eq!(string.as_os_str(), "name");
+    assert_eq!(string.into_os_string(), OsString::from("name"));
+}
+
+#[test]
+fn test_drop_queue() {
+    use std::rc::Rc;
+    use DropQueue;
+
+    let rc = Rc::new(());
+    let mut vec = SafeUninitializedVec::new(3);
+    for i in 0..3 {
+        vec.set_value(i, rc.clone());
+    }
+    let mut queue = DropQueue::with_capacity(2);
+    queue.park(vec.take(0).unwrap()).unwrap();
+    queue.park(vec.take(1).unwrap()).unwrap();
+    let rejected = queue.park(vec.take(2).unwrap()).unwrap_err().into_inner();
+    assert!(queue.is_full());
+    assert_eq!(Rc::strong_count(&rc), 4);
+
+    assert_eq!(queue.flush(), 2);
+    drop(rejected);
+    assert_eq!(Rc::strong_count(&rc), 1);
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn test_ordered_drops() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use OrderedDrops;
+
+    struct Logged(&'static str, Rc<RefCell<Vec<&'static str>>>);
+
+    impl Drop for Logged {
+        fn drop(&mut self) {
+            self.1.borrow_mut().push(self.0);
+        }
+    }
+
+    let log = Rc::new(RefCell::new(Vec::new()));
+    let mut drops = OrderedDrops::with_capacity(4);
+    drops.register(2, Logged("device", log.clone())).unwrap();
+    drops.register(0, Logged("buffer", log.clone())).unwrap();
+    let view = drops.register(0, Logged("view", log.clone())).unwrap();
+    drops.register(1, Logged("queue", log.clone())).unwrap();
+    assert!(drops.register(3, Logged("extra", log.clone())).is_err());
+    assert_eq!(drops.remove(view).map(|v| v.0), Some("view"));
+    assert_eq!(drops.len(), 3);
+    log.borrow_mut().clear();
+
+    drop(drops);
+    assert_eq!(*log.borrow(), ["buffer", "queue", "device"]);
+}
+
+#[test]
+fn test_counted_drop() {
+    use counted;
+    use {CountedDrop, DropCounter};
+
+    let counter = DropCounter::new();
+    let mut vec = SafeUninitializedVec::new(4);
+    for i in 0..3 {
+        vec.set_value(i, counter.wrap(i));
+    }
+    vec.set_value(1, counter.wrap(10));
+    assert_eq!(counter.live(), 3);
+    assert_eq!(vec.take(2).map(CountedDrop::into_inner), Some(2));
+    let cloned = vec.get_value(0).cloned().unwrap();
+    assert_eq!(counter.created(), 5);
+    drop(vec);
+    assert_eq!(counter.live(), 1);
+    drop(cloned);
+    counter.assert_all_dropped();
+
+    let global = counted::global_created();
+    let val = CountedDrop::new(());
+    assert!(counted::global_created() > global);
+    drop(val);
+}
+
+#[test]
+fn test_tracked_static() {
+    use error::Error;
+    use std::thread;
+    use {DropCounter, TrackedStatic};
+
+    static NAME: TrackedStatic<String> = TrackedStatic::new();
+    assert!(NAME.get().is_none());
+    NAME.init(String::from("global")).unwrap();
+    assert!(NAME.init(String::from("again")).is_err());
+    let len = thread::spawn(|| NAME.get().unwrap().len()).join().unwrap();
+    assert_eq!(len, 6);
+
+    let counter = DropCounter::new();
+    let slot = TrackedStatic::new();
+    slot.init(counter.wrap(())).unwrap_or_else(|_| panic!());
+    let guard = slot.get().unwrap();
+    assert_eq!(slot.deinit().unwrap_err(), Error::InUse { borrows: 1 });
+    drop(guard);
+    assert_eq!(slot.deinit(), Ok(true));
+    assert!(slot.get().is_none());
+    assert!(slot.is_dropped());
+    drop(slot);
+    counter.assert_all_dropped();
+}
+
+#[test]
+fn test_tracked_cstring_buf() {
+    use std::ptr;
+    use TrackedCStringBuf;
+
+    let mut buf = TrackedCStringBuf::with_capacity(16);
+    buf.push_str("key=").unwrap();
+    assert_eq!(buf.push_bytes(b"a\0b").unwrap_err().nul_position(), 5);
+    assert_eq!(buf.as_bytes(), b"key=");
+
+    // written the way a C function would, with a terminator
+    unsafe {
+        ptr::copy_nonoverlapping(b"value\0junk".as_ptr(), buf.spare_ptr(), 10);
+        assert_eq!(buf.commit_terminated(10), Some(5));
+    }
+    assert_eq!(buf.as_c_str().to_bytes(), b"key=value");
+    unsafe {
+        ptr::copy_nonoverlapping(b"!\0".as_ptr(), buf.spare_ptr(), 2);
+        assert!(buf.commit(2).is_err());
+        buf.commit(1).unwrap();
+    }
+    assert_eq!(buf.into_c_string().as_bytes(), b"key=value!");
+}
+
+#[test]
+fn test_might_own_slice() {
+    use MightOwnSlice;
+
+    let mut input = *b"GET /a%20b HTTP";
+    let (method, rest) = input.split_at_mut(4);
+    let mut buf = MightOwnSlice::new();
+    buf.push_borrowed(method);
+    buf.push_borrowed(rest);
+    assert_eq!(buf.len(), 15);
+    assert_eq!(buf.get(4), Some(&b'/'));
+
+    // decode the escape into an owned patch
+    buf.patch(6..9, b" ".to_vec());
+    let decoded: Vec<u8> = buf.iter().cloned().collect();
+    assert_eq!(decoded, b"GET /a b HTTP");
+    assert_eq!(buf.is_owned(6), Some(true));
+    assert_eq!(buf.is_owned(7), Some(false));
+    assert_eq!(buf.segments().count(), 4);
+
+    *buf.get_mut(0).unwrap() = b'P';
+    buf.patch(1..3, b"UT".to_vec());
+    buf.patch(13..13, b"/1.1".to_vec());
+    let decoded: Vec<u8> = buf.iter().cloned().collect();
+    assert_eq!(decoded, b"PUT /a b HTTP/1.1");
+    drop(buf);
+    assert_eq!(&input[..4], b"PET ");
+}
+
+#[test]
+fn test_uninit_vec_collect_into() {
+    use std::panic;
+    use std::rc::Rc;
+    use uninitialized::Collected;
+
+    let mut vec = SafeUninitializedVec::new(6);
+    vec.set_value(4, 40);
+    let mut items = 0..10;
+    assert_eq!(
+        vec.collect_into(&mut items),
+        Collected {
+            written: 4,
+            exhausted: false
+        }
+    );
+    assert_eq!(items.next(), Some(4));
+    assert_eq!(vec.get_value(3), Some(&3));
+
+    vec.take(1);
+    vec.take(2);
+    assert_eq!(
+        vec.collect_into(&mut vec![10, 20].into_iter()),
+        Collected {
+            written: 2,
+            exhausted: true
+        }
+    );
+    assert_eq!(vec.get_value(2), Some(&20));
+    assert_eq!(vec.collect_into(&mut (0..1)).written, 1);
+    assert_eq!(vec.get_value(5), Some(&0));
+
+    // values written before the iterator panics are kept and counted
+    let counter = Rc::new(());
+    let mut vec = SafeUninitializedVec::new(4);
+    let mut items = (0..4).map(|i| {
+        if i == 2 {
+            panic!("iterator failed");
+        }
+        counter.clone()
+    });
+    let collect = panic::catch_unwind(panic::AssertUnwindSafe(|| vec.collect_into(&mut items)));
+    assert!(collect.is_err());
+    assert_eq!(vec.count_initialized(), 2);
+    assert!(vec.get_value(1).is_some());
+    assert_eq!(Rc::strong_count(&counter), 3);
+    drop(vec);
+    assert_eq!(Rc::strong_count(&counter), 1);
+}
+
+#[test]
+fn test_might_own_watch() {
+    use std::thread;
+    use MightOwn;
+
+    let owned = MightOwn::owned(Box::new(5));
+    let watch = owned.watch();
+    let second = owned.watch();
+    assert!(watch.is_alive());
+    assert_eq!(*owned.get_owned().unwrap(), 5);
+    assert!(!watch.is_alive());
+    assert!(!thread::spawn(move || second.is_alive()).join().unwrap());
+
+    let mut val = 1;
+    let borrowed = MightOwn::unowned(&mut val);
+    let watch = borrowed.watch();
+    drop(borrowed);
+    assert!(!watch.is_alive());
+}
+
+#[test]
+fn test_diagnostics_cycles() {
+    use diagnostics::{self, Trace, Tracer, TrackedArc, TrackedRc};
+    use std::cell::RefCell;
+
+    struct Node {
+        next: RefCell<Option<TrackedRc<Node>>>,
+    }
+
+    impl Trace for Node {
+        fn trace(&self, tracer: &mut Tracer) {
+            self.next.trace(tracer);
+        }
+    }
+
+    let new_node = || {
+        TrackedRc::new_traced(Node {
+            next: RefCell::new(None),
+        })
+    };
+    let a = new_node();
+    let b = new_node();
+    *a.next.borrow_mut() = Some(b.clone());
+    let report = diagnostics::report();
+    assert_eq!(report.sites[0].live, 2);
+    assert!(report.unreachable.is_empty());
+
+    // closing the cycle and dropping the outside pointers leaks both nodes
+    *b.next.borrow_mut() = Some(a.clone());
+    drop(a);
+    drop(b);
+    let arc = TrackedArc::new(5);
+    let report = diagnostics::report();
+    let leaked: Vec<_> = report
+        .unreachable
+        .iter()
+        .filter(|alloc| alloc.type_name.ends_with("Node"))
+        .collect();
+    assert_eq!(leaked.len(), 2);
+    assert!(report
+        .sites
+        .iter()
+        .any(|site| site.type_name == "i32" && site.site.file().ends_with("mod.rs")));
+    assert_eq!(*arc, 5);
+}
+
+#[cfg(feature = "registry")]
+#[test]
+fn test_registry_report() {
+    use registry::ContainerReport;
+
+    struct Marker;
+
+    let mut vec = SafeUninitializedVec::from_vec(vec![Marker, Marker], 5);
+    vec.take(0);
+    let find = || {
+        ::report()
+            .containers
+            .into_iter()
+            .filter(|c| c.type_name.ends_with("Marker"))
+            .collect::<Vec<ContainerReport>>()
+    };
+    let found = find();
+    assert_eq!(found.len(), 1);
+    assert_eq!(
+        (found[0].len, found[0].initialized, found[0].bytes),
+        (5, 1, 0)
+    );
+
+    let report = ::report();
+    let json = report.to_json();
+    assert!(json.starts_with("{\"total_bytes\":"));
+    assert!(json.contains("Marker\",\"len\":5,\"initialized\":1,\"bytes\":0,\"region\":null}"));
+    drop(vec);
+    assert!(find().is_empty());
+}
+
+#[test]
+fn test_uninit_vec_disjoint_ranges() {
+    use error::Error;
+
+    let mut vec = SafeUninitializedVec::from_vec((0..4).collect(), 10);
+    {
+        let [mut low, mut high] = vec.get_disjoint_ranges_mut([0..5, 5..10]).unwrap();
+        for i in 0..5 {
+            if let Some(val) = low.take(i) {
+                high.set_value(i, val * 10);
+            }
+        }
+        assert_eq!(low.count_initialized(), 0);
+    }
+    assert_eq!(vec.get_value(8), Some(&30));
+    assert!(vec.get_value(0).is_none());
+    assert_eq!(vec.mask().count_ones(), 4);
+
+    assert_eq!(
+        vec.get_disjoint_ranges_mut([6..8, 0..2, 7..9]).err(),
+        Some(Error::Overlap {
+            first: 6..8,
+            second: 7..9
+        })
+    );
+    assert_eq!(
+        vec.get_disjoint_ranges_mut([0..1, 3..11]).err(),
+        Some(Error::OutOfBounds { index: 11, len: 10 })
+    );
+    assert!(vec.get_disjoint_ranges_mut([2..2, 0..10]).is_ok());
+}
+
+#[test]
+fn test_uninit_vec_iter() {
+    let mut vec = SafeUninitializedVec::from_vec(vec![0, 1, 2, 3], 2);
+    vec.take(0);
+    let collected: Vec<(usize, &i32)> = vec.iter().collect();
+    assert_eq!(collected, [(1, &1), (2, &2), (3, &3)]);
+
+    let mut vec = SafeUninitializedVec::new(100);
+    vec.set_value(70, "a");
+    vec.set_value(7, "b");
+    let mut found = Vec::new();
+    for (i, val) in &vec {
+        found.push((i, *val));
+    }
+    assert_eq!(found, [(7, "b"), (70, "a")]);
+}
+
+#[test]
+fn test_expiring_vec() {
+    use ExpiringVec;
+
+    let mut cache = ExpiringVec::new(4);
+    cache.set_value(0, "old", 10);
+    cache.set_value(1, "touched", 10);
+    cache.set_value(2, "new", 30);
+    assert!(cache.touch(1, 25));
+    assert!(!cache.touch(3, 25));
+
+    assert_eq!(cache.expire_older_than(20), 1);
+    assert!(cache.get_value(0).is_none());
+    assert_eq!(cache.stamp(1), Some(25));
+    assert_eq!(cache.expire_older_than(31), 2);
+    assert_eq!(cache.into_inner().mask().count_ones(), 0);
+}
+
+#[test]
+fn test_once_array() {
+    use std::sync::Arc;
+    use std::thread;
+    use OnceArray;
+
+    let array = Arc::new(OnceArray::new(100));
+    let threads: Vec<_> = (0..4)
+        .map(|t| {
+            let array = array.clone();
+            thread::spawn(move || (0..100).filter(|&i| array.set(i, (t, i)).is_ok()).count())
+        })
+        .collect();
+    let won: usize = threads.into_iter().map(|t| t.join().unwrap()).sum();
+    assert_eq!(won, 100);
+    assert_eq!(array.count_set(), 100);
+    assert!((0..100).all(|i| array.get(i).unwrap().1 == i));
+
+    let mut array = Arc::try_unwrap(array).ok().unwrap();
+    assert_eq!(array.set(5, (9, 9)), Err((9, 9)));
+    let old = array.take(5).unwrap();
+    assert_eq!(old.1, 5);
+    assert!(array.set(5, (9, 9)).is_ok());
+    assert_eq!(array.get(5), Some(&(9, 9)));
+}
+
+#[cfg(feature = "registry")]
+#[test]
+fn test_registry_regions() {
+    use registry::{self, RegionReport};
+    use HandleVec;
+
+    let totals = |name| {
+        registry::region_totals()
+            .into_iter()
+            .find(|r: &RegionReport| r.region == Some(name))
+    };
+    let mut vec = SafeUninitializedVec::<u64>::new(8);
+    vec.set_value(0, 1);
+    vec.set_region("test-regions-cache");
+    let handles = registry::in_region("test-regions-net", || {
+        let mut handles = HandleVec::new(4);
+        handles.set_value(1, 5u32);
+        handles
+    });
+    assert_eq!(vec.region(), Some("test-regions-cache"));
+    let cache = totals("test-regions-cache").unwrap();
+    assert_eq!(
+        (cache.containers, cache.initialized, cache.bytes),
+        (1, 1, 64)
+    );
+    assert!(totals("test-regions-net").unwrap().initialized >= 1);
+
+    let other = SafeUninitializedVec::<u8>::new(1);
+    assert_eq!(other.region(), None);
+    drop(handles);
+    assert!(totals("test-regions-net").is_none());
+}
+
+#[test]
+fn test_once_array_snapshot() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use OnceArray;
+
+    let array = OnceArray::new(1000);
+    for i in 0..10 {
+        array.set(i, i).unwrap();
+    }
+    let done = AtomicBool::new(false);
+    thread::scope(|scope| {
+        scope.spawn(|| {
+            for i in 10..1000 {
+                array.set(i, i).unwrap();
+            }
+            done.store(true, Ordering::Release);
+        });
+        let snapshot = array.read_snapshot();
+        let count = snapshot.count_set();
+        assert!(count >= 10);
+        // the snapshot does not change while the writer keeps going
+        while !done.load(Ordering::Acquire) {
+            assert_eq!(snapshot.iter().count(), count);
+        }
+        assert!(snapshot.iter().all(|(i, &val)| i == val));
+    });
+    assert_eq!(array.read_snapshot().count_set(), 1000);
+}
+
+#[test]
+fn test_uninit_vec_debug() {
+    let mut vec = SafeUninitializedVec::from_vec(vec![1, 2, 3], 2);
+    vec.take(0);
+    assert_eq!(
+        format!("{:?}", vec),
+        "SafeUninitializedVec { len: 3, initialized: 2, runs: [1..3], \
+         values: [<uninit>, 2, 3] }"
+    );
+
+    // long vectors are cut off
+    let mut vec = SafeUninitializedVec::from_vec((0..100).collect(), 100);
+    for i in (0..100).step_by(3) {
+        vec.take(i);
+    }
+    let debug = format!("{:?}", vec);
+    assert!(debug.starts_with("SafeUninitializedVec { len: 100, initialized: 66, runs: [1..3, "));
+    assert!(debug.contains("19..21, 22..24, ..]"));
+    assert!(debug.ends_with(
+        "values: [<uninit>, 1, 2, <uninit>, 4, 5, <uninit>, 7, 8, <uninit>, 10, 11, \
+             <uninit>, 13, 14, <uninit>, ..] }"
+    ));
+    let vec = SafeUninitializedVec::<String>::new(2);
+    let debug = format!("{:#?}", vec);
+    assert!(debug.contains("runs: [],\n"));
+    assert!(debug.contains("<uninit>,\n"));
+}
+
+#[test]
+fn test_block_pool() {
+    use std::mem;
+    use std::panic;
+    use std::thread;
+    use {BlockPool, DropCounter};
+
+    let counter = DropCounter::new();
+    let pool = BlockPool::new(70);
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                let blocks: Vec<_> = (0..10)
+                    .map(|_| pool.acquire(counter.wrap(0)).unwrap())
+                    .collect();
+                assert!(blocks.iter().all(|b| b.index() < 70));
+            });
+        }
+    });
+    assert_eq!(pool.in_use(), 0);
+
+    let mut blocks: Vec<_> = (0..70)
+        .map(|i| pool.acquire(counter.wrap(i)).unwrap())
+        .collect();
+    assert!(pool.acquire(counter.wrap(70)).is_err());
+    **blocks[3] += 100;
+    let block = blocks.swap_remove(3);
+    assert_eq!(BlockPool::release(block).into_inner(), 103);
+    assert_eq!(pool.acquire(counter.wrap(5)).unwrap().index(), 3);
+    drop(blocks);
+    assert_eq!(pool.in_use(), 0);
+    counter.assert_all_dropped();
+
+    // leaking a guard is caught when the pool is dropped
+    let pool = BlockPool::new(2);
+    mem::forget(pool.acquire(counter.wrap(0)).unwrap());
+    assert!(panic::catch_unwind(panic::AssertUnwindSafe(|| drop(pool))).is_err());
+    counter.assert_all_dropped();
+}
+
+#[test]
+fn test_uninit_vec_clone() {
+    use DropCounter;
+
+    let counter = DropCounter::new();
+    let mut vec = SafeUninitializedVec::from_vec(vec![counter.wrap(1), counter.wrap(2)], 5);
+    vec.set_value(3, counter.wrap(4));
+    vec.take(0);
+    let clone = vec.clone();
+    assert_eq!(clone.mask(), vec.mask());
+    assert!(clone.get_value(0).is_none());
+    assert_eq!(**clone.get_value(1).unwrap(), 2);
+    assert_eq!(**clone.get_value(3).unwrap(), 4);
+    assert_eq!(counter.live(), 4);
+    drop((vec, clone));
+
+    // the untracked tail is cloned too
+    let vec = SafeUninitializedVec::from_vec(vec![counter.wrap(1), counter.wrap(2)], 1);
+    let clone = vec.clone();
+    assert_eq!(clone.mask().len(), 1);
+    assert_eq!(clone.into_vec().unwrap().len(), 2);
+    drop(vec);
+    counter.assert_all_dropped();
+}
+
+#[test]
+fn test_uninit_vec_eq() {
+    let mut a = SafeUninitializedVec::new(4);
+    a.set_value(1, 10);
+    a.set_value(2, 20);
+    let mut b = SafeUninitializedVec::from_vec(vec![0, 10, 20], 4);
+    assert_ne!(a, b);
+    b.take(0);
+    assert_eq!(a, b);
+    b.set_value(2, 21);
+    assert_ne!(a, b);
+    assert_ne!(a, SafeUninitializedVec::new(3));
+
+    // the untracked tail compares as initialized
+    let tail = SafeUninitializedVec::from_vec(vec![1, 2, 3], 1);
+    let mut tracked = SafeUninitializedVec::new(3);
+    for i in 0..3 {
+        tracked.set_value(i, i + 1);
+    }
+    assert_eq!(tail, tracked);
+}
+
+#[test]
+fn test_uninit_vec_lengths() {
+    let vec: SafeUninitializedVec<u32> = SafeUninitializedVec::new(0);
+    assert!(vec.is_empty());
+
+    let mut vec = SafeUninitializedVec::from_vec(Vec::with_capacity(16), 4);
+    vec.set_value(0, 1);
+    assert_eq!((vec.len(), vec.tracked_len()), (4, 4));
+    assert!(vec.capacity() >= 16);
+
+    let mut vec = SafeUninitializedVec::from_vec(vec![1, 2, 3, 4], 2);
+    assert_eq!((vec.len(), vec.tracked_len()), (4, 2));
+    assert_eq!(vec.get_value(3), Some(&4));
+    vec.set_value(3, 5);
+    assert_eq!(vec.take(3), Some(5));
+    assert_eq!((vec.len(), vec.tracked_len()), (4, 4));
+    assert_eq!(vec.get_value(3), None);
+}
+
+#[test]
+fn test_uninit_vec_counts() {
+    let mut vec = SafeUninitializedVec::from_vec(vec![1, 2, 3], 5);
+    assert_eq!((vec.count_initialized(), vec.count_uninitialized()), (3, 2));
+    vec.take(0);
+    {
+        let [mut left, mut right] = vec.get_disjoint_ranges_mut([0..2, 2..5]).unwrap();
+        left.set_value(0, 0);
+        right.set_value(1, 4);
+        right.set_value(2, 5);
+        right.take(0);
+    }
+    assert_eq!(vec.count_initialized(), 4);
+    assert!(!vec.is_fully_initialized());
+    vec.set_value(2, 3);
+    assert!(vec.is_fully_initialized());
+    assert_eq!(vec.into_vec().unwrap(), vec![0, 2, 3, 4, 5]);
+
+    let vec = SafeUninitializedVec::from_vec(vec![1, 2, 3], 1);
+    assert!(vec.is_fully_initialized());
+    assert_eq!(vec.count_initialized(), 3);
+}
+
+#[test]
+fn test_next_uninitialized() {
+    let mut vec = SafeUninitializedVec::new(40);
+    assert_eq!(vec.first_uninitialized(), Some(0));
+    for i in (0..40).filter(|&i| i != 3 && i != 21 && i != 39) {
+        vec.set_value(i, i);
+    }
+    assert_eq!(vec.first_uninitialized(), Some(3));
+    assert_eq!(vec.next_uninitialized_after(3), Some(21));
+    assert_eq!(vec.next_uninitialized_after(21), Some(39));
+    assert_eq!(vec.next_uninitialized_after(39), None);
+    assert_eq!(vec.next_uninitialized_after(100), None);
+    assert_eq!(vec.mask().ones().filter(|&i| i > 20).count(), 17);
+    assert_eq!(vec.mask().next_one(39), None);
+
+    let vec = SafeUninitializedVec::from_vec(vec![1, 2], 2);
+    assert_eq!(vec.first_uninitialized(), None);
+}
+
+#[test]
+fn test_uninitialized_indices() {
+    let mut vec = SafeUninitializedVec::from_vec(vec![0; 3], 20);
+    for i in (5..20).step_by(2) {
+        vec.set_value(i, i);
+    }
+    let holes: Vec<usize> = vec.uninitialized_indices().collect();
+    assert_eq!(holes, vec![3, 4, 6, 8, 10, 12, 14, 16, 18]);
+    for i in holes {
+        vec.set_value(i, i);
+    }
+    assert_eq!(vec.uninitialized_indices().next(), None);
+}
+
+#[test]
+fn test_uninit_vec_push_pop() {
+    use DropCounter;
+
+    let counter = DropCounter::new();
+    let mut vec = SafeUninitializedVec::new(2);
+    vec.set_value(0, counter.wrap(0));
+    vec.push(counter.wrap(2));
+    vec.push(counter.wrap(3));
+    assert_eq!((vec.len(), vec.count_initialized()), (4, 3));
+    assert_eq!(vec.pop().unwrap().into_inner(), 3);
+    assert_eq!(vec.pop().unwrap().into_inner(), 2);
+    assert!(vec.pop().is_none());
+    assert_eq!(vec.len(), 1);
+    assert!(vec.pop().is_some());
+    assert!(vec.pop().is_none());
+    assert!(vec.is_empty());
+
+    // the untracked tail is folded in before pushing
+    let mut vec = SafeUninitializedVec::from_vec(vec![counter.wrap(0), counter.wrap(1)], 1);
+    vec.push(counter.wrap(2));
+    assert_eq!((vec.len(), vec.tracked_len()), (3, 3));
+    assert_eq!(**vec.get_value(2).unwrap(), 2);
+    drop(vec);
+    counter.assert_all_dropped();
+}
+
+#[test]
+fn test_uninit_vec_resize() {
+    use DropCounter;
+
+    let counter = DropCounter::new();
+    let mut vec = SafeUninitializedVec::from_vec(vec![counter.wrap(0), counter.wrap(1)], 4);
+    vec.resize(8);
+    assert_eq!((vec.len(), vec.count_initialized()), (8, 2));
+    vec.set_value(7, counter.wrap(7));
+    vec.resize(1);
+    assert_eq!((vec.len(), vec.count_initialized()), (1, 1));
+    assert_eq!(counter.live(), 1);
+    vec.resize(3);
+    assert!(vec.get_value(1).is_none());
+    drop(vec);
+    counter.assert_all_dropped();
+}
+
+#[test]
+fn test_uninit_vec_truncate_clear() {
+    use DropCounter;
+
+    let counter = DropCounter::new();
+    let mut vec = SafeUninitializedVec::new(6);
+    for i in (0..6).step_by(2) {
+        vec.set_value(i, counter.wrap(i));
+    }
+    let capacity = vec.capacity();
+    vec.truncate(10);
+    assert_eq!(vec.len(), 6);
+    vec.truncate(3);
+    assert_eq!((vec.len(), counter.live()), (3, 2));
+    vec.clear();
+    assert!(vec.is_empty());
+    assert_eq!(vec.capacity(), capacity);
+    counter.assert_all_dropped();
+}
+
+#[test]
+fn test_uninit_vec_extend() {
+    let mut vec = SafeUninitializedVec::new(3);
+    vec.set_value(1, 1);
+    vec.extend(3..6);
+    assert_eq!((vec.len(), vec.count_initialized()), (6, 4));
+    assert_eq!(vec.get_value(5), Some(&5));
+    vec.set_value(0, 0);
+    vec.set_value(2, 2);
+    assert_eq!(vec.into_vec().unwrap(), (0..6).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_uninit_vec_fill() {
+    use DropCounter;
+
+    let counter = DropCounter::new();
+    let mut vec = SafeUninitializedVec::from_vec(vec![counter.wrap(0)], 3);
+    vec.fill(counter.wrap(7));
+    assert!(vec.is_fully_initialized());
+    assert_eq!(counter.live(), 3);
+    let mut next = 0;
+    vec.fill_with(|| {
+        next += 1;
+        counter.wrap(next)
+    });
+    assert_eq!(counter.live(), 3);
+    let vals: Vec<_> = vec
+        .into_vec()
+        .unwrap()
+        .into_iter()
+        .map(|v| v.into_inner())
+        .collect();
+    assert_eq!(vals, vec![1, 2, 3]);
+    counter.assert_all_dropped();
+}
+
+#[test]
+fn test_init_missing_with() {
+    let mut vec = SafeUninitializedVec::new(6);
+    vec.set_value(1, 10);
+    vec.set_value(4, 40);
+    let mut calls = Vec::new();
+    vec.init_missing_with(|i| {
+        calls.push(i);
+        i
+    });
+    assert_eq!(calls, vec![0, 2, 3, 5]);
+    assert_eq!(vec.into_vec().unwrap(), vec![0, 10, 2, 3, 40, 5]);
+}
+
+#[test]
+fn test_uninit_vec_set_range() {
+    use std::panic;
+
+    let mut vec = SafeUninitializedVec::new(6);
+    vec.set_value(2, String::from("old"));
+    let src: Vec<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+    vec.set_range(1, &src);
+    assert_eq!(vec.count_initialized(), 3);
+    assert_eq!(vec.get_value(2).map(|s| &s[..]), Some("b"));
+    assert!(vec.get_value(4).is_none());
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| vec.set_range(4, &src)));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_copy_from_slice_at() {
+    let mut vec = SafeUninitializedVec::new(10);
+    vec.set_value(3, 99);
+    vec.copy_from_slice_at(2, &[2, 3, 4]);
+    assert_eq!(vec.count_initialized(), 3);
+    assert_eq!(vec.get_value(3), Some(&3));
+    vec.copy_from_slice_at(0, &[0, 1]);
+    vec.copy_from_slice_at(5, &[5, 6, 7, 8, 9]);
+    assert_eq!(vec.into_vec().unwrap(), (0..10).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_uninit_vec_remove() {
+    use DropCounter;
+
+    let counter = DropCounter::new();
+    let mut vec = SafeUninitializedVec::from_vec(vec![counter.wrap(0), counter.wrap(1)], 4);
+    vec.set_value(3, counter.wrap(3));
+    assert_eq!(vec.remove(0).unwrap().into_inner(), 0);
+    assert!(vec.remove(1).is_none());
+    assert_eq!(vec.len(), 2);
+    assert_eq!(**vec.get_value(0).unwrap(), 1);
+    assert_eq!(**vec.get_value(1).unwrap(), 3);
+    assert!(vec.is_fully_initialized());
+    drop(vec);
+    counter.assert_all_dropped();
+}
+
+#[test]
+fn test_uninit_vec_insert() {
+    let mut vec = SafeUninitializedVec::new(2);
+    vec.set_value(1, 2);
+    vec.insert(0, 0);
+    vec.insert(3, 3);
+    assert_eq!(vec.len(), 4);
+    assert!(vec.get_value(1).is_none());
+    vec.insert(1, 1);
+    assert_eq!(vec.remove(2), None);
+    assert_eq!(vec.into_vec().unwrap(), vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn test_uninit_vec_drain() {
+    use DropCounter;
+
+    let counter = DropCounter::new();
+    let mut vec = SafeUninitializedVec::from_vec(vec![counter.wrap(0), counter.wrap(1)], 5);
+    vec.set_value(3, counter.wrap(3));
+    let drained: Vec<(usize, i32)> = vec.drain().map(|(i, v)| (i, v.into_inner())).collect();
+    assert_eq!(drained, vec![(0, 0), (1, 1), (3, 3)]);
+    assert_eq!((vec.len(), vec.count_initialized()), (5, 0));
+
+    vec.fill_with(|| counter.wrap(7));
+    assert_eq!(vec.drain().next().map(|(i, _)| i), Some(0));
+    assert_eq!(counter.live(), 0);
+    assert_eq!(vec.first_uninitialized(), Some(0));
+    drop(vec);
+    counter.assert_all_dropped();
+}
+
+#[test]
+fn test_uninit_vec_retain() {
+    use DropCounter;
+
+    let counter = DropCounter::new();
+    let mut vec = SafeUninitializedVec::from_vec((0..4).map(|i| counter.wrap(i)).collect(), 6);
+    vec.set_value(5, counter.wrap(5));
+    let mut seen = Vec::new();
+    vec.retain(|i, v| {
+        seen.push(i);
+        **v += 10;
+        i % 2 == 1
+    });
+    assert_eq!(seen, vec![0, 1, 2, 3, 5]);
+    assert_eq!(counter.live(), 3);
+    let kept: Vec<(usize, i32)> = vec.iter().map(|(i, v)| (i, **v)).collect();
+    assert_eq!(kept, vec![(1, 11), (3, 13), (5, 15)]);
+    drop(vec);
+    counter.assert_all_dropped();
+}
+
+#[test]
+fn test_into_options() {
+    let mut vec = SafeUninitializedVec::from_vec(vec![String::from("a")], 3);
+    vec.set_value(2, String::from("c"));
+    let options = vec.into_options();
+    assert_eq!(
+        options,
+        vec![Some(String::from("a")), None, Some(String::from("c"))]
+    );
+}
+
+#[test]
+fn test_from_options() {
+    let options = vec![None, Some(1), None, Some(3)];
+    let vec = SafeUninitializedVec::from_options(options.clone());
+    assert_eq!(vec.uninitialized_indices().collect::<Vec<_>>(), vec![0, 2]);
+    assert_eq!(vec.get_value(3), Some(&3));
+    assert_eq!(vec.into_options(), options);
+}
+
+#[test]
+fn test_uninit_vec_compact() {
+    use DropCounter;
+
+    let counter = DropCounter::new();
+    let mut vec = SafeUninitializedVec::from_vec(vec![counter.wrap(0), counter.wrap(1)], 6);
+    vec.take(0);
+    vec.set_value(4, counter.wrap(4));
+    let dense: Vec<i32> = vec.compact().into_iter().map(|v| v.into_inner()).collect();
+    assert_eq!(dense, vec![1, 4]);
+
+    let vec = SafeUninitializedVec::from_vec(vec![counter.wrap(0), counter.wrap(1)], 1);
+    assert_eq!(vec.compact().len(), 2);
+    counter.assert_all_dropped();
+}
+
+#[test]
+fn test_take_initialized_prefix() {
+    let mut vec = SafeUninitializedVec::new(6);
+    vec.copy_from_slice_at(0, &[0, 1]);
+    vec.set_value(3, 3);
+    assert_eq!(vec.initialized_prefix(), &[0, 1]);
+    assert_eq!(vec.take_initialized_prefix(), vec![0, 1]);
+    assert_eq!((vec.len(), vec.count_initialized()), (4, 1));
+    assert!(vec.take_initialized_prefix().is_empty());
+    vec.set_value(0, 2);
+    assert_eq!(vec.take_initialized_prefix(), vec![2, 3]);
+    assert_eq!(vec.len(), 2);
+}
+
+#[test]
+fn test_initialized_runs() {
+    let mut vec = SafeUninitializedVec::from_vec(vec![0, 1, 2, 3, 4, 5], 4);
+    vec.take(2);
+    let runs: Vec<(usize, &[i32])> = vec.initialized_runs().collect();
+    assert_eq!(runs, vec![(0, &[0, 1][..]), (3, &[3, 4, 5][..])]);
+
+    let mut vec = SafeUninitializedVec::from_vec(vec![0, 1, 2], 2);
+    vec.take(1);
+    let runs: Vec<(usize, &[i32])> = vec.initialized_runs().collect();
+    assert_eq!(runs, vec![(0, &[0][..]), (2, &[2][..])]);
+    vec.take(0);
+    vec.take(2);
+    assert_eq!(vec.initialized_runs().count(), 0);
+}
+
+#[test]
+fn test_uninit_vec_unchecked() {
+    let mut vec = SafeUninitializedVec::new(4);
+    for i in 0..4 {
+        unsafe {
+            vec.set_value_unchecked(i, i * 2);
+        }
+    }
+    assert!(vec.is_fully_initialized());
+    unsafe {
+        *vec.get_unchecked_mut(1) += 1;
+        assert_eq!(*vec.get_unchecked(1), 3);
+    }
+    assert_eq!(vec.into_vec().unwrap(), vec![0, 3, 4, 6]);
+}
+
+#[test]
+fn test_get_or_insert_with() {
+    fn fib(memo: &mut SafeUninitializedVec<u64>, n: usize) -> u64 {
+        if n < 2 {
+            return n as u64;
+        }
+        if let Some(&v) = memo.get_value(n) {
+            return v;
+        }
+        let v = fib(memo, n - 1) + fib(memo, n - 2);
+        *memo.get_or_insert_with(n, || v)
+    }
+
+    let mut memo = SafeUninitializedVec::new(50);
+    assert_eq!(fib(&mut memo, 49), 7_778_742_049);
+    *memo.get_or_insert_with(49, || unreachable!()) += 1;
+    assert_eq!(memo.get_value(49), Some(&7_778_742_050));
+    assert_eq!(memo.count_initialized(), 48);
+}
+
+#[test]
+fn test_take_moves_out_safely() {
+    use std::panic;
+    use DropCounter;
+
+    // types with invalid bit patterns were undefined behaviour with the old take
+    let mut chars = SafeUninitializedVec::new(2);
+    chars.set_value(0, 'x');
+    assert_eq!(chars.take(0), Some('x'));
+    assert_eq!(chars.take(0), None);
+    let mut refs: SafeUninitializedVec<&str> = SafeUninitializedVec::from_vec(vec!["a"], 2);
+    assert_eq!(refs.take(0), Some("a"));
+    assert_eq!(refs.take(1), None);
+
+    let counter = DropCounter::new();
+    let mut boxes = SafeUninitializedVec::new(3);
+    boxes.set_value(1, Box::new(counter.wrap(1)));
+    let taken = boxes.take(1).unwrap();
+    assert_eq!(counter.live(), 1);
+    drop(boxes);
+    assert_eq!(**taken, 1);
+    drop(taken);
+    counter.assert_all_dropped();
+
+    // a value whose destructor panics is dropped exactly once after being taken
+    struct PanicOnDrop;
+
+    impl Drop for PanicOnDrop {
+        fn drop(&mut self) {
+            panic!("dropped");
+        }
+    }
+
+    let mut vec = SafeUninitializedVec::new(2);
+    vec.set_value(0, PanicOnDrop);
+    let taken = vec.take(0).unwrap();
+    assert!(panic::catch_unwind(panic::AssertUnwindSafe(|| drop(taken))).is_err());
+    assert_eq!(vec.count_initialized(), 0);
+    drop(vec);
+}
+
+#[test]
+fn test_get_many_mut() {
+    let mut vec = SafeUninitializedVec::from_vec(vec![1, 2, 3, 4], 2);
+    vec.take(1);
+    {
+        let [a, b, c] = vec.get_many_mut([3, 0, 2]).unwrap();
+        std::mem::swap(a, b);
+        *c += 10;
+    }
+    assert!(vec.get_many_mut([0, 0]).is_none());
+    assert!(vec.get_many_mut([0, 1]).is_none());
+    assert!(vec.get_many_mut([0, 4]).is_none());
+    assert_eq!(vec.into_options(), vec![Some(4), None, Some(13), Some(1)]);
+}
+
+#[test]
+fn test_uninit_vec_split_at_mut() {
+    use std::thread;
+
+    let mut vec = SafeUninitializedVec::new(8);
+    {
+        let (left, right) = vec.split_at_mut(3);
+        let (mut middle, mut end) = right.split_at_mut(2);
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                let mut left = left;
+                for i in 0..left.len() {
+                    left.set_value(i, i);
+                }
+            });
+            scope.spawn(move || end.set_value(0, 5));
+        });
+        middle.set_value(1, 4);
+    }
+    assert_eq!(vec.count_initialized(), 5);
+    assert_eq!(
+        vec.uninitialized_indices().collect::<Vec<_>>(),
+        vec![3, 6, 7]
+    );
+}
+
+#[test]
+fn test_uninit_vec_subviews() {
+    let mut vec = SafeUninitializedVec::new(10);
+    {
+        let mut stage = vec.view_mut(4..8);
+        stage.set_value(0, 40);
+        stage.set_value(3, 70);
+    }
+    let view = vec.view(3..8);
+    assert_eq!(view.len(), 5);
+    assert_eq!(view.get_value(1), Some(&40));
+    assert!(!view.is_initialized(0));
+    assert_eq!(view.count_initialized(), 2);
+    assert_eq!(vec.get_value(7), Some(&70));
+
+    // views across several words, and over the untracked tail
+    let mut vec = SafeUninitializedVec::from_vec((0..300).collect(), 200);
+    for i in (0..200).filter(|i| i % 3 == 0) {
+        vec.take(i);
+    }
+    for (start, end) in [
+        (0, 300),
+        (5, 5),
+        (10, 150),
+        (63, 65),
+        (190, 250),
+        (250, 300),
+    ] {
+        let expected = (start..end).filter(|&i| vec.get_value(i).is_some()).count();
+        assert_eq!(vec.view(start..end).count_initialized(), expected);
+    }
+}
+
+#[test]
+fn test_compact_in_place() {
+    let mut vec = SafeUninitializedVec::from_vec(vec![0, 1, 2, 3, 4, 5], 4);
+    vec.take(0);
+    vec.take(2);
+    assert_eq!(vec.compact_in_place(), 4);
+    assert_eq!(vec.len(), 6);
+    assert_eq!(vec.initialized_prefix(), &[1, 3, 4, 5]);
+    assert_eq!(vec.uninitialized_indices().collect::<Vec<_>>(), vec![4, 5]);
+}
+
+#[test]
+fn test_uninit_vec_reserve_shrink() {
+    let mut vec = SafeUninitializedVec::new(4);
+    vec.set_value(0, 0u64);
+    vec.reserve(100);
+    assert!(vec.capacity() >= 104);
+    vec.reserve_exact(200);
+    assert!(vec.capacity() >= 204);
+    vec.truncate(2);
+    vec.shrink_to_fit();
+    assert_eq!(vec.capacity(), 2);
+    assert_eq!(vec.get_value(0), Some(&0));
+    assert!(vec.get_value(1).is_none());
+}
+
+#[test]
+fn test_spare_capacity_tracked() {
+    use error::Error;
+    use DropCounter;
+
+    let counter = DropCounter::new();
+    let mut vec = SafeUninitializedVec::new(1);
+    vec.reserve_exact(4);
+    {
+        let mut spare = vec.spare_capacity_tracked();
+        assert_eq!(spare.len(), 4);
+        spare.set_value(0, counter.wrap(10));
+        spare.set_value(2, counter.wrap(12));
+        assert_eq!(
+            spare.commit(2),
+            Err(Error::Uninitialized {
+                len: 2,
+                index: Some(1)
+            })
+        );
+        spare.set_value(1, counter.wrap(11));
+        spare.set_value(1, counter.wrap(11));
+        spare.commit(2).unwrap();
+        assert_eq!(spare.len(), 2);
+        assert_eq!(**spare.get_value(0).unwrap(), 12);
+        spare.set_value(1, counter.wrap(13));
+    }
+    assert_eq!(counter.live(), 2);
+    assert_eq!((vec.len(), vec.count_initialized()), (3, 2));
+    assert_eq!(**vec.get_value(2).unwrap(), 11);
+    drop(vec);
+    counter.assert_all_dropped();
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_uninit_vec_serde() {
+    use serde_json;
+
+    let mut vec = SafeUninitializedVec::from_vec(vec![String::from("a")], 3);
+    vec.set_value(2, String::from("c"));
+    let json = serde_json::to_string(&vec).unwrap();
+    assert_eq!(json, r#"["a",null,"c"]"#);
+    let restored: SafeUninitializedVec<String> = serde_json::from_str(&json).unwrap();
+    assert_eq!(restored, vec);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn test_par_init_and_iter() {
+    use rayon::iter::ParallelIterator;
+
+    let mut vec = SafeUninitializedVec::new(10_000);
+    vec.set_value(7, 0);
+    vec.par_init_with(|i| i * 2);
+    assert!(vec.is_fully_initialized());
+    assert_eq!(vec.get_value(7), Some(&0));
+    assert_eq!(vec.get_value(9_999), Some(&19_998));
+
+    vec.take(3);
+    vec.par_iter_mut().for_each(|(_, v)| *v += 1);
+    let sum: usize = vec.par_iter().map(|(_, v)| *v).sum();
+    assert_eq!(sum, (0..10_000).map(|i| i * 2 + 1).sum::<usize>() - 7 - 14);
+    assert_eq!(vec.par_iter().count(), 9_999);
+
+    let mut empty: SafeUninitializedVec<usize> = SafeUninitializedVec::new(0);
+    empty.par_init_with(|i| i);
+}
+
+#[test]
+fn test_uninit_vec_from_iter() {
+    let vec: SafeUninitializedVec<u32> = (0..5).collect();
+    assert!(vec.is_fully_initialized());
+    assert_eq!(vec.len(), 5);
+
+    let vec: SafeUninitializedVec<u32> = (0..5)
+        .map(|i| if i % 2 == 0 { Some(i) } else { None })
+        .collect();
+    assert_eq!(vec.uninitialized_indices().collect::<Vec<_>>(), vec![1, 3]);
+    assert_eq!(vec.get_value(4), Some(&4));
+}
+
+#[test]
+fn test_uninit_vec_default_with_capacity() {
+    let vec: SafeUninitializedVec<String> = Default::default();
+    assert!(vec.is_empty());
+
+    let mut vec = SafeUninitializedVec::with_capacity(8);
+    assert_eq!((vec.len(), vec.capacity()), (0, 8));
+    vec.push(1);
+    {
+        let mut spare = vec.spare_capacity_tracked();
+        assert_eq!(spare.len(), 7);
+        spare.set_value(0, 2);
+        spare.commit(1).unwrap();
+    }
+    assert_eq!(vec.into_vec().unwrap(), vec![1, 2]);
+}
+
+#[test]
+fn test_uninit_vec_send_sync() {
+    use std::cell::Cell;
+    use std::thread;
+    use uninitialized::{SpareCapacity, TrackedView, TrackedViewMut};
+
+    fn send<T: Send>() {}
+    fn sync<T: Sync>() {}
+
+    send::<SafeUninitializedVec<String>>();
+    sync::<SafeUninitializedVec<String>>();
+    send::<TrackedViewMut<'static, String>>();
+    sync::<TrackedViewMut<'static, String>>();
+    send::<TrackedView<'static, String>>();
+    sync::<TrackedView<'static, String>>();
+    send::<SpareCapacity<'static, String>>();
+    // a `Cell` can be sent but not shared, so a mutable view of one can be sent
+    send::<SafeUninitializedVec<Cell<u8>>>();
+    send::<TrackedViewMut<'static, Cell<u8>>>();
+
+    let mut vec = SafeUninitializedVec::new(4);
+    vec.set_value(0, String::from("a"));
+    let mut vec = thread::spawn(move || {
+        vec.set_value(1, String::from("b"));
+        vec
+    })
+    .join()
+    .unwrap();
+    assert_eq!(vec.count_initialized(), 2);
+    let (_, mut right) = vec.split_at_mut(2);
+    thread::scope(|scope| {
+        scope.spawn(move || right.set_value(0, String::from("c")));
+    });
+    assert_eq!(vec.count_initialized(), 3);
+}
+
+#[test]
+// the cached count is atomic, but it is never changed through a shared reference
+#[allow(clippy::mutable_key_type)]
+fn test_uninit_vec_hash() {
+    use std::collections::HashSet;
+
+    let mut a = SafeUninitializedVec::from_vec(vec![1, 2, 3], 1);
+    let mut b = SafeUninitializedVec::new(3);
+    b.copy_from_slice_at(0, &[1, 2, 3]);
+    let mut set = HashSet::new();
+    set.insert(b.clone());
+    assert!(set.contains(&a));
+    a.take(1);
+    assert!(!set.contains(&a));
+    b.take(1);
+    set.insert(b);
+    assert!(set.contains(&a));
+    assert_eq!(set.len(), 2);
+}
+
+#[test]
+fn test_uninitialized_error_report() {
+    use error::Error;
+    use std::error::Error as StdError;
+
+    let mut vec = SafeUninitializedVec::new(1_000);
+    vec.init_missing_with(|i| i);
+    vec.take(10);
+    vec.take(900);
+    let err = vec.into_vec().unwrap_err();
+    assert_eq!(err.first_uninitialized_index(), Some(10));
+    assert_eq!(err.missing_indices().collect::<Vec<_>>(), vec![10, 900]);
+    assert_eq!(
+        err.to_string(),
+        "2 of 1000 slots are uninitialized, the first at index 10"
+    );
+    let boxed: Box<dyn StdError> = Box::new(err);
+    assert!(boxed.source().is_none());
+    let mut vec = SafeUninitializedVec::<u8>::new(3);
+    vec.set_value(0, 0);
+    assert_eq!(
+        Error::from(vec.into_vec().unwrap_err()),
+        Error::Uninitialized {
+            len: 3,
+            index: Some(1)
+        }
+    );
+}
+
+#[test]
+fn test_uninit_vec_try_access() {
+    use error::Error;
+
+    let mut vec = SafeUninitializedVec::new(2);
+    assert!(vec.try_set_value(1, String::from("b")).is_ok());
+    let err = vec.try_set_value(2, String::from("c")).unwrap_err();
+    assert_eq!((err.index(), err.len()), (2, 2));
+    assert_eq!(
+        err.to_string(),
+        "index 2 is out of bounds for a vector of length 2"
+    );
+    assert_eq!(err.into_inner(), "c");
+    assert_eq!(vec.try_get_value(0).unwrap(), None);
+    assert_eq!(vec.try_get_value(1).unwrap().map(|s| &s[..]), Some("b"));
+    vec.try_get_value_mut(1).unwrap().unwrap().push('!');
+    let err = vec.try_get_value(5).unwrap_err();
+    assert_eq!(Error::from(err), Error::OutOfBounds { index: 5, len: 2 });
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn test_uninit_vec_zeroize() {
+    use std::mem::{self, MaybeUninit};
+    use std::slice;
+    use std::sync::atomic::AtomicU64;
+    use TrackedSlice;
+
+    let key = [0xa5u8; 16];
+    let mut vec = SafeUninitializedVec::new(3);
+    vec.set_value(0, key);
+    vec.set_value(1, key);
+    vec.set_value(2, key);
+    assert_eq!(vec.take(0), Some(key));
+    vec.set_value(1, [1; 16]);
+    assert_eq!(vec.pop(), Some(key));
+    let (ptr, len, cap, mask) = vec.into_raw_parts();
+    unsafe {
+        // the slots that were taken or popped were written with zeros, so they can be read
+        let bytes = slice::from_raw_parts(ptr as *const u8, cap * mem::size_of::<[u8; 16]>());
+        assert!(bytes[..16].iter().all(|&b| b == 0));
+        assert!(bytes[16..32].iter().all(|&b| b == 1));
+        assert!(bytes[32..48].iter().all(|&b| b == 0));
+        drop(SafeUninitializedVec::from_raw_parts(ptr, len, cap, mask));
+    }
+
+    // tracked slices scrub the caller's storage the same way
+    let mut storage = [MaybeUninit::<[u8; 16]>::uninit(); 2];
+    let mut flags = [AtomicU64::new(0)];
+    let mut slice = TrackedSlice::new(&mut storage, &mut flags);
+    slice.set_value(0, key);
+    slice.set_value(1, key);
+    assert_eq!(slice.take(0), Some(key));
+    slice.set_value(1, [1; 16]);
+    drop(slice);
+    unsafe {
+        assert_eq!(storage[0].assume_init(), [0; 16]);
+        assert_eq!(storage[1].assume_init(), [0; 16]);
+    }
+}
+
+#[test]
+fn test_init_mask_across_words() {
+    use InitMask;
+
+    let mut bits: Vec<bool> = (0..200).map(|i| i % 7 == 0 || i % 5 == 1).collect();
+    let mut mask: InitMask = bits.clone().into();
+    mask.insert(63, true);
+    bits.insert(63, true);
+    mask.insert(0, false);
+    bits.insert(0, false);
+    assert_eq!(mask.remove(128), bits.remove(128));
+    assert_eq!(
+        mask.set_range(60..140, false),
+        80 - bits[60..140].iter().filter(|&&bit| !bit).count()
+    );
+    for bit in &mut bits[60..140] {
+        *bit = false;
+    }
+    assert_eq!(Vec::<bool>::from(mask.clone()), bits);
+    assert_eq!(mask.count_ones(), bits.iter().filter(|&&bit| bit).count());
+    assert_eq!(
+        mask.rank(150),
+        bits[..150].iter().filter(|&&bit| bit).count()
+    );
+    assert_eq!(
+        mask.count_ones_in(30..170),
+        bits[30..170].iter().filter(|&&bit| bit).count()
+    );
+    assert_eq!(mask.count_ones_in(70..70), 0);
+    assert_eq!(
+        mask.next_one(60),
+        bits.iter().skip(60).position(|&bit| bit).map(|i| i + 60)
+    );
+    let starts = (0..bits.len()).filter(|&i| bits[i] && (i == 0 || !bits[i - 1]));
+    assert_eq!(
+        mask.runs().map(|run| run.start).collect::<Vec<_>>(),
+        starts.collect::<Vec<_>>()
+    );
+
+    let mut full = InitMask::repeat(true, 130);
+    assert_eq!(full.next_zero(0), None);
+    full.invert();
+    assert!(!full.any());
+    full.resize(140, true);
+    assert_eq!(full.next_one(0), Some(130));
+    assert_eq!(full.select(9), Some(139));
+    full.truncate(135);
+    assert_eq!(full, (0..135).map(|i| i >= 130).collect());
+}
+
+#[test]
+fn test_init_mask_summary() {
+    use InitMask;
+
+    let len = 1 << 20;
+    let mut mask = InitMask::repeat(true, len);
+    assert!(mask.all());
+    assert_eq!(mask.next_zero(0), None);
+    mask.set(len - 3, false);
+    mask.set(5000, false);
+    assert_eq!(mask.next_zero(0), Some(5000));
+    assert_eq!(mask.next_zero(5001), Some(len - 3));
+    assert_eq!(
+        mask.gaps().collect::<Vec<_>>(),
+        vec![5000..5001, len - 3..len - 2]
+    );
+    mask.set_range(0..len, false);
+    assert!(!mask.any());
+    mask.push(true);
+    assert_eq!(mask.next_one(0), Some(len));
+    assert_eq!(mask.clone().next_one(7), Some(len));
+
+    // views change the words without the summary, which is rebuilt on the next search
+    let mut vec = SafeUninitializedVec::new(len);
+    vec.fill(0u8);
+    {
+        let (mut left, mut right) = vec.split_at_mut(len / 2 + 1);
+        left.take(300_000);
+        right.take(100);
+    }
+    assert_eq!(vec.first_uninitialized(), Some(300_000));
+    assert_eq!(vec.next_uninitialized_after(300_000), Some(len / 2 + 101));
+    assert_eq!(vec.next_uninitialized_after(len / 2 + 101), None);
+}
+
+#[test]
+fn test_interval_tracking() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+    use InitMask;
+
+    let mut intervals = InitMask::new_intervals(1000);
+    let mut bits = InitMask::new(1000);
+    for mask in [&mut intervals, &mut bits] {
+        mask.set_range(10..500, true);
+        assert!(mask.set(20, false));
+        mask.set_range(600..700, true);
+        mask.insert(0, true);
+        assert!(mask.remove(101));
+        mask.push(true);
+        mask.swap(1, 600);
+    }
+    assert!(intervals.uses_intervals() && !bits.uses_intervals());
+    assert_eq!(intervals, bits);
+    let hash = |mask: &InitMask| {
+        let mut hasher = DefaultHasher::new();
+        mask.hash(&mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(hash(&intervals), hash(&bits));
+    assert_eq!(intervals.count_ones(), bits.count_ones());
+    assert_eq!(intervals.rank(650), bits.rank(650));
+    assert_eq!(intervals.select(400), bits.select(400));
+    assert_eq!(intervals.next_zero(11), bits.next_zero(11));
+    assert_eq!(!&intervals, !&bits);
+    assert_eq!(&intervals & &!&bits, InitMask::new(1001));
+    assert_eq!(intervals.split_off(300), bits.split_off(300));
+    assert_eq!(intervals.gaps().collect::<Vec<_>>(), vec![2..11, 21..22]);
+
+    let mut vec = SafeUninitializedVec::with_interval_tracking(1 << 16);
+    vec.copy_from_slice_at(100, &[7u8; 4000]);
+    vec.set_value(0, 1);
+    assert!(vec.mask().uses_intervals());
+    assert_eq!(vec.count_initialized(), 4001);
+    assert_eq!(vec.first_uninitialized(), Some(1));
+    assert_eq!(vec.next_uninitialized_after(99), Some(4100));
+    assert_eq!(vec.take_initialized_prefix(), vec![1]);
+    assert!(vec.mask().uses_intervals());
+    // views need one bit per slot
+    vec.view_mut(0..10).set_value(0, 2);
+    assert!(!vec.mask().uses_intervals());
+    assert_eq!(vec.count_initialized(), 4001);
+}
+
+#[test]
+fn test_uninit_vec_word_scans() {
+    use DropCounter;
+
+    let counter = DropCounter::new();
+    let mut vec = SafeUninitializedVec::new(300);
+    for i in (0..300).filter(|i| i % 70 < 40) {
+        vec.set_value(i, counter.wrap(i));
+    }
+    assert_eq!(vec.compact_in_place(), 180);
+    assert_eq!(vec.initialized_runs().count(), 1);
+    assert!((0..180).all(|i| **vec.get_value(i).unwrap() == i / 40 * 70 + i % 40));
+    drop(vec);
+    counter.assert_all_dropped();
+
+    let mut vec = SafeUninitializedVec::from_vec(vec![counter.wrap(0); 3], 200);
+    vec.set_value(150, counter.wrap(1));
+    {
+        let (_, mut view) = vec.split_at_mut(70);
+        assert_eq!(view.count_initialized(), 1);
+        view.init_missing_with(|i| counter.wrap(i));
+        assert_eq!(view.count_initialized(), 130);
+        assert_eq!(**view.get_value(80).unwrap(), 1);
+    }
+    assert_eq!(vec.count_initialized(), 133);
+    assert_eq!(counter.live(), 133);
+    drop(vec);
+    counter.assert_all_dropped();
+}
+
+#[test]
+fn test_uninit_vec_niche_values() {
+    use std::num::NonZeroU32;
+
+    let word = String::from("tracked");
+    let mut refs: SafeUninitializedVec<&str> = SafeUninitializedVec::new(4);
+    refs.set_value(2, &word);
+    assert_eq!(refs.get_value(1), None);
+    assert_eq!(refs.get_value(2), Some(&"tracked"));
+    assert_eq!(refs.compact(), vec!["tracked"]);
+
+    let mut vec: SafeUninitializedVec<Option<NonZeroU32>> = SafeUninitializedVec::new(3);
+    vec.push(NonZeroU32::new(7));
+    vec.set_value(0, None);
+    vec.resize(6);
+    assert_eq!(vec.count_initialized(), 2);
+    assert_eq!(vec.take(3), Some(NonZeroU32::new(7)));
+    assert_eq!(vec.initialized_prefix(), &[None]);
+    let (ptr, len, cap, mask) = vec.into_raw_parts();
+    let vec = unsafe { SafeUninitializedVec::from_raw_parts(ptr, len, cap, mask) };
+    assert_eq!(vec.len(), 6);
+    assert_eq!(vec.iter().collect::<Vec<_>>(), vec![(0, &None)]);
+}
+
+#[test]
+fn test_uninit_vec_zero_sized() {
+    use std::cell::Cell;
+
+    thread_local!(static DROPS: Cell<usize> = const { Cell::new(0) });
+    struct Token;
+    impl Drop for Token {
+        fn drop(&mut self) {
+            DROPS.with(|drops| drops.set(drops.get() + 1));
+        }
+    }
+    let drops = || DROPS.with(Cell::get);
+
+    let mut units: SafeUninitializedVec<()> = SafeUninitializedVec::new(100);
+    assert_eq!(units.capacity(), usize::MAX);
+    units.set_value(3, ());
+    units.push(());
+    units.insert(0, ());
+    assert_eq!(units.len(), 102);
+    assert_eq!(units.count_initialized(), 3);
+    assert_eq!(units.remove(4), Some(()));
+    assert_eq!(units.spare_capacity_tracked().len(), 0);
+    assert_eq!(units.clone().compact(), vec![(); 2]);
+
+    let mut vec = SafeUninitializedVec::new(1000);
+    for i in (0..1000).step_by(3) {
+        vec.set_value(i, Token);
+    }
+    assert_eq!(vec.count_initialized(), 334);
+    drop(vec.take(0));
+    vec.retain(|i, _| i % 2 == 0);
+    assert_eq!(drops(), 168);
+    let (ptr, len, cap, mask) = vec.into_raw_parts();
+    let mut vec = unsafe { SafeUninitializedVec::from_raw_parts(ptr, len, cap, mask) };
+    assert_eq!(vec.drain().take(10).count(), 10);
+    assert_eq!(drops(), 334);
+    vec.resize(10);
+    vec.push(Token);
+    drop(vec);
+    assert_eq!(drops(), 335);
+}
+
+#[test]
+fn test_uninit_vec_custom_allocator() {
+    use std::alloc::Layout;
+    use std::cell::Cell;
+    use std::ptr::NonNull;
+    use {Global, RawAlloc};
+
+    // counts the bytes it has handed out that have not been freed yet
+    #[derive(Default)]
+    struct Counting {
+        live: Cell<usize>,
+    }
+
+    unsafe impl RawAlloc for Counting {
+        fn allocate(&self, layout: Layout) -> Option<NonNull<u8>> {
+            self.live.set(self.live.get() + layout.size());
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            self.live.set(self.live.get() - layout.size());
+            Global.deallocate(ptr, layout)
+        }
+    }
+
+    let alloc = Counting::default();
+    {
+        let mut vec = SafeUninitializedVec::new_in(10, &alloc);
+        assert_eq!(alloc.live.get(), 10 * 8);
+        vec.set_value(3, 3u64);
+        for i in 0..20 {
+            vec.push(i);
+        }
+        assert_eq!(alloc.live.get(), vec.capacity() * 8);
+        vec.view_mut(0..2).set_value(1, 1);
+        let clone = vec.clone();
+        assert_eq!(clone, vec);
+        assert_eq!(alloc.live.get(), (vec.capacity() + clone.capacity()) * 8);
+        drop(clone);
+        vec.truncate(12);
+        vec.shrink_to_fit();
+        assert_eq!(alloc.live.get(), 12 * 8);
+        assert_eq!(vec.count_initialized(), 4);
+        assert_eq!(
+            vec.iter().map(|(_, &v)| v).collect::<Vec<_>>(),
+            vec![1, 3, 0, 1]
+        );
+        // vectors compare equal whatever their allocators are
+        let mut other = SafeUninitializedVec::new(12);
+        for &(i, v) in &[(1, 1), (3, 3), (10, 0), (11, 1)] {
+            other.set_value(i, v);
+        }
+        assert_eq!(vec, other);
+    }
+    assert_eq!(alloc.live.get(), 0);
+}
+
+#[test]
+fn test_once_vec() {
+    use Error;
+    use OnceVec;
+
+    let mut vec = OnceVec::new(4);
+    vec.set_value(2, "first").unwrap();
+    let err = vec.set_value(2, "second").unwrap_err();
+    assert_eq!(err.index(), 2);
+    assert_eq!(err.to_string(), "slot 2 is already initialized");
+    assert_eq!(err.into_inner(), "second");
+    assert_eq!(vec.get_value(2), Some(&"first"));
+    assert_eq!(vec.count_initialized(), 1);
+
+    let mut vec: OnceVec<_> = SafeUninitializedVec::from_vec(vec![1, 2], 3).into();
+    assert_eq!(
+        Error::from(vec.set_value(0, 5).unwrap_err()),
+        Error::AlreadyInitialized { index: 0 }
+    );
+    vec.set_value(2, 3).unwrap();
+    assert_eq!(vec.into_inner().into_vec().unwrap(), vec![1, 2, 3]);
+}
+
+#[test]
+fn test_sequential_vec() {
+    use std::convert::TryFrom;
+    use std::mem::MaybeUninit;
+    use {DropCounter, SequentialVec};
+
+    let counter = DropCounter::new();
+    let mut seq = SequentialVec::new(6);
+    seq.push_init(counter.wrap(0)).unwrap();
+    seq.push_init(counter.wrap(1)).unwrap();
+    for (i, slot) in seq.remaining_mut()[..3].iter_mut().enumerate() {
+        *slot = MaybeUninit::new(counter.wrap(i + 2));
+    }
+    unsafe {
+        seq.advance(3);
+    }
+    assert_eq!(seq.written(), 5);
+    assert_eq!(seq.as_slice().len(), 5);
+    seq.push_init(counter.wrap(5)).unwrap();
+    assert!(seq.is_full());
+    let err = seq.push_init(counter.wrap(6)).unwrap_err();
+    assert_eq!((err.index(), err.len()), (6, 6));
+    drop(err);
+
+    let mut vec = seq.into_tracked();
+    assert!(vec.is_fully_initialized());
+    drop(vec.take(2));
+    // the hole at 2 comes before initialized slots
+    let mut vec = SequentialVec::try_from(vec).unwrap_err();
+    vec.truncate(2);
+    vec.resize(4);
+    let seq = SequentialVec::try_from(vec).unwrap();
+    assert_eq!((seq.len(), seq.written()), (4, 2));
+    assert_eq!(counter.live(), 2);
+    drop(seq);
+    counter.assert_all_dropped();
+}
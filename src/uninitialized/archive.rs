@@ -0,0 +1,142 @@
+//! Zero-copy archiving of tracked vectors with rkyv.
+//!
+//! A vector is archived as its length, the mask packed into bytes, and the initialized
+//! values stored densely in index order. The archived form can be read in place, for
+//! example out of a memory mapped file, without deserializing it first.
+
+use rkyv::bytecheck::CheckBytes;
+use rkyv::munge::munge;
+use rkyv::primitive::ArchivedUsize;
+use rkyv::rancor::Fallible;
+use rkyv::ser::{Allocator, Writer};
+use rkyv::vec::{ArchivedVec, VecResolver};
+use rkyv::{Archive, Deserialize, Place, Portable, Serialize};
+
+use super::SafeUninitializedVec;
+
+/// The archived form of a `SafeUninitializedVec`.
+#[derive(Portable, CheckBytes)]
+#[bytecheck(crate = ::rkyv::bytecheck)]
+#[repr(C)]
+pub struct ArchivedSafeUninitializedVec<T: Archive> {
+    len: ArchivedUsize,
+    // one bit per slot, least significant bit first
+    mask: ArchivedVec<u8>,
+    values: ArchivedVec<T::Archived>,
+}
+
+/// The resolver for an archived `SafeUninitializedVec`.
+pub struct SafeUninitializedVecResolver {
+    mask: VecResolver,
+    values: VecResolver,
+}
+
+impl<T: Archive> ArchivedSafeUninitializedVec<T> {
+    /// The length of the archived vector.
+    pub fn len(&self) -> usize {
+        self.len.to_native() as usize
+    }
+
+    /// Returns true if the archived vector has a length of 0.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns true if the value at `i` was initialized.
+    pub fn is_initialized(&self, i: usize) -> bool {
+        i < self.len()
+            && self
+                .mask
+                .get(i / 8)
+                .is_some_and(|byte| byte & (1 << (i % 8)) != 0)
+    }
+
+    /// The number of initialized values.
+    pub fn count_initialized(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Gets the archived value at `i`, or none if it was not initialized.
+    pub fn get_value(&self, i: usize) -> Option<&T::Archived> {
+        if !self.is_initialized(i) {
+            return None;
+        }
+        // the position of the value is the number of initialized values before it
+        let whole: usize = self.mask[..i / 8]
+            .iter()
+            .map(|byte| byte.count_ones() as usize)
+            .sum();
+        let partial = (self.mask[i / 8] & ((1 << (i % 8)) - 1)).count_ones() as usize;
+        self.values.get(whole + partial)
+    }
+
+    /// The initialized values, in index order.
+    pub fn values(&self) -> &[T::Archived] {
+        self.values.as_slice()
+    }
+}
+
+impl<T> SafeUninitializedVec<T> {
+    // the mask packed into bytes, with the untracked tail marked as initialized
+    fn packed_mask(&self) -> Vec<u8> {
+        let len = self.vals.len();
+        let mut packed = vec![0u8; len.div_ceil(8)];
+        for i in self.initialized.ones().chain(self.initialized.len()..len) {
+            packed[i / 8] |= 1 << (i % 8);
+        }
+        packed
+    }
+}
+
+impl<T: Archive> Archive for SafeUninitializedVec<T> {
+    type Archived = ArchivedSafeUninitializedVec<T>;
+    type Resolver = SafeUninitializedVecResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        munge!(let ArchivedSafeUninitializedVec { len, mask, values } = out);
+        let vec_len = self.vals.len();
+        vec_len.resolve((), len);
+        ArchivedVec::resolve_from_len(vec_len.div_ceil(8), resolver.mask, mask);
+        ArchivedVec::resolve_from_len(self.count_initialized(), resolver.values, values);
+    }
+}
+
+impl<T, S> Serialize<S> for SafeUninitializedVec<T>
+where
+    T: Serialize<S>,
+    S: Fallible + Allocator + Writer + ?Sized,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        let mask = ArchivedVec::<u8>::serialize_from_slice(&self.packed_mask(), serializer)?;
+        let tracked = self.initialized.len();
+        let initialized: Vec<&T> = self
+            .initialized
+            .ones()
+            .chain(tracked..self.vals.len())
+            .map(|i| unsafe { self.vals[i].assume_init_ref() })
+            .collect();
+        let values = ArchivedVec::<T::Archived>::serialize_from_iter::<T, _, _>(
+            initialized.iter().copied(),
+            serializer,
+        )?;
+        Ok(SafeUninitializedVecResolver { mask, values })
+    }
+}
+
+impl<T, D> Deserialize<SafeUninitializedVec<T>, D> for ArchivedSafeUninitializedVec<T>
+where
+    T: Archive,
+    T::Archived: Deserialize<T, D>,
+    D: Fallible + ?Sized,
+{
+    /// Rebuilds the vector. Slots are only initialized if both the mask and the stored
+    /// values cover them.
+    fn deserialize(&self, deserializer: &mut D) -> Result<SafeUninitializedVec<T>, D::Error> {
+        let mut vec = SafeUninitializedVec::new(self.len());
+        let indices = (0..self.len()).filter(|&i| self.is_initialized(i));
+        for (i, value) in indices.zip(self.values.iter()) {
+            vec.set_value(i, value.deserialize(deserializer)?);
+        }
+        Ok(vec)
+    }
+}
@@ -0,0 +1,83 @@
+//! Conversions between `SafeUninitializedVec` and Arrow arrays.
+//!
+//! The initialization mask maps to the Arrow validity bitmap, so uninitialized
+//! slots become nulls and nulls become uninitialized slots.
+//!
+//! Only available with the `arrow` feature.
+
+use std::mem;
+
+use arrow_array::types::ArrowPrimitiveType;
+use arrow_array::PrimitiveArray;
+use arrow_buffer::{ArrowNativeType, BooleanBuffer, Buffer, NullBuffer, ScalarBuffer};
+
+use super::{from_slots, into_slots, SafeUninitializedVec};
+use mask::{word_count, InitMask};
+
+impl<T: ArrowNativeType> SafeUninitializedVec<T> {
+    /// Converts the vector into an Arrow array, where uninitialized values become nulls.
+    ///
+    /// The backing `Vec` is moved into the array without copying. Uninitialized slots
+    /// are set to the default value first, since Arrow allows reading the values of nulls.
+    /// Values past the tracked length of the vector are valid.
+    pub fn into_arrow<A>(mut self) -> PrimitiveArray<A>
+    where
+        A: ArrowPrimitiveType<Native = T>,
+    {
+        for i in self.initialized.zeros() {
+            // the slot is uninitialized, so there is nothing to drop
+            self.vals[i].write(T::default());
+        }
+        self.untrack();
+        let mut validity = mem::take(&mut self.initialized);
+        // every slot has been filled in
+        let vals = unsafe { from_slots(mem::take(&mut self.vals)) };
+        let nulls = if validity.all() {
+            None
+        } else {
+            // both the mask and the validity bitmap put the first bit in the lowest bit
+            // of the first word, so the words can be moved over as they are
+            let len = vals.len();
+            validity.resize(len, true);
+            let bits = Buffer::from_vec(validity.into_u64s());
+            Some(NullBuffer::new(BooleanBuffer::new(bits, 0, len)))
+        };
+        PrimitiveArray::new(ScalarBuffer::from(vals), nulls)
+    }
+
+    /// Creates a vector from an Arrow array, where nulls become uninitialized slots.
+    ///
+    /// The values buffer of the array is reused without copying if it is not shared
+    /// with any other array. Otherwise, the values are copied.
+    pub fn from_arrow<A>(array: PrimitiveArray<A>) -> SafeUninitializedVec<T>
+    where
+        A: ArrowPrimitiveType<Native = T>,
+    {
+        let (_, values, nulls) = array.into_parts();
+        let vals = match values.into_inner().into_vec::<T>() {
+            Ok(vals) => vals,
+            Err(buffer) => buffer.typed_data::<T>().to_vec(),
+        };
+        let initialized = match nulls {
+            Some(nulls) => validity_mask(nulls),
+            None => InitMask::repeat(true, vals.len()),
+        };
+        SafeUninitializedVec::from_parts(into_slots(vals), initialized)
+    }
+}
+
+// converts a validity bitmap into a mask, reusing its words if the bitmap is not shared
+// and starts at the beginning of a word
+fn validity_mask(nulls: NullBuffer) -> InitMask {
+    let bits = nulls.into_inner();
+    let (offset, len) = (bits.offset(), bits.len());
+    let buffer = match bits.into_inner().into_vec::<u64>() {
+        Ok(words) if offset == 0 && words.len() >= word_count(len) => {
+            return InitMask::from_u64s(words, len);
+        }
+        Ok(words) => Buffer::from_vec(words),
+        Err(buffer) => buffer,
+    };
+    let words = buffer.bit_chunks(offset, len).iter_padded().collect();
+    InitMask::from_u64s(words, len)
+}
@@ -0,0 +1,54 @@
+//! Conversions between the initialization mask and `bitvec` types.
+//!
+//! Only available with the `bitvec` feature.
+
+use std::mem::MaybeUninit;
+
+use bitvec::order::Lsb0;
+use bitvec::slice::BitSlice;
+
+use super::{SafeUninitializedVec, Slots};
+use mask::InitMask;
+
+impl<T> SafeUninitializedVec<T> {
+    /// Creates a vector from values and a mask of which values should be kept.
+    ///
+    /// Values whose bit is not set are dropped and their slots become uninitialized.
+    ///
+    /// Panics if the mask is longer than `vals`.
+    pub fn from_bitvec(vals: Vec<T>, mask: &BitSlice) -> SafeUninitializedVec<T> {
+        assert!(mask.len() <= vals.len(), "mask is longer than the values");
+        let mut vec = SafeUninitializedVec::from_vec(vals, mask.len());
+        for i in mask.iter_zeros() {
+            vec.take(i);
+        }
+        vec
+    }
+
+    /// Creates a vector from possibly uninitialized storage and a mask of which values
+    /// are initialized. Slots past the end of the mask are uninitialized.
+    ///
+    /// # Safety
+    /// Every value whose bit is set in `mask` must be initialized.
+    ///
+    /// Panics if the mask is longer than `vals`.
+    pub unsafe fn from_uninit_bitvec(
+        vals: Vec<MaybeUninit<T>>,
+        mask: &BitSlice,
+    ) -> SafeUninitializedVec<T> {
+        assert!(mask.len() <= vals.len(), "mask is longer than the values");
+        let mut initialized: InitMask = mask.iter().by_vals().collect();
+        initialized.resize(vals.len(), false);
+        SafeUninitializedVec::from_parts(Slots::from(vals), initialized)
+    }
+
+    /// Borrows the initialization mask, with a bit set for every initialized value.
+    ///
+    /// The bits are the mask's own words, so nothing is copied. Values past the tracked
+    /// length of the vector are tracked first, so that every slot has a bit.
+    pub fn as_bitslice(&mut self) -> &BitSlice<u64, Lsb0> {
+        self.track_tail();
+        let len = self.initialized.len();
+        &BitSlice::from_slice(self.initialized.as_u64s())[..len]
+    }
+}
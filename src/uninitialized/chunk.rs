@@ -0,0 +1,56 @@
+//! Fixed size chunks of initialized values, for reading records out of a vector.
+
+use std::convert::TryFrom;
+use std::ptr;
+
+use super::{assume_init_slice, SafeUninitializedVec};
+use alloc::RawAlloc;
+use error::{Error, Result};
+
+impl<T, A: RawAlloc> SafeUninitializedVec<T, A> {
+    /// Gets a reference to the `N` values starting at `start`, or none if any of them are
+    /// uninitialized or out of bounds.
+    pub fn get_chunk<const N: usize>(&self, start: usize) -> Option<&[T; N]> {
+        self.check_chunk(start, N).ok()?;
+        let chunk = unsafe { assume_init_slice(&self.vals[start..start + N]) };
+        // the slice has exactly N elements, so this conversion can not fail
+        <&[T; N]>::try_from(chunk).ok()
+    }
+
+    /// Moves the `N` values starting at `start` out of the vector, marking their slots as
+    /// uninitialized.
+    ///
+    /// Nothing is taken unless every value in the chunk is initialized.
+    pub fn take_chunk<const N: usize>(&mut self, start: usize) -> Result<[T; N]> {
+        self.check_chunk(start, N)?;
+        if start + N > self.initialized.len() {
+            self.track_tail();
+        }
+        self.initialized.set_range(start..start + N, false);
+        self.report_deinitialized(N);
+        // every slot was initialized and has now been marked as uninitialized,
+        // so the values are moved out exactly once
+        unsafe { Ok(ptr::read(self.vals.as_ptr().add(start) as *const [T; N])) }
+    }
+
+    // checks that a chunk is in bounds and fully initialized
+    fn check_chunk(&self, start: usize, n: usize) -> Result<()> {
+        let len = self.vals.len();
+        let end = match start.checked_add(n) {
+            Some(end) if end <= len => end,
+            _ => {
+                return Err(Error::OutOfBounds {
+                    index: start.saturating_add(n).saturating_sub(1),
+                    len,
+                })
+            }
+        };
+        match self.first_uninitialized_in(start..end) {
+            Some(index) => Err(Error::Uninitialized {
+                len,
+                index: Some(index),
+            }),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! Building vectors from iterators, and filling them in place or by appending.
+
+use std::iter::FromIterator;
+
+use super::SafeUninitializedVec;
+use alloc::RawAlloc;
+
+/// The result of `collect_into`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Collected {
+    /// The number of items that were written.
+    pub written: usize,
+    /// Whether the iterator is known to have no more items.
+    pub exhausted: bool,
+}
+
+impl<T, A: RawAlloc> SafeUninitializedVec<T, A> {
+    /// Writes items from `iter` into consecutive uninitialized slots, starting at the first
+    /// uninitialized slot.
+    ///
+    /// This stops at the first slot that is already initialized, at the end of the vector,
+    /// or when the iterator runs out. An item is only taken from the iterator once there
+    /// is a slot for it, so the remaining items can still be used.
+    pub fn collect_into<I: Iterator<Item = T>>(&mut self, iter: &mut I) -> Collected {
+        let len = self.initialized.len();
+        let start = self.initialized.next_zero(0).unwrap_or(len);
+        let end = self.initialized.next_one(start).unwrap_or(len);
+        let mut written = Written {
+            vec: self,
+            start,
+            end: start,
+        };
+        let mut exhausted = false;
+        while written.end < end {
+            match iter.next() {
+                Some(val) => {
+                    written.vec.vals[written.end].write(val);
+                    written.end += 1;
+                }
+                None => {
+                    exhausted = true;
+                    break;
+                }
+            }
+        }
+        let count = written.end - start;
+        drop(written);
+        Collected {
+            written: count,
+            exhausted: exhausted || iter.size_hint().1 == Some(0),
+        }
+    }
+}
+
+// marks the slots that have been written as initialized when dropped, even if a panic
+// in the iterator interrupts the loop that writes them
+struct Written<'a, T: 'a, A: 'a + RawAlloc> {
+    vec: &'a mut SafeUninitializedVec<T, A>,
+    start: usize,
+    end: usize,
+}
+
+impl<'a, T, A: RawAlloc> Drop for Written<'a, T, A> {
+    fn drop(&mut self) {
+        let count = self.vec.initialized.set_range(self.start..self.end, true);
+        self.vec.report_initialized(count);
+    }
+}
+
+impl<T, A: RawAlloc> Extend<T> for SafeUninitializedVec<T, A> {
+    /// Appends every item of the iterator as an initialized value.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.vals.reserve(iter.size_hint().0);
+        for val in iter {
+            self.push(val);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for SafeUninitializedVec<T> {
+    /// Collects the items into a fully initialized vector.
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> SafeUninitializedVec<T> {
+        let vals: Vec<T> = iter.into_iter().collect();
+        let len = vals.len();
+        SafeUninitializedVec::from_vec(vals, len)
+    }
+}
+
+impl<T> FromIterator<Option<T>> for SafeUninitializedVec<T> {
+    /// Collects the items into a vector, where every `None` becomes an uninitialized slot.
+    fn from_iter<I: IntoIterator<Item = Option<T>>>(iter: I) -> SafeUninitializedVec<T> {
+        let iter = iter.into_iter();
+        let mut vec = SafeUninitializedVec::new(0);
+        vec.reserve(iter.size_hint().0);
+        for option in iter {
+            match option {
+                Some(val) => vec.push(val),
+                None => {
+                    let len = vec.len();
+                    vec.resize(len + 1);
+                }
+            }
+        }
+        vec
+    }
+}
@@ -0,0 +1,131 @@
+//! Converting between element types, and to and from other representations.
+
+use std::marker::PhantomData;
+use std::mem::{self, ManuallyDrop, MaybeUninit};
+use std::ptr;
+
+use super::{SafeUninitializedVec, Slots};
+use mask::InitMask;
+
+// fails to compile if `T` and `U` don't have the same size and alignment
+struct SameLayout<T, U>(PhantomData<(T, U)>);
+
+impl<T, U> SameLayout<T, U> {
+    const CHECK: () = assert!(
+        mem::size_of::<T>() == mem::size_of::<U>() && mem::align_of::<T>() == mem::align_of::<U>(),
+        "map_in_place requires types with the same size and alignment"
+    );
+}
+
+// cleans up the storage if the conversion panics partway through
+struct MapGuard<'a, T, U> {
+    ptr: *mut T,
+    len: usize,
+    cap: usize,
+    initialized: &'a InitMask,
+    // every initialized slot before this holds a `U`, every one after it holds a `T`
+    pos: usize,
+    _marker: PhantomData<U>,
+}
+
+impl<'a, T, U> Drop for MapGuard<'a, T, U> {
+    fn drop(&mut self) {
+        unsafe {
+            for i in self.initialized.ones() {
+                if i < self.pos {
+                    ptr::drop_in_place(self.ptr.add(i) as *mut U);
+                } else if i > self.pos {
+                    ptr::drop_in_place(self.ptr.add(i));
+                }
+                // the value at pos was moved into the closure
+            }
+            drop(Vec::from_raw_parts(self.ptr, 0, self.cap));
+        }
+    }
+}
+
+impl<T> SafeUninitializedVec<T> {
+    /// Converts every initialized value with `f`, writing the results into the same
+    /// allocation. Uninitialized slots stay uninitialized.
+    ///
+    /// `T` and `U` must have the same size and alignment, which is checked at compile time.
+    /// If `f` panics, every value that has not been moved into `f` is dropped.
+    pub fn map_in_place<U, F: FnMut(T) -> U>(mut self, mut f: F) -> SafeUninitializedVec<U> {
+        #[allow(clippy::let_unit_value)]
+        let () = SameLayout::<T, U>::CHECK;
+        self.track_tail();
+        self.untrack();
+        let mut vals = ManuallyDrop::new(mem::take(&mut self.vals));
+        let initialized = mem::take(&mut self.initialized);
+        drop(self);
+
+        let mut guard = MapGuard::<T, U> {
+            ptr: vals.as_mut_ptr() as *mut T,
+            len: vals.len(),
+            cap: vals.capacity(),
+            initialized: &initialized,
+            pos: 0,
+            _marker: PhantomData,
+        };
+        for i in initialized.ones() {
+            guard.pos = i;
+            unsafe {
+                let val = ptr::read(guard.ptr.add(i));
+                ptr::write(guard.ptr.add(i) as *mut U, f(val));
+            }
+        }
+        let (ptr, len, cap) = (guard.ptr, guard.len, guard.cap);
+        mem::forget(guard);
+        // the layouts of T and U match, so the allocation can be reused as a Vec<U>
+        let vals = unsafe { Vec::from_raw_parts(ptr as *mut MaybeUninit<U>, len, cap) };
+        SafeUninitializedVec::from_parts(Slots::from(vals), initialized)
+    }
+
+    /// Creates a vector from a `Vec` of options, where every `None` becomes an
+    /// uninitialized slot.
+    pub fn from_options(options: Vec<Option<T>>) -> SafeUninitializedVec<T> {
+        let mut vec = SafeUninitializedVec::new(options.len());
+        for (i, option) in options.into_iter().enumerate() {
+            if let Some(val) = option {
+                vec.set_value(i, val);
+            }
+        }
+        vec
+    }
+
+    /// Converts the vector into a `Vec` of options, with `None` for every uninitialized
+    /// slot. The values are moved, not cloned.
+    pub fn into_options(mut self) -> Vec<Option<T>> {
+        let mut options: Vec<Option<T>> = (0..self.vals.len()).map(|_| None).collect();
+        for (i, val) in self.drain() {
+            options[i] = Some(val);
+        }
+        options
+    }
+}
+
+/// Drops the values in `vec` and reuses its allocation as uninitialized storage for `U`.
+///
+/// The allocation is only reused if `T` and `U` have the same alignment and the allocation
+/// holds a whole number of `U`s. Otherwise a new allocation of the same size in bytes is
+/// made. The returned vector is as long as the number of `U`s that fit.
+pub fn recycle<U, T>(mut vec: Vec<T>) -> SafeUninitializedVec<U> {
+    vec.clear();
+    let bytes = vec.capacity() * mem::size_of::<T>();
+    let size = mem::size_of::<U>();
+    if size == 0 || bytes == 0 {
+        return SafeUninitializedVec::new(0);
+    }
+    let len = bytes / size;
+    if mem::align_of::<T>() != mem::align_of::<U>() || !bytes.is_multiple_of(size) {
+        return SafeUninitializedVec::new(len);
+    }
+    let mut vec = ManuallyDrop::new(vec);
+    // the allocation has the same size and alignment as one for `len` values of `U`
+    let vals = unsafe {
+        let mut vals = Vec::from_raw_parts(vec.as_mut_ptr() as *mut MaybeUninit<U>, 0, len);
+        vals.set_len(len);
+        vals
+    };
+    SafeUninitializedVec::from_parts(Slots::from(vals), InitMask::new(len))
+}
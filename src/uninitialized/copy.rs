@@ -0,0 +1,78 @@
+//! Bulk copies of `Copy` values into tracked vectors.
+
+use std::ptr;
+
+use super::SafeUninitializedVec;
+use alloc::RawAlloc;
+
+/// Decides what happens to destination slots that are already initialized when
+/// copying between tracked vectors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyPolicy {
+    /// Initialized destination values are replaced by the source values.
+    Overwrite,
+    /// Initialized destination values are kept.
+    SkipInitialized,
+}
+
+impl<T: Copy, A: RawAlloc> SafeUninitializedVec<T, A> {
+    /// Copies `src` into the slots starting at `offset`, initializing them.
+    ///
+    /// The values are copied with a single `memcpy` and their bits are set as a range, so
+    /// this is much faster than setting them one at a time. Panics if the range is out of
+    /// bounds.
+    pub fn copy_from_slice_at(&mut self, offset: usize, src: &[T]) {
+        assert!(
+            offset <= self.vals.len() && src.len() <= self.vals.len() - offset,
+            "range out of bounds"
+        );
+        self.track_tail();
+        unsafe {
+            // T is Copy, so overwritten values do not need to be dropped
+            ptr::copy_nonoverlapping(
+                src.as_ptr(),
+                self.vals.as_mut_ptr().add(offset) as *mut T,
+                src.len(),
+            );
+        }
+        let changed = self.initialized.set_range(offset..offset + src.len(), true);
+        self.report_initialized(changed);
+    }
+
+    /// Copies every initialized value of `other` into the same slot of this vector.
+    ///
+    /// Values are copied a run at a time instead of one by one. Values past the end of
+    /// the tracked range of either vector count as initialized, and slots past the end of
+    /// the shorter vector are ignored. Returns the number of values copied.
+    pub fn copy_from<B: RawAlloc>(
+        &mut self,
+        other: &SafeUninitializedVec<T, B>,
+        policy: CopyPolicy,
+    ) -> usize {
+        self.track_tail();
+        let len = self.vals.len().min(other.vals.len());
+        // the slots past the end of the source's mask hold values too
+        let mut copied = other.initialized.clone();
+        copied.resize(len, true);
+        if policy == CopyPolicy::SkipInitialized {
+            let mut existing = self.initialized.clone();
+            existing.truncate(len);
+            copied.difference_with(&existing);
+        }
+        let mut count = 0;
+        for run in copied.runs() {
+            unsafe {
+                // T is Copy, so overwritten values do not need to be dropped
+                ptr::copy_nonoverlapping(
+                    other.vals.as_ptr().add(run.start),
+                    self.vals.as_mut_ptr().add(run.start),
+                    run.end - run.start,
+                );
+            }
+            count += run.end - run.start;
+            let changed = self.initialized.set_range(run, true);
+            self.report_initialized(changed);
+        }
+        count
+    }
+}
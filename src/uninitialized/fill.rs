@@ -0,0 +1,139 @@
+//! Bulk and guarded multi-step writes into a `SafeUninitializedVec`.
+
+use std::ops::Range;
+
+use super::SafeUninitializedVec;
+use alloc::{Global, RawAlloc};
+use mask::InitMask;
+
+/// A guard that initializes values in a range of a `SafeUninitializedVec`.
+///
+/// Values written through the guard only become permanent once `commit` is called.
+/// If the guard is dropped without being committed (for example because of an early
+/// return or a panic), every value it wrote is dropped and any value it replaced is
+/// put back, so the vector is left exactly as it was before `begin_fill`.
+pub struct FillGuard<'a, T: 'a, A: 'a + RawAlloc = Global> {
+    vec: &'a mut SafeUninitializedVec<T, A>,
+    range: Range<usize>,
+    // whether the guard has written to each index in the range
+    written: InitMask,
+    // values that were initialized before the guard overwrote them
+    displaced: Vec<(usize, T)>,
+}
+
+impl<T, A: RawAlloc> SafeUninitializedVec<T, A> {
+    /// Sets every slot to a clone of `value`, dropping the values that were initialized.
+    pub fn fill(&mut self, value: T)
+    where
+        T: Clone,
+    {
+        self.fill_with(|| value.clone());
+    }
+
+    /// Sets every slot to the result of calling `f`, dropping the values that were
+    /// initialized. Slots are filled in order.
+    pub fn fill_with<F: FnMut() -> T>(&mut self, mut f: F) {
+        for i in 0..self.vals.len() {
+            self.set_value(i, f());
+        }
+    }
+
+    /// Sets the slots starting at `offset` to clones of the values in `src`, dropping the
+    /// values that were initialized there.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn set_range(&mut self, offset: usize, src: &[T])
+    where
+        T: Clone,
+    {
+        assert!(
+            offset <= self.vals.len() && src.len() <= self.vals.len() - offset,
+            "range out of bounds"
+        );
+        for (i, val) in (offset..).zip(src) {
+            self.set_value(i, val.clone());
+        }
+    }
+
+    /// Initializes every uninitialized slot with the result of calling `f` with its index.
+    /// Values that are already initialized are left as they are.
+    pub fn init_missing_with<F: FnMut(usize) -> T>(&mut self, mut f: F) {
+        let mut next = self.first_uninitialized();
+        while let Some(i) = next {
+            self.set_value(i, f(i));
+            next = self.next_uninitialized_after(i);
+        }
+    }
+
+    /// Starts a fill of the values in `range`. Writes go through the returned guard.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn begin_fill(&mut self, range: Range<usize>) -> FillGuard<'_, T, A> {
+        assert!(
+            range.start <= range.end && range.end <= self.initialized.len(),
+            "fill range out of bounds"
+        );
+        FillGuard {
+            written: InitMask::new(range.end - range.start),
+            vec: self,
+            range,
+            displaced: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T, A: RawAlloc> FillGuard<'a, T, A> {
+    /// The range of indices this guard can write to.
+    pub fn range(&self) -> Range<usize> {
+        self.range.clone()
+    }
+
+    /// Sets the value at index `i`. The index is relative to the whole vector,
+    /// not to the start of the range.
+    ///
+    /// Panics if `i` is outside of the guarded range.
+    pub fn set_value(&mut self, i: usize, val: T) {
+        assert!(
+            i >= self.range.start && i < self.range.end,
+            "index {} is outside of the fill range",
+            i
+        );
+        let offset = i - self.range.start;
+        if !self.written.set(offset, true) {
+            if let Some(old) = self.vec.take(i) {
+                self.displaced.push((i, old));
+            }
+        }
+        self.vec.set_value(i, val);
+    }
+
+    /// Gets a reference to a value in the vector.
+    pub fn get_value(&self, i: usize) -> Option<&T> {
+        self.vec.get_value(i)
+    }
+
+    /// Returns true if the guard has written to every index in its range.
+    pub fn is_complete(&self) -> bool {
+        self.written.all()
+    }
+
+    /// Keeps every value written by the guard. Values that were replaced are dropped.
+    pub fn commit(mut self) {
+        self.written.clear();
+        self.displaced.clear();
+    }
+}
+
+impl<'a, T, A: RawAlloc> Drop for FillGuard<'a, T, A> {
+    fn drop(&mut self) {
+        // the guard was not committed, so undo every write
+        for offset in 0..self.written.len() {
+            if self.written.is_set(offset) {
+                self.vec.take(self.range.start + offset);
+            }
+        }
+        for (i, old) in self.displaced.drain(..) {
+            self.vec.set_value(i, old);
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Hashing the initialized contents of a `SafeUninitializedVec`.
+
+use std::hash::{Hash, Hasher};
+
+use super::SafeUninitializedVec;
+use alloc::RawAlloc;
+
+// parameters of the 64 bit FNV-1a hash
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+impl<T: Hash, A: RawAlloc> SafeUninitializedVec<T, A> {
+    /// Feeds the length, the position of every initialized run and the values in it into
+    /// a hasher.
+    ///
+    /// Uninitialized slots are never read, so two vectors with the same initialized slots
+    /// and values always produce the same hash. Values past the end of the mask count as
+    /// initialized.
+    pub fn hash_initialized<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.vals.len());
+        for (start, run) in self.initialized_runs() {
+            state.write_usize(start);
+            run.hash(state);
+        }
+    }
+}
+
+impl<T: Hash, A: RawAlloc> Hash for SafeUninitializedVec<T, A> {
+    /// Hashes the length and, for every slot, whether it is initialized and its value.
+    ///
+    /// Values past the end of the mask are hashed as initialized, so that vectors that are
+    /// equal always have the same hash.
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.vals.len());
+        for i in 0..self.vals.len() {
+            self.get_value(i).hash(state);
+        }
+    }
+}
+
+impl<A: RawAlloc> SafeUninitializedVec<u8, A> {
+    /// Computes a digest of the initialized bytes and their positions.
+    ///
+    /// This uses 64 bit FNV-1a over the offset and length of every initialized run
+    /// followed by its bytes, so the result is the same on every platform and every
+    /// version of this crate. It is meant for content addressed caching, not security.
+    pub fn initialized_digest(&self) -> u64 {
+        let mut hash = FNV_OFFSET;
+        let mut feed = |bytes: &[u8]| {
+            for &byte in bytes {
+                hash ^= u64::from(byte);
+                hash = hash.wrapping_mul(FNV_PRIME);
+            }
+        };
+        feed(&(self.vals.len() as u64).to_le_bytes());
+        for (start, run) in self.initialized_runs() {
+            feed(&(start as u64).to_le_bytes());
+            feed(&(run.len() as u64).to_le_bytes());
+            feed(run);
+        }
+        hash
+    }
+}
@@ -0,0 +1,69 @@
+//! Reading directly into the uninitialized parts of a tracked byte buffer.
+
+use std::io::{self, IoSliceMut, Read};
+use std::ops::Range;
+use std::ptr;
+use std::slice;
+
+use super::SafeUninitializedVec;
+use alloc::RawAlloc;
+
+impl<A: RawAlloc> SafeUninitializedVec<u8, A> {
+    /// Reads into several uninitialized regions of the buffer with a single vectored read.
+    ///
+    /// The regions are filled in the order they are given, and exactly the bytes that were
+    /// read are marked as initialized. Returns the number of bytes read.
+    ///
+    /// The regions are zeroed before reading, since `Read` implementations are allowed to
+    /// read from the buffer they are given. Fails with `InvalidInput` if a region is out of
+    /// bounds, contains initialized bytes, or overlaps another region.
+    pub fn read_vectored_into<R: Read + ?Sized>(
+        &mut self,
+        reader: &mut R,
+        regions: &[Range<usize>],
+    ) -> io::Result<usize> {
+        self.check_read_regions(regions)?;
+        let base = self.vals.as_mut_ptr() as *mut u8;
+        let read = {
+            let mut slices: Vec<IoSliceMut> = regions
+                .iter()
+                .map(|r| unsafe {
+                    // the regions are in bounds, uninitialized and do not overlap
+                    ptr::write_bytes(base.add(r.start), 0, r.len());
+                    IoSliceMut::new(slice::from_raw_parts_mut(base.add(r.start), r.len()))
+                })
+                .collect();
+            reader.read_vectored(&mut slices)?
+        };
+        let mut remaining = read;
+        for r in regions {
+            let filled = remaining.min(r.len());
+            let changed = self.initialized.set_range(r.start..r.start + filled, true);
+            self.report_initialized(changed);
+            remaining -= filled;
+        }
+        Ok(read)
+    }
+
+    fn check_read_regions(&self, regions: &[Range<usize>]) -> io::Result<()> {
+        let invalid = |msg| Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        let mut sorted = regions.to_vec();
+        sorted.sort_by_key(|r| r.start);
+        for (n, r) in sorted.iter().enumerate() {
+            if r.start > r.end || r.end > self.initialized.len() {
+                return invalid("region out of bounds");
+            }
+            if n > 0 && sorted[n - 1].end > r.start {
+                return invalid("regions overlap");
+            }
+            if self
+                .initialized
+                .next_one(r.start)
+                .is_some_and(|i| i < r.end)
+            {
+                return invalid("region contains initialized bytes");
+            }
+        }
+        Ok(())
+    }
+}
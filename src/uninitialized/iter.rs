@@ -0,0 +1,146 @@
+//! Iteration over the initialized values of a vector.
+
+use std::mem::MaybeUninit;
+use std::ops::Range;
+use std::ptr;
+
+use super::{assume_init_slice, scrub, SafeUninitializedVec};
+use alloc::RawAlloc;
+use mask::{Indices, InitMask, Runs};
+
+/// An iterator over the initialized values of a `SafeUninitializedVec` and their indices.
+pub struct Iter<'a, T: 'a> {
+    vals: &'a [MaybeUninit<T>],
+    ones: Indices<'a>,
+    // the values past the end of the mask, which are always initialized
+    tail: Range<usize>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+    type Item = (usize, &'a T);
+
+    fn next(&mut self) -> Option<(usize, &'a T)> {
+        let i = match self.ones.next() {
+            Some(i) => i,
+            None => self.tail.next()?,
+        };
+        Some((i, unsafe { self.vals[i].assume_init_ref() }))
+    }
+}
+
+/// An iterator over the maximal runs of initialized values of a `SafeUninitializedVec`,
+/// along with the index each run starts at.
+pub struct InitializedRuns<'a, T: 'a> {
+    vals: &'a [MaybeUninit<T>],
+    runs: Runs<'a>,
+    // the values past the end of the mask, which are always initialized
+    tail: Range<usize>,
+}
+
+impl<'a, T> Iterator for InitializedRuns<'a, T> {
+    type Item = (usize, &'a [T]);
+
+    fn next(&mut self) -> Option<(usize, &'a [T])> {
+        let run = match self.runs.next() {
+            // a run that reaches the end of the mask continues into the tail
+            Some(run) if run.end == self.tail.start => {
+                let run = run.start..self.tail.end;
+                self.tail.start = self.tail.end;
+                run
+            }
+            Some(run) => run,
+            None if !self.tail.is_empty() => {
+                let run = self.tail.clone();
+                self.tail.start = self.tail.end;
+                run
+            }
+            None => return None,
+        };
+        Some((run.start, unsafe { assume_init_slice(&self.vals[run]) }))
+    }
+}
+
+/// An iterator that moves the initialized values out of a `SafeUninitializedVec`, along
+/// with their indices.
+///
+/// Every slot is marked as uninitialized as soon as the iterator is created. Values that
+/// are not yielded are dropped along with the iterator.
+pub struct Drain<'a, T: 'a> {
+    vals: &'a mut [MaybeUninit<T>],
+    // the slots that have not been moved out yet
+    remaining: InitMask,
+    pos: usize,
+}
+
+impl<'a, T> Iterator for Drain<'a, T> {
+    type Item = (usize, T);
+
+    fn next(&mut self) -> Option<(usize, T)> {
+        let i = self.remaining.next_one(self.pos)?;
+        self.remaining.set(i, false);
+        self.pos = i + 1;
+        // the slot is no longer marked as initialized anywhere, so it is only read once
+        unsafe {
+            let slot = self.vals[i].as_mut_ptr();
+            let val = ptr::read(slot);
+            scrub(slot, 1);
+            Some((i, val))
+        }
+    }
+}
+
+impl<'a, T> Drop for Drain<'a, T> {
+    fn drop(&mut self) {
+        // a panicking destructor leaks the rest of the values instead of dropping them twice
+        self.for_each(drop);
+    }
+}
+
+impl<T, A: RawAlloc> SafeUninitializedVec<T, A> {
+    /// Moves every initialized value out of the vector, in index order, along with its
+    /// index. Every slot is left uninitialized, and the allocation is kept.
+    ///
+    /// If the iterator is dropped early, the remaining values are dropped. If it is leaked,
+    /// they are leaked too, but the vector is still left uninitialized.
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        self.track_tail();
+        let len = self.initialized.len();
+        let remaining = self.initialized.clone();
+        let count = self.initialized.set_range(0..len, false);
+        self.report_deinitialized(count);
+        Drain {
+            vals: &mut self.vals,
+            remaining,
+            pos: 0,
+        }
+    }
+
+    /// Iterates over every maximal run of initialized values in index order, along with the
+    /// index each run starts at.
+    pub fn initialized_runs(&self) -> InitializedRuns<'_, T> {
+        InitializedRuns {
+            vals: &self.vals,
+            runs: self.initialized.runs(),
+            tail: self.initialized.len()..self.vals.len(),
+        }
+    }
+
+    /// Iterates over the initialized values in index order, along with their indices.
+    /// Uninitialized slots are skipped.
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            vals: &self.vals,
+            ones: self.initialized.ones(),
+            tail: self.initialized.len()..self.vals.len(),
+        }
+    }
+}
+
+impl<'a, T, A: RawAlloc> IntoIterator for &'a SafeUninitializedVec<T, A> {
+    type Item = (usize, &'a T);
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Iter<'a, T> {
+        self.iter()
+    }
+}
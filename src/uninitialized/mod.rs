@@ -1,62 +1,199 @@
 //! Provides safe wrappers for uninitialzed memory.
-//! 
+//!
 //! These track whether values have been uninitialzed. This
 //! adds some overhead, but is still faster than other safe workarounds
 //! for uninitialzed data (e.g. default, linked lists, etc) in some cases.
 
+use std::cmp;
+use std::error;
 use std::fmt;
-use std::mem;
+use std::mem::{self, ManuallyDrop, MaybeUninit};
+use std::ops::Range;
 use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use alloc::{Global, RawAlloc};
+use error::Error;
+use instrument;
+use mask::{Indices, InitMask};
+#[cfg(feature = "registry")]
+use registry::Registration;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+#[cfg(feature = "rkyv")]
+mod archive;
+#[cfg(feature = "arrow")]
+mod arrow;
+#[cfg(feature = "bitvec")]
+mod bits;
+mod chunk;
+mod collect;
+mod convert;
+mod copy;
+mod fill;
+mod hash;
+mod io;
+mod iter;
+mod once;
+#[cfg(feature = "rayon")]
+mod parallel;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "rand")]
+mod random;
+mod sequential;
+#[cfg(feature = "serde")]
+mod serialize;
+mod slice;
+mod slots;
+mod spare;
+mod sparse;
+mod view;
+
+#[cfg(feature = "rkyv")]
+pub use self::archive::{ArchivedSafeUninitializedVec, SafeUninitializedVecResolver};
+pub use self::collect::Collected;
+pub use self::convert::recycle;
+pub use self::copy::CopyPolicy;
+pub use self::fill::FillGuard;
+pub use self::iter::{Drain, InitializedRuns, Iter};
+pub use self::once::{AlreadyInitializedError, OnceVec};
+#[cfg(feature = "python")]
+pub use self::python::PyTrackedBuffer;
+pub use self::sequential::SequentialVec;
+pub use self::slice::TrackedSlice;
+pub use self::spare::SpareCapacity;
+pub use self::sparse::{SparseElement, SparseFormatError};
+pub use self::view::{TrackedView, TrackedViewMut};
+
+use self::slots::Slots;
 
 /// Used to store an uninitialized array.
 ///
 /// This keeps track of which values have been initialized, allowing it to be used safely and dropped safely.
-pub struct SafeUninitializedVec<T> {
-    // THIS VEC CAN CONTAIN UNINITIALIZED DATA
-    vals: Vec<T>,
-    initialized: Vec<bool>,
+///
+/// With the `zeroize` feature, the memory of a value is overwritten with zeros when it is
+/// dropped, taken, or overwritten, and the whole allocation is zeroed when the vector is
+/// dropped. Memory that the allocator releases when the vector grows or shrinks is not
+/// zeroed, so buffers for sensitive data should be allocated at their full size up front.
+///
+/// Like `Vec`, the vector is `Send` if `T` is `Send` and `Sync` if `T` is `Sync`, so a
+/// partially initialized vector can be moved to another thread, as long as its allocator
+/// can be too. The views borrowed from it follow the same rules as the references they are
+/// made of. These all come from the fields, so there are no `unsafe impl`s on the vector
+/// that could give a view more than it should have.
+///
+/// Zero-sized values, like `()`, take no storage. The vector only tracks which slots are
+/// initialized, and dropping it drops exactly the values it holds. Like `Vec`, it never
+/// allocates for them and its capacity is `usize::MAX`.
+///
+/// The storage comes from the allocator `A`, which is the global allocator unless the
+/// vector is created with `new_in` or `with_capacity_in`. Conversions to and from `Vec`
+/// reuse the allocation, so they are only available with the global allocator.
+pub struct SafeUninitializedVec<T, A: RawAlloc = Global> {
+    // a slot holds a value if its bit in the mask is set, or if it is past the end of the
+    // mask. Every other slot is uninitialized
+    vals: Slots<T, A>,
+    initialized: InitMask,
+    // the number of set bits in the mask, kept up to date by `report_initialized` and
+    // `report_deinitialized`. It is atomic so that views can update it while sharing it
+    initialized_count: AtomicUsize,
+    #[cfg(feature = "registry")]
+    registration: Registration,
 }
 
-impl<T> Drop for SafeUninitializedVec<T> {
+impl<T, A: RawAlloc> Drop for SafeUninitializedVec<T, A> {
     fn drop(&mut self) {
-        let mut len = (&self.vals).len();
-        let checked_len = (&self.initialized).len();
-        // note that the vec that was originally passed using from_vec could be longer than
-        // the length of this vec
-        while len > checked_len {
-            // all values outside the checked range cannot be uninitialized
-            self.vals.pop();
-            len -= 1;
-        }
-        while let Some(init) = self.initialized.pop() {
-            len -= 1;
-            if init {
-                // the popped value will be dropped when it goes out of scope
-                // this is only safe to do if the value is initialized
-                self.vals.pop();
-            } else {
-                unsafe {
-                    // if the value is uninitialized, then we decrease the length of vals
-                    // this will not drop the value when it goes out of scope
-                    self.vals.set_len(len);
-                }
+        self.untrack();
+        instrument::container_dropped();
+        let len = self.vals.len();
+        let checked_len = self.initialized.len();
+        unsafe {
+            // vals is given length 0 before anything is dropped, so that it only frees the
+            // allocation, and a panicking destructor can only leak the values after it
+            self.vals.set_len(0);
+            let vals = self.vals.as_mut_ptr() as *mut T;
+            // the initialized values are dropped a run at a time
+            // note that the vec that was originally passed using from_vec could be longer
+            // than the mask, and all values outside the checked range are initialized
+            for run in self.initialized.runs().chain(Some(checked_len..len)) {
+                ptr::drop_in_place(ptr::slice_from_raw_parts_mut(
+                    vals.add(run.start),
+                    run.len(),
+                ));
             }
+            scrub(vals, self.vals.capacity());
         }
-        // vals now has length 0, but still retains its capacity, so the allocated memory can be freed correctly.
     }
 }
 
+// overwrites `count` slots with zeros, so that values that were moved out or dropped do not
+// linger in memory. This does nothing without the `zeroize` feature
+#[inline]
+unsafe fn scrub<T>(ptr: *mut T, count: usize) {
+    #[cfg(feature = "zeroize")]
+    ::std::slice::from_raw_parts_mut(ptr as *mut MaybeUninit<T>, count).zeroize();
+    #[cfg(not(feature = "zeroize"))]
+    let _ = (ptr, count);
+}
+
+// reuses the allocation of a vec as slots that may be uninitialized
+fn into_slots<T>(vec: Vec<T>) -> Slots<T> {
+    let mut vec = ManuallyDrop::new(vec);
+    // MaybeUninit<T> has the same layout as T
+    let vec = unsafe {
+        Vec::from_raw_parts(
+            vec.as_mut_ptr() as *mut MaybeUninit<T>,
+            vec.len(),
+            vec.capacity(),
+        )
+    };
+    Slots::from(vec)
+}
+
+// reuses the allocation of slots as a vec of values
+// every slot must be initialized, unless the caller makes sure the vec never reads or drops
+// the ones that are not
+unsafe fn from_slots<T>(slots: Slots<T>) -> Vec<T> {
+    let mut vec = ManuallyDrop::new(Vec::from(slots));
+    Vec::from_raw_parts(vec.as_mut_ptr() as *mut T, vec.len(), vec.capacity())
+}
+
+// views slots as values. Every slot must be initialized
+unsafe fn assume_init_slice<T>(slots: &[MaybeUninit<T>]) -> &[T] {
+    &*(slots as *const [MaybeUninit<T>] as *const [T])
+}
+
+unsafe fn assume_init_slice_mut<T>(slots: &mut [MaybeUninit<T>]) -> &mut [T] {
+    &mut *(slots as *mut [MaybeUninit<T>] as *mut [T])
+}
+
 impl<T> SafeUninitializedVec<T> {
     /// Creates a new `SafeUninitialzedVec` with a set length.
     pub fn new(len: usize) -> SafeUninitializedVec<T> {
-        let mut vec = Vec::with_capacity(len);
+        SafeUninitializedVec::new_in(len, Global)
+    }
+
+    /// Creates a new vector with a set length, which tracks its initialized slots as
+    /// ranges instead of with one bit per slot.
+    ///
+    /// This saves memory and time when values are set in large contiguous chunks, for
+    /// example with `set_range` or `collect_into`, but makes scattered single writes
+    /// slower. See `InitMask::new_intervals`. Borrowing mutable views switches the vector
+    /// to one bit per slot.
+    pub fn with_interval_tracking(len: usize) -> SafeUninitializedVec<T> {
+        let mut vec = Slots::with_capacity_in(len, Global);
         unsafe {
+            // the slots do not need to be initialized
             vec.set_len(len);
         }
-        SafeUninitializedVec {
-            vals: vec,
-            initialized: vec![false; len],
-        }
+        SafeUninitializedVec::from_parts(vec, InitMask::new_intervals(len))
+    }
+
+    /// Creates an empty vector that can hold `capacity` slots without reallocating.
+    pub fn with_capacity(capacity: usize) -> SafeUninitializedVec<T> {
+        SafeUninitializedVec::with_capacity_in(capacity, Global)
     }
 
     /// Uses and existing `Vec` to create a SafeUninitializedVec.
@@ -65,73 +202,296 @@ impl<T> SafeUninitializedVec<T> {
     /// then if will reserve additional capacity and increase the length of the `Vec` without
     /// initializing the further elements.
     /// The struct keeps track of these elements, allowing it to be used safely
-    pub fn from_vec(mut vec: Vec<T>, len: usize) -> SafeUninitializedVec<T> {
+    pub fn from_vec(vec: Vec<T>, len: usize) -> SafeUninitializedVec<T> {
         let mut init_vals;
-        let vec_len = (&vec).len();
+        let vec_len = vec.len();
+        let mut vec = into_slots(vec);
         if len > vec_len {
-            init_vals = vec![false; len];
-            for i in 0..(&vec).len() {
-                init_vals[i] = true;
-            }
-            vec.reserve(len - vec_len);
-            unsafe {
-                vec.set_len(len);
-            }
+            init_vals = InitMask::new(len);
+            init_vals.set_range(0..vec_len, true);
+            vec.resize_with(len, MaybeUninit::uninit);
         } else {
-            init_vals = vec![true; len];
-        }
-        SafeUninitializedVec {
-            vals: vec,
-            initialized: init_vals,
+            init_vals = InitMask::repeat(true, len);
         }
+        SafeUninitializedVec::from_parts(vec, init_vals)
     }
 
     /// Returns either the backing vector or an error that contains self.
     /// This error allows the vector to continue to be used even if this fails.
     pub fn into_vec(mut self) -> Result<Vec<T>, UninitializedError<T>> {
-        // Note: While none of this is marked as unsafe, it is nevertheless VERY UNSAFE
-        // This is because self.vals can contain UNINITIALIZED DATA
-
         // Here, we check to see if all the values that are being returned are initialized
-        let len = (&self.initialized).len();
-        for i in 0..len {
-            if !self.initialized[i] {
-                return Err(UninitializedError::new(self));
-            }
+        if !self.is_fully_initialized() {
+            return Err(UninitializedError::new(self));
         }
-        self.initialized = Vec::new();
-        Ok(mem::replace(&mut self.vals, Vec::new()))
+        self.untrack();
+        self.initialized.clear();
+        Ok(unsafe { from_slots(mem::take(&mut self.vals)) })
+    }
+
+    /// Moves the initialized values into a `Vec` in index order, skipping uninitialized
+    /// slots. The allocation is reused.
+    pub fn compact(mut self) -> Vec<T> {
+        let dense = self.compact_in_place();
+        self.untrack();
+        self.initialized.clear();
+        let mut vals = mem::take(&mut self.vals);
+        vals.truncate(dense);
+        // the first dense values are initialized
+        unsafe { from_slots(vals) }
     }
 
     /// Gets the values and a vec that contains a value of true for every initialized value
     /// and false for every uninitialized value.
+    ///
+    /// # Safety
+    /// The returned `Vec<T>` contains uninitialized values wherever the bool vec is false.
+    /// These must not be read or dropped, so the caller is responsible for setting the length
+    /// of the vec or otherwise making sure they are never accessed.
     pub unsafe fn get_parts(mut self) -> (Vec<T>, Vec<bool>) {
+        self.untrack();
+        (
+            from_slots(mem::take(&mut self.vals)),
+            mem::take(&mut self.initialized).into(),
+        )
+    }
+
+    /// Decomposes the vector into a pointer to its storage, its length, its capacity, and
+    /// the mask of which values are initialized.
+    ///
+    /// Nothing is dropped or freed. The vector can be rebuilt with `from_raw_parts`.
+    pub fn into_raw_parts(mut self) -> (*mut T, usize, usize, InitMask) {
+        self.track_tail();
+        self.untrack();
+        let mut vals = ManuallyDrop::new(mem::take(&mut self.vals));
+        let initialized = mem::take(&mut self.initialized);
         (
-            mem::replace(&mut self.vals, Vec::new()),
-            mem::replace(&mut self.initialized, Vec::new()),
+            vals.as_mut_ptr() as *mut T,
+            vals.len(),
+            vals.capacity(),
+            initialized,
         )
     }
 
+    /// Rebuilds a vector from the parts returned by `into_raw_parts`.
+    ///
+    /// # Safety
+    /// `ptr`, `len` and `capacity` must satisfy the requirements of `Vec::from_raw_parts`,
+    /// except that the values do not need to be initialized. Every value whose bit is set
+    /// in `initialized` must be initialized, and the length of the mask must be `len`.
+    pub unsafe fn from_raw_parts(
+        ptr: *mut T,
+        len: usize,
+        capacity: usize,
+        initialized: InitMask,
+    ) -> SafeUninitializedVec<T> {
+        assert_eq!(
+            initialized.len(),
+            len,
+            "mask length does not match vector length"
+        );
+        let vals = Vec::from_raw_parts(ptr as *mut MaybeUninit<T>, len, capacity);
+        SafeUninitializedVec::from_parts(Slots::from(vals), initialized)
+    }
+}
+
+impl<T, A: RawAlloc> SafeUninitializedVec<T, A> {
+    /// Creates a new vector with a set length, whose storage comes from `alloc`.
+    pub fn new_in(len: usize, alloc: A) -> SafeUninitializedVec<T, A> {
+        let mut vals = Slots::with_capacity_in(len, alloc);
+        unsafe {
+            // the slots do not need to be initialized
+            vals.set_len(len);
+        }
+        SafeUninitializedVec::from_parts(vals, InitMask::new(len))
+    }
+
+    /// Creates an empty vector whose storage comes from `alloc`, which can hold `capacity`
+    /// slots without reallocating.
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> SafeUninitializedVec<T, A> {
+        SafeUninitializedVec::from_parts(Slots::with_capacity_in(capacity, alloc), InitMask::new(0))
+    }
+
+    /// The allocator the storage of the vector comes from.
+    pub fn allocator(&self) -> &A {
+        self.vals.allocator()
+    }
+
+    /// Moves every initialized value to the front of the vector, preserving their order,
+    /// and returns how many there are. The length of the vector does not change, and every
+    /// slot after the returned length is left uninitialized.
+    pub fn compact_in_place(&mut self) -> usize {
+        self.track_tail();
+        self.compact_front()
+    }
+
+    /// The longest prefix of the vector that is initialized.
+    pub fn initialized_prefix(&self) -> &[T] {
+        let end = self.first_uninitialized().unwrap_or(self.vals.len());
+        unsafe { assume_init_slice(&self.vals[..end]) }
+    }
+
+    /// Moves the longest initialized prefix out of the vector. The remaining slots are
+    /// shifted to the front, keeping their initialization state, so that the next prefix
+    /// can be taken once it has been filled.
+    pub fn take_initialized_prefix(&mut self) -> Vec<T> {
+        self.track_tail();
+        let len = self.vals.len();
+        let end = self.first_uninitialized().unwrap_or(len);
+        self.initialized = self.initialized.split_off(end);
+        self.report_untracked(end);
+        self.report_deinitialized(end);
+        let mut prefix = Vec::with_capacity(end);
+        unsafe {
+            let start = self.vals.as_mut_ptr();
+            ptr::copy_nonoverlapping(start as *const T, prefix.as_mut_ptr(), end);
+            prefix.set_len(end);
+            ptr::copy(start.add(end), start, len - end);
+            scrub(start.add(len - end), end);
+            self.vals.set_len(len - end);
+        }
+        prefix
+    }
+
+    /// Tags the vector with a region for the `registry`, replacing any region it had.
+    #[cfg(feature = "registry")]
+    pub fn set_region(&mut self, region: &'static str) {
+        self.registration.set_region(region);
+    }
+
+    /// The region the vector is tagged with in the `registry`.
+    #[cfg(feature = "registry")]
+    pub fn region(&self) -> Option<&'static str> {
+        self.registration.region()
+    }
+
+    /// Gets the mask of which values are initialized.
+    pub fn mask(&self) -> &InitMask {
+        &self.initialized
+    }
+
+    /// The number of slots in the vector, including any values past the end of the mask
+    /// that were left over from `from_vec`.
+    pub fn len(&self) -> usize {
+        self.vals.len()
+    }
+
+    /// Returns true if the vector has no slots.
+    pub fn is_empty(&self) -> bool {
+        self.vals.is_empty()
+    }
+
+    /// The number of slots the backing storage can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.vals.capacity()
+    }
+
+    /// Reserves space for at least `additional` more slots, in both the values and the mask.
+    pub fn reserve(&mut self, additional: usize) {
+        let untracked = self.vals.len() - self.initialized.len();
+        self.vals.reserve(additional);
+        self.initialized.reserve(untracked + additional);
+    }
+
+    /// Reserves space for exactly `additional` more slots, in both the values and the mask.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        let untracked = self.vals.len() - self.initialized.len();
+        self.vals.reserve_exact(additional);
+        self.initialized.reserve_exact(untracked + additional);
+    }
+
+    /// Frees as much unused space as possible, in both the values and the mask.
+    pub fn shrink_to_fit(&mut self) {
+        self.vals.shrink_to_fit();
+        self.initialized.shrink_to_fit();
+    }
+
+    /// The number of slots covered by the mask. The slots past this are initialized values
+    /// that were left over from `from_vec` when it was given a longer `Vec`.
+    pub fn tracked_len(&self) -> usize {
+        self.initialized.len()
+    }
+
+    /// The number of initialized values. This takes constant time.
+    pub fn count_initialized(&self) -> usize {
+        self.initialized_count.load(Ordering::Relaxed) + self.vals.len() - self.initialized.len()
+    }
+
+    /// The number of uninitialized slots. This takes constant time.
+    pub fn count_uninitialized(&self) -> usize {
+        self.vals.len() - self.count_initialized()
+    }
+
+    /// Returns true if every slot is initialized. This takes constant time.
+    pub fn is_fully_initialized(&self) -> bool {
+        self.count_uninitialized() == 0
+    }
+
+    /// The index of the first uninitialized slot, if there is one.
+    pub fn first_uninitialized(&self) -> Option<usize> {
+        self.initialized.next_zero(0)
+    }
+
+    /// The index of the first uninitialized slot after `i`, if there is one.
+    pub fn next_uninitialized_after(&self, i: usize) -> Option<usize> {
+        self.initialized.next_zero(i.saturating_add(1))
+    }
+
+    /// An iterator over the indices of the uninitialized slots, in order.
+    pub fn uninitialized_indices(&self) -> Indices<'_> {
+        self.initialized.zeros()
+    }
+
     /// Sets a value in the array to the provided value. This will initialize the
     /// value if it is uninitialized, and drops an existing value if present.
     pub fn set_value(&mut self, i: usize, val: T) {
-        if self.initialized[i] {
+        if self.holds_value(i) {
             // replace vals[i] with val, running the destructor on the existing value
-            self.vals[i] = val;
-        } else {
+            let slot = self.vals[i].as_mut_ptr();
             unsafe {
-                // write to vals[i] without running a destructor on uninitialzed memory
-                ptr::write(&mut self.vals[i], val);
-                self.initialized[i] = true;
+                ptr::drop_in_place(slot);
+                scrub(slot, 1);
+                ptr::write(slot, val);
             }
+        } else {
+            self.vals[i].write(val);
+            self.initialized.set(i, true);
+            self.report_initialized(1);
+        }
+    }
+
+    /// Sets a value like `set_value`, but returns an error containing the value instead of
+    /// panicking if `i` is out of bounds.
+    pub fn try_set_value(&mut self, i: usize, val: T) -> Result<(), IndexError<T>> {
+        if i >= self.vals.len() {
+            return Err(IndexError {
+                index: i,
+                len: self.vals.len(),
+                val,
+            });
         }
+        self.set_value(i, val);
+        Ok(())
+    }
+
+    /// Gets a value like `get_value`, but returns an error instead of panicking if `i` is
+    /// out of bounds.
+    pub fn try_get_value(&self, i: usize) -> Result<Option<&T>, IndexError> {
+        self.check_index(i)?;
+        Ok(self.get_value(i))
+    }
+
+    /// Gets a value like `get_value_mut`, but returns an error instead of panicking if `i`
+    /// is out of bounds.
+    pub fn try_get_value_mut(&mut self, i: usize) -> Result<Option<&mut T>, IndexError> {
+        self.check_index(i)?;
+        Ok(self.get_value_mut(i))
     }
 
     /// Gets a reference to an element of the vector. Will return none
     /// if the value is not initialized.
-    pub fn get_value<'a>(&'a self, i: usize) -> Option<&'a T> {
-        if self.initialized[i] {
-            Some(&self.vals[i])
+    pub fn get_value(&self, i: usize) -> Option<&T> {
+        if self.holds_value(i) {
+            Some(unsafe { self.vals[i].assume_init_ref() })
         } else {
             None
         }
@@ -139,32 +499,209 @@ impl<T> SafeUninitializedVec<T> {
 
     /// Gets a mutable reference to an element of the vector. Will
     /// return none if the value is not initialized.
-    pub fn get_value_mut<'a>(&'a mut self, i: usize) -> Option<&'a T> {
-        if self.initialized[i] {
-            Some(&mut self.vals[i])
+    pub fn get_value_mut(&mut self, i: usize) -> Option<&mut T> {
+        if self.holds_value(i) {
+            Some(unsafe { self.vals[i].assume_init_mut() })
         } else {
             None
         }
     }
 
+    /// Gets mutable references to several elements at once. Returns none if any index is
+    /// out of bounds or uninitialized, or if two indices are the same.
+    pub fn get_many_mut<const N: usize>(&mut self, indices: [usize; N]) -> Option<[&mut T; N]> {
+        let len = self.vals.len();
+        for (n, &i) in indices.iter().enumerate() {
+            if i >= len || !self.holds_value(i) || indices[..n].contains(&i) {
+                return None;
+            }
+        }
+        let vals = self.vals.as_mut_ptr();
+        // the indices are in bounds and distinct, so the references never alias
+        Some(indices.map(|i| unsafe { (*vals.add(i)).assume_init_mut() }))
+    }
+
+    /// Gets a mutable reference to an element, initializing it with `f` first if it is
+    /// not initialized.
+    pub fn get_or_insert_with<F: FnOnce() -> T>(&mut self, i: usize, f: F) -> &mut T {
+        if !self.holds_value(i) {
+            self.set_value(i, f());
+        }
+        unsafe { self.vals[i].assume_init_mut() }
+    }
+
+    /// Gets a reference to an element without checking that it is in bounds or initialized.
+    ///
+    /// # Safety
+    /// `i` must be less than `len`, and the value at `i` must be initialized.
+    pub unsafe fn get_unchecked(&self, i: usize) -> &T {
+        self.vals.get_unchecked(i).assume_init_ref()
+    }
+
+    /// Gets a mutable reference to an element without checking that it is in bounds or
+    /// initialized.
+    ///
+    /// # Safety
+    /// `i` must be less than `len`, and the value at `i` must be initialized.
+    pub unsafe fn get_unchecked_mut(&mut self, i: usize) -> &mut T {
+        self.vals.get_unchecked_mut(i).assume_init_mut()
+    }
+
+    /// Initializes a value without checking that it is in bounds or that the slot is
+    /// uninitialized.
+    ///
+    /// # Safety
+    /// `i` must be less than `tracked_len`, and the value at `i` must be uninitialized.
+    /// If it is initialized, the old value is overwritten without being dropped, and the
+    /// count of initialized values becomes wrong.
+    pub unsafe fn set_value_unchecked(&mut self, i: usize, val: T) {
+        self.vals.get_unchecked_mut(i).write(val);
+        self.initialized.set_unchecked(i, true);
+        self.report_initialized(1);
+    }
+
     /// Swaps two elements.
     pub fn swap(&mut self, x: usize, y: usize) {
         self.initialized.swap(x, y);
         self.vals.swap(x, y);
     }
 
+    /// Appends an initialized value to the end of the vector.
+    pub fn push(&mut self, val: T) {
+        self.track_tail();
+        self.vals.push(MaybeUninit::new(val));
+        self.initialized.push(true);
+        self.report_tracked(1);
+        self.report_initialized(1);
+    }
+
+    /// Removes the last slot of the vector, returning its value if it was initialized.
+    ///
+    /// Returns none if the slot was uninitialized or the vector is empty. Use `len` to
+    /// tell these apart.
+    pub fn pop(&mut self) -> Option<T> {
+        self.track_tail();
+        let init = self.initialized.pop()?;
+        self.report_untracked(1);
+        let slot = self.vals.pop();
+        if init {
+            self.report_deinitialized(1);
+            unsafe {
+                scrub(self.vals.as_mut_ptr().add(self.vals.len()), 1);
+                slot.map(|slot| slot.assume_init())
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Inserts an initialized value at `i`, shifting every slot after it up.
+    ///
+    /// Panics if `i` is greater than the length of the vector.
+    pub fn insert(&mut self, i: usize, val: T) {
+        let len = self.vals.len();
+        assert!(i <= len, "index {} out of bounds for length {}", i, len);
+        self.track_tail();
+        self.vals.reserve(1);
+        unsafe {
+            let slot = self.vals.as_mut_ptr().add(i);
+            ptr::copy(slot, slot.add(1), len - i);
+            ptr::write(slot, MaybeUninit::new(val));
+            self.vals.set_len(len + 1);
+        }
+        self.initialized.insert(i, true);
+        self.report_tracked(1);
+        self.report_initialized(1);
+    }
+
+    /// Removes slot `i`, shifting every slot after it down. Returns the value if it was
+    /// initialized.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn remove(&mut self, i: usize) -> Option<T> {
+        let len = self.vals.len();
+        assert!(i < len, "index {} out of bounds for length {}", i, len);
+        self.track_tail();
+        let init = self.initialized.remove(i);
+        self.report_untracked(1);
+        unsafe {
+            let slot = self.vals.as_mut_ptr().add(i);
+            // the value is only read if it is initialized
+            let val = if init {
+                Some(ptr::read(slot).assume_init())
+            } else {
+                None
+            };
+            ptr::copy(slot.add(1), slot, len - i - 1);
+            scrub(self.vals.as_mut_ptr().add(len - 1), 1);
+            self.vals.set_len(len - 1);
+            if init {
+                self.report_deinitialized(1);
+            }
+            val
+        }
+    }
+
+    /// Drops every initialized value for which `f` returns false, marking its slot as
+    /// uninitialized. Nothing is moved, and values are visited in index order.
+    pub fn retain<F: FnMut(usize, &mut T) -> bool>(&mut self, mut f: F) {
+        self.track_tail();
+        let mut next = self.initialized.next_one(0);
+        while let Some(i) = next {
+            if !f(i, unsafe { self.vals[i].assume_init_mut() }) {
+                drop(self.take(i));
+            }
+            next = self.initialized.next_one(i + 1);
+        }
+    }
+
+    /// Changes the length of the vector. New slots are uninitialized, and initialized
+    /// values in slots that are removed are dropped.
+    pub fn resize(&mut self, new_len: usize) {
+        self.track_tail();
+        let len = self.vals.len();
+        if new_len > len {
+            self.vals.resize_with(new_len, MaybeUninit::uninit);
+            self.initialized.resize(new_len, false);
+            self.report_tracked(new_len - len);
+        } else {
+            // each value is dropped after its slot is removed, so a panic leaves the
+            // vector consistent
+            for _ in new_len..len {
+                self.pop();
+            }
+        }
+    }
+
+    /// Shortens the vector to `len` slots, dropping the initialized values that are
+    /// removed. The allocation is kept. Does nothing if the vector is not longer than `len`.
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.vals.len() {
+            self.resize(len);
+        }
+    }
+
+    /// Removes every slot, dropping the initialized values. The allocation is kept.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
     /// Moves a value out of the array, marking its space in the array as uninitialized
     pub fn take(&mut self, i: usize) -> Option<T> {
+        if i >= self.initialized.len() && i < self.vals.len() {
+            self.track_tail();
+        }
         // first check that the value being requested is initialized
-        if self.initialized[i] {
+        if self.initialized.is_set(i) {
             // mark that the value has been deinitialized
-            self.initialized[i] = false;
+            self.initialized.set(i, false);
+            self.report_deinitialized(1);
+            // the slot is now marked as uninitialized, so the value is only moved out once
             unsafe {
-                // create memory on the stack for the value to be copied into
-                let mut value = mem::uninitialized();
-                // move the value in the array into the result
-                ptr::copy(&self.vals[i], &mut value, 1);
-                Some(value)
+                let slot = self.vals.as_mut_ptr().add(i);
+                let val = ptr::read(slot).assume_init();
+                scrub(slot, 1);
+                Some(val)
             }
         } else {
             None
@@ -172,20 +709,280 @@ impl<T> SafeUninitializedVec<T> {
     }
 }
 
+impl<T, A: RawAlloc> SafeUninitializedVec<T, A> {
+    // every constructor goes through here, so that the vector is reported to `instrument`
+    fn from_parts(vals: Slots<T, A>, initialized: InitMask) -> SafeUninitializedVec<T, A> {
+        instrument::container_created();
+        let count = initialized.count_ones();
+        let mut vec = SafeUninitializedVec {
+            vals,
+            initialized,
+            initialized_count: AtomicUsize::new(0),
+            #[cfg(feature = "registry")]
+            registration: Registration::new::<T>(),
+        };
+        vec.report_tracked(vec.initialized.len());
+        vec.report_initialized(count);
+        vec
+    }
+
+    // the reverse of `from_parts`, which takes the storage and the mask out of the vector
+    // without dropping any values. The untracked tail is tracked first
+    fn into_parts(mut self) -> (Slots<T, A>, InitMask) {
+        self.track_tail();
+        self.untrack();
+        instrument::container_dropped();
+        let vec = ManuallyDrop::new(self);
+        // every field is moved out exactly once
+        unsafe {
+            #[cfg(feature = "registry")]
+            drop(ptr::read(&vec.registration));
+            (ptr::read(&vec.vals), ptr::read(&vec.initialized))
+        }
+    }
+
+    // reports that the current contents of the vector are no longer tracked
+    // this must be called before the vals and mask are taken out of the vector
+    fn untrack(&mut self) {
+        let count = *self.initialized_count.get_mut();
+        self.report_untracked(self.initialized.len());
+        self.report_deinitialized(count);
+    }
+
+    // reports that `len` more slots are tracked
+    fn report_tracked(&self, len: usize) {
+        instrument::bytes_tracked(len * mem::size_of::<T>());
+        #[cfg(feature = "registry")]
+        self.registration.tracked(len);
+    }
+
+    // reports that `len` slots are no longer tracked
+    fn report_untracked(&self, len: usize) {
+        instrument::bytes_untracked(len * mem::size_of::<T>());
+        #[cfg(feature = "registry")]
+        self.registration.untracked(len);
+    }
+
+    // reports that `count` slots were initialized
+    fn report_initialized(&mut self, count: usize) {
+        *self.initialized_count.get_mut() += count;
+        instrument::slots_initialized(count);
+        #[cfg(feature = "registry")]
+        self.registration.initialized(count);
+    }
+
+    // reports that `count` slots were deinitialized
+    fn report_deinitialized(&mut self, count: usize) {
+        *self.initialized_count.get_mut() -= count;
+        instrument::slots_deinitialized(count);
+        #[cfg(feature = "registry")]
+        self.registration.deinitialized(count);
+    }
+
+    // starts tracking the initialized values past the end of the mask that were left
+    // over from `from_vec`, so that they can be deinitialized
+    fn track_tail(&mut self) {
+        let tracked = self.initialized.len();
+        let len = self.vals.len();
+        if len > tracked {
+            self.initialized.resize(len, true);
+            self.report_tracked(len - tracked);
+            self.report_initialized(len - tracked);
+        }
+    }
+
+    fn check_index(&self, i: usize) -> Result<(), IndexError> {
+        if i < self.vals.len() {
+            Ok(())
+        } else {
+            Err(IndexError {
+                index: i,
+                len: self.vals.len(),
+                val: (),
+            })
+        }
+    }
+
+    // whether slot `i` holds a value, counting the untracked tail as initialized
+    fn holds_value(&self, i: usize) -> bool {
+        i >= self.initialized.len() || self.initialized.is_set(i)
+    }
+
+    // moves every initialized value to the front of the vector, preserving their order,
+    // and returns the number of initialized values
+    fn compact_front(&mut self) -> usize {
+        let len = self.initialized.len();
+        let mut dense = 0;
+        let mut from = 0;
+        // values are moved a run at a time
+        while let Some(start) = self.initialized.next_one(from) {
+            let end = self.initialized.next_zero(start).unwrap_or(len);
+            let count = end - start;
+            if start != dense {
+                unsafe {
+                    let vals = self.vals.as_mut_ptr();
+                    // the slots before start are uninitialized, so nothing is overwritten
+                    ptr::copy(vals.add(start), vals.add(dense), count);
+                    let vacated = cmp::max(start, dense + count);
+                    scrub(vals.add(vacated), end - vacated);
+                }
+                self.initialized.set_range(start..end, false);
+                self.initialized.set_range(dense..dense + count, true);
+            }
+            dense += count;
+            from = end;
+        }
+        dense
+    }
+
+    // the first uninitialized slot in a range, which may extend into the untracked tail
+    fn first_uninitialized_in(&self, range: Range<usize>) -> Option<usize> {
+        let end = cmp::min(range.end, self.initialized.len());
+        self.initialized.next_zero(range.start).filter(|&i| i < end)
+    }
+}
+
+impl<T, A: RawAlloc + Default> Default for SafeUninitializedVec<T, A> {
+    /// Creates an empty vector.
+    fn default() -> SafeUninitializedVec<T, A> {
+        SafeUninitializedVec::new_in(0, A::default())
+    }
+}
+
+impl<T: Clone, A: RawAlloc + Clone> Clone for SafeUninitializedVec<T, A> {
+    /// Clones every initialized value into a new vector with the same mask. Slots that are
+    /// uninitialized here are left uninitialized in the clone, whose storage comes from a
+    /// clone of the allocator.
+    fn clone(&self) -> SafeUninitializedVec<T, A> {
+        let tracked = self.initialized.len();
+        let mut clone = SafeUninitializedVec::new_in(tracked, self.allocator().clone());
+        clone.vals.reserve_exact(self.vals.len() - tracked);
+        // each value is written as it is cloned, so a panic leaves the clone consistent
+        for i in self.initialized.ones() {
+            clone.set_value(i, unsafe { self.vals[i].assume_init_ref() }.clone());
+        }
+        // the untracked tail stays untracked, like in the original
+        for val in unsafe { assume_init_slice(&self.vals[tracked..]) } {
+            clone.vals.push(MaybeUninit::new(val.clone()));
+        }
+        clone
+    }
+}
+
+impl<T: PartialEq, A: RawAlloc, B: RawAlloc> PartialEq<SafeUninitializedVec<T, B>>
+    for SafeUninitializedVec<T, A>
+{
+    /// Two vectors are equal if they have the same length, the same slots are initialized,
+    /// and the initialized values are equal. Values past the end of the mask count as
+    /// initialized. The allocators are not compared.
+    fn eq(&self, other: &SafeUninitializedVec<T, B>) -> bool {
+        self.vals.len() == other.vals.len()
+            && (0..self.vals.len()).all(|i| self.get_value(i) == other.get_value(i))
+    }
+}
+
+impl<T: Eq, A: RawAlloc> Eq for SafeUninitializedVec<T, A> {}
+
+// the number of runs and slots shown by the debug output before it is cut off
+const DEBUG_RUNS: usize = 8;
+const DEBUG_VALUES: usize = 16;
+
+impl<T: fmt::Debug, A: RawAlloc> fmt::Debug for SafeUninitializedVec<T, A> {
+    /// Shows a summary of the vector: its length, how many values are initialized, the
+    /// runs of initialized slots and every slot in order, with `<uninit>` for the
+    /// uninitialized ones. Long vectors only show the first few runs and slots.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // stands in for a value that is not initialized
+        struct Hole;
+
+        impl fmt::Debug for Hole {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "<uninit>")
+            }
+        }
+
+        let runs = || {
+            self.initialized_runs()
+                .map(|(start, run)| start..start + run.len())
+        };
+        let values = || {
+            (0..self.vals.len()).map(|i| match self.get_value(i) {
+                Some(val) => val as &dyn fmt::Debug,
+                None => &Hole as &dyn fmt::Debug,
+            })
+        };
+        f.debug_struct("SafeUninitializedVec")
+            .field("len", &self.vals.len())
+            .field("initialized", &self.count_initialized())
+            .field("runs", &Truncated(DEBUG_RUNS, runs))
+            .field("values", &Truncated(DEBUG_VALUES, values))
+            .finish()
+    }
+}
+
+// formats the first items of an iterator as a list, ending it with `..` if there are more
+struct Truncated<F>(usize, F);
+
+impl<F, I> fmt::Debug for Truncated<F>
+where
+    F: Fn() -> I,
+    I: Iterator,
+    I::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut items = (self.1)();
+        let mut list = f.debug_list();
+        list.entries(items.by_ref().take(self.0));
+        if items.next().is_some() {
+            list.entry(&format_args!(".."));
+        }
+        list.finish()
+    }
+}
+
+/// The error returned when a vector could not be converted because it still had
+/// uninitialized slots. It contains the vector, so it can still be used.
 pub struct UninitializedError<T> {
     vec: SafeUninitializedVec<T>,
 }
 
 impl<T> UninitializedError<T> {
     fn new(vec: SafeUninitializedVec<T>) -> UninitializedError<T> {
-        UninitializedError { vec: vec }
+        UninitializedError { vec }
     }
 
     pub fn unwrap(self) -> SafeUninitializedVec<T> {
         self.vec
     }
+
+    /// The index of the first uninitialized slot.
+    pub fn first_uninitialized_index(&self) -> Option<usize> {
+        self.vec.first_uninitialized()
+    }
+
+    /// An iterator over the indices of every uninitialized slot.
+    pub fn missing_indices(&self) -> Indices<'_> {
+        self.vec.uninitialized_indices()
+    }
+}
+
+impl<T> fmt::Display for UninitializedError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} slots are uninitialized",
+            self.vec.count_uninitialized(),
+            self.vec.len()
+        )?;
+        if let Some(index) = self.first_uninitialized_index() {
+            write!(f, ", the first at index {}", index)?;
+        }
+        Ok(())
+    }
 }
 
+impl<T> error::Error for UninitializedError<T> {}
+
 impl<T> fmt::Debug for UninitializedError<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
@@ -194,3 +991,60 @@ impl<T> fmt::Debug for UninitializedError<T> {
         )
     }
 }
+
+/// The error returned when an index is out of bounds. If the operation was given a value,
+/// the error contains it, so it is not lost.
+pub struct IndexError<T = ()> {
+    index: usize,
+    len: usize,
+    val: T,
+}
+
+#[allow(clippy::len_without_is_empty)]
+impl<T> IndexError<T> {
+    /// The index that was used.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The length of the vector.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Gets back the value that could not be stored.
+    pub fn into_inner(self) -> T {
+        self.val
+    }
+}
+
+impl<T> fmt::Debug for IndexError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IndexError")
+            .field("index", &self.index)
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for IndexError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "index {} is out of bounds for a vector of length {}",
+            self.index, self.len
+        )
+    }
+}
+
+impl<T> error::Error for IndexError<T> {}
+
+impl<T> From<IndexError<T>> for Error {
+    /// Converts the error, dropping the value it contains.
+    fn from(err: IndexError<T>) -> Error {
+        Error::OutOfBounds {
+            index: err.index,
+            len: err.len,
+        }
+    }
+}
@@ -0,0 +1,110 @@
+//! A write-once mode for vectors, which treats writing a slot twice as an error.
+
+use std::error;
+use std::fmt;
+use std::ops::Deref;
+
+use super::SafeUninitializedVec;
+use alloc::{Global, RawAlloc};
+use error::Error;
+
+/// A `SafeUninitializedVec` whose slots can each be written once.
+///
+/// Writing to a slot that is already initialized returns an error and leaves the old value
+/// in place, so a pipeline that fills slots out of order notices when two writers produce
+/// the same slot. Everything that only reads the vector is available through `Deref`.
+pub struct OnceVec<T, A: RawAlloc = Global> {
+    vec: SafeUninitializedVec<T, A>,
+}
+
+impl<T> OnceVec<T> {
+    /// Creates a vector of `len` uninitialized slots.
+    pub fn new(len: usize) -> OnceVec<T> {
+        OnceVec {
+            vec: SafeUninitializedVec::new(len),
+        }
+    }
+}
+
+impl<T, A: RawAlloc> OnceVec<T, A> {
+    /// Initializes slot `i`. If the slot already holds a value, it is kept, and `val` is
+    /// given back in the error.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn set_value(&mut self, i: usize, val: T) -> Result<(), AlreadyInitializedError<T>> {
+        if self.vec.get_value(i).is_some() {
+            return Err(AlreadyInitializedError { index: i, val });
+        }
+        self.vec.set_value(i, val);
+        Ok(())
+    }
+
+    /// Gets back the vector, which can then be written to freely.
+    pub fn into_inner(self) -> SafeUninitializedVec<T, A> {
+        self.vec
+    }
+}
+
+impl<T, A: RawAlloc> From<SafeUninitializedVec<T, A>> for OnceVec<T, A> {
+    /// Puts a vector in write-once mode. Slots that are already initialized can not be
+    /// written again.
+    fn from(vec: SafeUninitializedVec<T, A>) -> OnceVec<T, A> {
+        OnceVec { vec }
+    }
+}
+
+impl<T, A: RawAlloc> Deref for OnceVec<T, A> {
+    type Target = SafeUninitializedVec<T, A>;
+
+    fn deref(&self) -> &SafeUninitializedVec<T, A> {
+        &self.vec
+    }
+}
+
+impl<T: fmt::Debug, A: RawAlloc> fmt::Debug for OnceVec<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("OnceVec").field(&self.vec).finish()
+    }
+}
+
+/// The error returned when a slot of a `OnceVec` is written a second time. It contains
+/// the value that was not written.
+pub struct AlreadyInitializedError<T> {
+    index: usize,
+    val: T,
+}
+
+impl<T> AlreadyInitializedError<T> {
+    /// The slot that was already initialized.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Gets back the value that could not be stored.
+    pub fn into_inner(self) -> T {
+        self.val
+    }
+}
+
+impl<T> fmt::Debug for AlreadyInitializedError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AlreadyInitializedError")
+            .field("index", &self.index)
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for AlreadyInitializedError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "slot {} is already initialized", self.index)
+    }
+}
+
+impl<T> error::Error for AlreadyInitializedError<T> {}
+
+impl<T> From<AlreadyInitializedError<T>> for Error {
+    /// Converts the error, dropping the value it contains.
+    fn from(err: AlreadyInitializedError<T>) -> Error {
+        Error::AlreadyInitialized { index: err.index }
+    }
+}
@@ -0,0 +1,123 @@
+//! Parallel operations on a `SafeUninitializedVec` using rayon.
+//!
+//! Only available with the `rayon` feature.
+
+use std::cmp::{self, Ordering};
+use std::ops::Range;
+
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+use rayon::slice::ParallelSliceMut;
+
+use super::{assume_init_slice_mut, scrub, SafeUninitializedVec};
+
+// a pointer to the values that can be shared between threads
+// every thread only reads slots that no other thread touches
+#[derive(Clone, Copy)]
+struct SharedPtr<T>(*mut T);
+
+unsafe impl<T: Send> Send for SharedPtr<T> {}
+unsafe impl<T: Send> Sync for SharedPtr<T> {}
+
+impl<T: Send> SafeUninitializedVec<T> {
+    /// Initializes every uninitialized slot in parallel with the result of calling `f`
+    /// with its index. Values that are already initialized are left as they are.
+    ///
+    /// The vector is split into disjoint views, which are filled on the rayon thread pool.
+    pub fn par_init_with<F>(&mut self, f: F)
+    where
+        F: Fn(usize) -> T + Sync,
+    {
+        let len = self.vals.len();
+        let chunk = cmp::max(len.div_ceil(rayon::current_num_threads() * 4), 1);
+        let mut views = Vec::new();
+        let mut rest = self.view_mut(0..len);
+        while rest.len() > chunk {
+            let (view, tail) = rest.split_at_mut(chunk);
+            views.push(view);
+            rest = tail;
+        }
+        views.push(rest);
+        views
+            .into_par_iter()
+            .enumerate()
+            .for_each(|(n, mut view)| view.init_missing_with(|i| f(n * chunk + i)));
+    }
+
+    /// A parallel iterator over the initialized values and their indices.
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (usize, &T)> + '_
+    where
+        T: Sync,
+    {
+        (0..self.vals.len())
+            .into_par_iter()
+            .filter_map(move |i| self.get_value(i).map(|val| (i, val)))
+    }
+
+    /// A parallel iterator over mutable references to the initialized values and their
+    /// indices.
+    pub fn par_iter_mut(&mut self) -> impl ParallelIterator<Item = (usize, &mut T)> + '_ {
+        let tracked = self.initialized.len();
+        let mask = &self.initialized;
+        let vals = SharedPtr(self.vals.as_mut_ptr() as *mut T);
+        (0..self.vals.len())
+            .into_par_iter()
+            .filter(move |&i| i >= tracked || mask.is_set(i))
+            // every index is only visited once, so the references never alias
+            .map(move |i| (i, unsafe { &mut *vals.0.add(i) }))
+    }
+
+    /// Sorts the initialized values in parallel, returning the range they occupy.
+    ///
+    /// If the initialized values are already contiguous, they are sorted where they are.
+    /// Otherwise, they are first moved to the front of the vector. The sort is stable.
+    pub fn par_sort_initialized(&mut self) -> Range<usize>
+    where
+        T: Ord,
+    {
+        self.par_sort_by(Ord::cmp)
+    }
+
+    /// Sorts the initialized values in parallel with a comparator function, returning
+    /// the range they occupy. Works like `par_sort_initialized`.
+    pub fn par_sort_by<F>(&mut self, compare: F) -> Range<usize>
+    where
+        F: Fn(&T, &T) -> Ordering + Sync,
+    {
+        self.track_tail();
+        let range = self.contiguous_initialized();
+        // every value in the range is initialized
+        unsafe { assume_init_slice_mut(&mut self.vals[range.clone()]) }.par_sort_by(compare);
+        range
+    }
+
+    /// Moves every initialized value out of the vector in parallel, returning them
+    /// along with their indices, in index order. Every slot is left uninitialized.
+    pub fn par_collect_initialized(&mut self) -> Vec<(usize, T)> {
+        self.track_tail();
+        let indices: Vec<usize> = self.initialized.ones().collect();
+        // mark everything as uninitialized first, so a panic can only leak values
+        let len = self.initialized.len();
+        self.initialized.set_range(0..len, false);
+        self.report_deinitialized(indices.len());
+        let vals = SharedPtr(self.vals.as_mut_ptr() as *mut T);
+        indices
+            .into_par_iter()
+            .map(move |i| unsafe {
+                let slot = vals.0.add(i);
+                let val = slot.read();
+                scrub(slot, 1);
+                (i, val)
+            })
+            .collect()
+    }
+
+    // gets the range of initialized values, moving them to the front if they are not contiguous
+    fn contiguous_initialized(&mut self) -> Range<usize> {
+        let mut runs = self.initialized.runs();
+        match (runs.next(), runs.next()) {
+            (None, _) => 0..0,
+            (Some(run), None) => run,
+            _ => 0..self.compact_front(),
+        }
+    }
+}
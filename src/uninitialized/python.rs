@@ -0,0 +1,188 @@
+//! Python buffer protocol support for tracked byte buffers.
+//!
+//! A `PyTrackedBuffer` can be passed to Python, where it can be written to through
+//! a `memoryview` or any other API that accepts writable buffers. Python code then calls
+//! `commit(start, end)` to mark the bytes it wrote as initialized, and Rust code can take
+//! the tracked buffer back without copying it.
+//!
+//! Only available with the `python` feature.
+
+use std::ffi::CString;
+use std::mem;
+use std::os::raw::{c_int, c_void};
+use std::ptr;
+
+use pyo3::exceptions::{PyBufferError, PyIndexError};
+use pyo3::prelude::*;
+use pyo3::{ffi, Bound, PyResult};
+
+use super::SafeUninitializedVec;
+
+/// A tracked `u8` buffer that can be shared with Python using the buffer protocol.
+///
+/// Uninitialized bytes are zeroed when the buffer is wrapped, since Python is allowed
+/// to read any byte of the buffer. They are still tracked as uninitialized until they
+/// are committed.
+#[pyclass(name = "TrackedBuffer")]
+pub struct PyTrackedBuffer {
+    buf: SafeUninitializedVec<u8>,
+    // the number of buffer views currently held by Python
+    exports: usize,
+}
+
+impl PyTrackedBuffer {
+    /// Wraps a tracked buffer so it can be passed to Python.
+    pub fn new(buf: SafeUninitializedVec<u8>) -> PyTrackedBuffer {
+        let mut buf = PyTrackedBuffer { buf, exports: 0 };
+        buf.zero_uninitialized();
+        buf
+    }
+
+    /// Gets the tracked buffer.
+    pub fn get(&self) -> &SafeUninitializedVec<u8> {
+        &self.buf
+    }
+
+    /// Takes the tracked buffer out, leaving an empty buffer in its place.
+    ///
+    /// Fails if Python still holds a view of the buffer.
+    pub fn take_buffer(&mut self) -> PyResult<SafeUninitializedVec<u8>> {
+        if self.exports > 0 {
+            return Err(PyBufferError::new_err("buffer is still exported"));
+        }
+        Ok(mem::replace(&mut self.buf, SafeUninitializedVec::new(0)))
+    }
+
+    /// Adds the `TrackedBuffer` class to a Python module.
+    pub fn register(module: &Bound<'_, PyModule>) -> PyResult<()> {
+        module.add_class::<PyTrackedBuffer>()
+    }
+}
+
+#[pymethods]
+impl PyTrackedBuffer {
+    /// Creates a buffer of `len` uninitialized bytes.
+    #[new]
+    fn py_new(len: usize) -> PyTrackedBuffer {
+        PyTrackedBuffer::new(SafeUninitializedVec::new(len))
+    }
+
+    fn __len__(&self) -> usize {
+        self.buf.initialized.len()
+    }
+
+    /// Marks the bytes in `start..end` as initialized.
+    fn commit(&mut self, start: usize, end: usize) -> PyResult<()> {
+        self.check_range(start, end)?;
+        // every byte was zeroed when the buffer was created, so all of them are valid
+        let changed = self.buf.initialized.set_range(start..end, true);
+        self.buf.report_initialized(changed);
+        Ok(())
+    }
+
+    /// Marks the bytes in `start..end` as uninitialized.
+    fn discard(&mut self, start: usize, end: usize) -> PyResult<()> {
+        self.check_range(start, end)?;
+        let changed = self.buf.initialized.set_range(start..end, false);
+        self.buf.report_deinitialized(changed);
+        Ok(())
+    }
+
+    /// Returns true if byte `i` is initialized.
+    fn is_initialized(&self, i: usize) -> PyResult<bool> {
+        if i >= self.buf.initialized.len() {
+            return Err(PyIndexError::new_err("index out of range"));
+        }
+        Ok(self.buf.initialized.is_set(i))
+    }
+
+    /// The number of initialized bytes.
+    fn count_initialized(&self) -> usize {
+        self.buf.count_initialized()
+    }
+
+    /// A list of `(start, end)` pairs for every run of initialized bytes.
+    fn initialized_ranges(&self) -> Vec<(usize, usize)> {
+        self.buf
+            .initialized
+            .runs()
+            .map(|r| (r.start, r.end))
+            .collect()
+    }
+
+    /// A list of `(start, end)` pairs for every run of uninitialized bytes.
+    fn uninitialized_ranges(&self) -> Vec<(usize, usize)> {
+        self.buf
+            .initialized
+            .gaps()
+            .map(|r| (r.start, r.end))
+            .collect()
+    }
+
+    unsafe fn __getbuffer__(
+        slf: Bound<'_, Self>,
+        view: *mut ffi::Py_buffer,
+        flags: c_int,
+    ) -> PyResult<()> {
+        if view.is_null() {
+            return Err(PyBufferError::new_err("view is null"));
+        }
+        let mut this = slf.borrow_mut();
+        this.exports += 1;
+        let len = this.buf.initialized.len();
+        (*view).buf = this.buf.vals.as_mut_ptr() as *mut c_void;
+        drop(this);
+        (*view).len = len as isize;
+        (*view).readonly = 0;
+        (*view).itemsize = 1;
+        (*view).format = if (flags & ffi::PyBUF_FORMAT) == ffi::PyBUF_FORMAT {
+            CString::new("B").unwrap().into_raw()
+        } else {
+            ptr::null_mut()
+        };
+        (*view).ndim = 1;
+        (*view).shape = if (flags & ffi::PyBUF_ND) == ffi::PyBUF_ND {
+            &mut (*view).len
+        } else {
+            ptr::null_mut()
+        };
+        (*view).strides = if (flags & ffi::PyBUF_STRIDES) == ffi::PyBUF_STRIDES {
+            &mut (*view).itemsize
+        } else {
+            ptr::null_mut()
+        };
+        (*view).suboffsets = ptr::null_mut();
+        (*view).internal = ptr::null_mut();
+        (*view).obj = slf.into_any().into_ptr();
+        Ok(())
+    }
+
+    unsafe fn __releasebuffer__(&mut self, view: *mut ffi::Py_buffer) {
+        self.exports -= 1;
+        if !(*view).format.is_null() {
+            drop(CString::from_raw((*view).format));
+        }
+    }
+}
+
+impl PyTrackedBuffer {
+    // writes zeros to every uninitialized byte, so the whole buffer can be read
+    fn zero_uninitialized(&mut self) {
+        for range in self.buf.initialized.gaps() {
+            unsafe {
+                ptr::write_bytes(
+                    self.buf.vals.as_mut_ptr().add(range.start),
+                    0,
+                    range.end - range.start,
+                );
+            }
+        }
+    }
+
+    fn check_range(&self, start: usize, end: usize) -> PyResult<()> {
+        if start > end || end > self.buf.initialized.len() {
+            return Err(PyIndexError::new_err("range out of bounds"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+//! Random selection among the initialized values of a `SafeUninitializedVec`.
+//!
+//! Only available with the `rand` feature.
+
+use std::sync::atomic::Ordering;
+
+use rand::seq::index;
+use rand::Rng;
+
+use super::SafeUninitializedVec;
+use alloc::RawAlloc;
+
+impl<T, A: RawAlloc> SafeUninitializedVec<T, A> {
+    /// Picks one of the initialized values uniformly at random.
+    ///
+    /// Returns `None` if no values are initialized.
+    pub fn choose_initialized<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<&T> {
+        let count = self.count_initialized();
+        if count == 0 {
+            return None;
+        }
+        Some(self.nth_initialized(rng.gen_range(0..count)))
+    }
+
+    /// Picks `k` distinct initialized values uniformly at random.
+    ///
+    /// If fewer than `k` values are initialized, all of them are returned.
+    /// The order of the returned values is random.
+    pub fn sample_initialized<R: Rng + ?Sized>(&self, rng: &mut R, k: usize) -> Vec<&T> {
+        let count = self.count_initialized();
+        index::sample(rng, count, k.min(count))
+            .into_iter()
+            .map(|n| self.nth_initialized(n))
+            .collect()
+    }
+
+    // the `n`th initialized value, counting the untracked tail after the masked slots
+    fn nth_initialized(&self, n: usize) -> &T {
+        let tracked = self.initialized_count.load(Ordering::Relaxed);
+        let i = if n < tracked {
+            self.initialized.select(n).unwrap()
+        } else {
+            self.initialized.len() + (n - tracked)
+        };
+        unsafe { self.vals[i].assume_init_ref() }
+    }
+}
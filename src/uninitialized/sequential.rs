@@ -0,0 +1,164 @@
+//! Filling a vector front to back, tracking only how far it has been written.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::mem::{ManuallyDrop, MaybeUninit};
+use std::ptr;
+
+use super::{
+    assume_init_slice, assume_init_slice_mut, scrub, IndexError, SafeUninitializedVec, Slots,
+};
+use alloc::{Global, RawAlloc};
+use mask::InitMask;
+
+/// A vector that is written in order from the front, with a watermark instead of a mask.
+///
+/// The slots before the watermark hold values and the rest are uninitialized, so nothing
+/// has to be zeroed or tracked slot by slot. Converting to a `SafeUninitializedVec` gives
+/// random access, and a vector whose initialized slots form a prefix converts back.
+pub struct SequentialVec<T, A: RawAlloc = Global> {
+    // the first `written` slots hold values
+    vals: Slots<T, A>,
+    written: usize,
+}
+
+impl<T, A: RawAlloc> Drop for SequentialVec<T, A> {
+    fn drop(&mut self) {
+        let written = self.written;
+        // written is reset first so that the values are never dropped twice, even if a
+        // destructor panics
+        self.written = 0;
+        unsafe {
+            let vals = self.vals.as_mut_ptr() as *mut T;
+            ptr::drop_in_place(ptr::slice_from_raw_parts_mut(vals, written));
+            scrub(vals, self.vals.capacity());
+        }
+    }
+}
+
+impl<T> SequentialVec<T> {
+    /// Creates a vector of `len` uninitialized slots.
+    pub fn new(len: usize) -> SequentialVec<T> {
+        SequentialVec::new_in(len, Global)
+    }
+}
+
+impl<T, A: RawAlloc> SequentialVec<T, A> {
+    /// Creates a vector of `len` uninitialized slots, whose storage comes from `alloc`.
+    pub fn new_in(len: usize, alloc: A) -> SequentialVec<T, A> {
+        let mut vals = Slots::with_capacity_in(len, alloc);
+        unsafe {
+            // the slots do not need to be initialized
+            vals.set_len(len);
+        }
+        SequentialVec { vals, written: 0 }
+    }
+
+    /// The number of slots, written or not.
+    pub fn len(&self) -> usize {
+        self.vals.len()
+    }
+
+    /// Returns true if the vector has no slots.
+    pub fn is_empty(&self) -> bool {
+        self.vals.is_empty()
+    }
+
+    /// The number of slots that have been written, which is where the next write goes.
+    pub fn written(&self) -> usize {
+        self.written
+    }
+
+    /// Returns true if every slot has been written.
+    pub fn is_full(&self) -> bool {
+        self.written == self.vals.len()
+    }
+
+    /// Writes a value to the next slot. If every slot has been written, the value is given
+    /// back in the error.
+    pub fn push_init(&mut self, val: T) -> Result<(), IndexError<T>> {
+        if self.is_full() {
+            return Err(IndexError {
+                index: self.written,
+                len: self.vals.len(),
+                val,
+            });
+        }
+        self.vals[self.written].write(val);
+        self.written += 1;
+        Ok(())
+    }
+
+    /// The values that have been written.
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { assume_init_slice(&self.vals[..self.written]) }
+    }
+
+    /// The values that have been written, mutably.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        let written = self.written;
+        unsafe { assume_init_slice_mut(&mut self.vals[..written]) }
+    }
+
+    /// The slots that have not been written yet, so that they can be filled directly, for
+    /// example by a reader. Call `advance` afterwards to move the watermark past them.
+    pub fn remaining_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        let written = self.written;
+        &mut self.vals[written..]
+    }
+
+    /// Moves the watermark forward by `n` slots, marking them as written.
+    ///
+    /// Panics if fewer than `n` slots remain.
+    ///
+    /// # Safety
+    /// The first `n` slots of `remaining_mut` must have been initialized.
+    pub unsafe fn advance(&mut self, n: usize) {
+        assert!(
+            n <= self.vals.len() - self.written,
+            "advanced past the end of the vector"
+        );
+        self.written += n;
+    }
+
+    /// Converts the vector into a `SafeUninitializedVec` with the same values, so that
+    /// its slots can be written in any order. The allocation is reused.
+    pub fn into_tracked(self) -> SafeUninitializedVec<T, A> {
+        let vec = ManuallyDrop::new(self);
+        let mut initialized = InitMask::new(vec.vals.len());
+        initialized.set_range(0..vec.written, true);
+        // the storage is moved out once, and nothing else in the vector needs dropping
+        let vals = unsafe { ptr::read(&vec.vals) };
+        SafeUninitializedVec::from_parts(vals, initialized)
+    }
+}
+
+impl<T, A: RawAlloc> From<SequentialVec<T, A>> for SafeUninitializedVec<T, A> {
+    fn from(vec: SequentialVec<T, A>) -> SafeUninitializedVec<T, A> {
+        vec.into_tracked()
+    }
+}
+
+impl<T, A: RawAlloc> TryFrom<SafeUninitializedVec<T, A>> for SequentialVec<T, A> {
+    type Error = SafeUninitializedVec<T, A>;
+
+    /// Converts a vector whose initialized slots all come before its uninitialized ones.
+    /// Any other vector is given back unchanged.
+    fn try_from(vec: SafeUninitializedVec<T, A>) -> Result<SequentialVec<T, A>, Self::Error> {
+        let written = vec.first_uninitialized().unwrap_or(vec.len());
+        if vec.count_initialized() != written {
+            return Err(vec);
+        }
+        let (vals, _) = vec.into_parts();
+        Ok(SequentialVec { vals, written })
+    }
+}
+
+impl<T: fmt::Debug, A: RawAlloc> fmt::Debug for SequentialVec<T, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SequentialVec")
+            .field("len", &self.vals.len())
+            .field("written", &self.as_slice())
+            .finish()
+    }
+}
@@ -0,0 +1,26 @@
+//! Serde support for tracked vectors.
+//!
+//! Only available with the `serde` feature. A vector is serialized as a sequence with one
+//! option per slot, where uninitialized slots are `None`, so it can be checkpointed in any
+//! format and restored with the same slots initialized.
+
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::SafeUninitializedVec;
+
+impl<T: Serialize> Serialize for SafeUninitializedVec<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.vals.len()))?;
+        for i in 0..self.vals.len() {
+            seq.serialize_element(&self.get_value(i))?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for SafeUninitializedVec<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Option<T>>::deserialize(deserializer).map(SafeUninitializedVec::from_options)
+    }
+}
@@ -0,0 +1,154 @@
+//! Tracked storage borrowed from the caller.
+//!
+//! A `TrackedSlice` works like a `SafeUninitializedVec`, except that both the values and
+//! the initialization bits live in memory provided by the caller. This means it can be
+//! used without a heap, for example on a static buffer in firmware.
+
+use std::mem::{self, MaybeUninit};
+use std::ptr;
+use std::slice;
+use std::sync::atomic::AtomicU64;
+
+use super::scrub;
+use mask::{word_count, BitsMut};
+
+/// A tracked view of caller provided storage.
+///
+/// Values that are still initialized when the `TrackedSlice` is dropped are dropped with it.
+pub struct TrackedSlice<'a, T: 'a> {
+    vals: &'a mut [MaybeUninit<T>],
+    // one bit per slot, packed into the words the caller provided
+    initialized: BitsMut<'a>,
+}
+
+impl<'a, T> Drop for TrackedSlice<'a, T> {
+    fn drop(&mut self) {
+        let mut from = 0;
+        while let Some(i) = self.initialized.next_one(from) {
+            self.initialized.set(i, false);
+            from = i + 1;
+            unsafe {
+                ptr::drop_in_place(self.vals[i].as_mut_ptr());
+            }
+        }
+        unsafe {
+            scrub(self.vals.as_mut_ptr(), self.vals.len());
+        }
+    }
+}
+
+impl<'a, T> TrackedSlice<'a, T> {
+    /// The number of words of flags needed to track `len` slots.
+    pub const fn flag_words(len: usize) -> usize {
+        word_count(len)
+    }
+
+    /// Creates a tracked slice over `storage`, using the bits of `flags` to keep track of
+    /// which values are initialized. Every value starts out uninitialized.
+    ///
+    /// Panics if `flags` has fewer than `flag_words(storage.len())` words.
+    pub fn new(
+        storage: &'a mut [MaybeUninit<T>],
+        flags: &'a mut [AtomicU64],
+    ) -> TrackedSlice<'a, T> {
+        let words = TrackedSlice::<T>::flag_words(storage.len());
+        assert!(flags.len() >= words, "not enough flags for the storage");
+        let flags = &mut flags[..words];
+        for word in flags.iter_mut() {
+            *word.get_mut() = 0;
+        }
+        let len = storage.len();
+        TrackedSlice {
+            vals: storage,
+            initialized: BitsMut::new(flags, 0..len),
+        }
+    }
+
+    /// The number of slots in the slice.
+    pub fn len(&self) -> usize {
+        self.vals.len()
+    }
+
+    /// Returns true if the slice has no slots.
+    pub fn is_empty(&self) -> bool {
+        self.vals.is_empty()
+    }
+
+    /// Returns true if the value at `i` is initialized.
+    pub fn is_initialized(&self, i: usize) -> bool {
+        self.initialized.is_set(i)
+    }
+
+    /// The number of initialized values.
+    pub fn count_initialized(&self) -> usize {
+        self.initialized.count_ones()
+    }
+
+    /// Sets a value in the slice. This will initialize the value if it is
+    /// uninitialized, and drops an existing value if present.
+    pub fn set_value(&mut self, i: usize, val: T) {
+        if self.initialized.is_set(i) {
+            // replace the value, running the destructor on the existing one first
+            let slot = self.vals[i].as_mut_ptr();
+            unsafe {
+                ptr::drop_in_place(slot);
+                scrub(slot, 1);
+                ptr::write(slot, val);
+            }
+        } else {
+            self.vals[i] = MaybeUninit::new(val);
+            self.initialized.set(i, true);
+        }
+    }
+
+    /// Gets a reference to a value. Will return none if the value is not initialized.
+    pub fn get_value(&self, i: usize) -> Option<&T> {
+        if self.initialized.is_set(i) {
+            unsafe { Some(&*self.vals[i].as_ptr()) }
+        } else {
+            None
+        }
+    }
+
+    /// Gets a mutable reference to a value. Will return none if the value is not initialized.
+    pub fn get_value_mut(&mut self, i: usize) -> Option<&mut T> {
+        if self.initialized.is_set(i) {
+            unsafe { Some(&mut *self.vals[i].as_mut_ptr()) }
+        } else {
+            None
+        }
+    }
+
+    /// Moves a value out of the slice, marking its slot as uninitialized.
+    pub fn take(&mut self, i: usize) -> Option<T> {
+        if self.initialized.set(i, false) {
+            unsafe {
+                let slot = self.vals[i].as_mut_ptr();
+                let val = slot.read();
+                scrub(slot, 1);
+                Some(val)
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Swaps two slots.
+    pub fn swap(&mut self, x: usize, y: usize) {
+        self.vals.swap(x, y);
+        self.initialized.swap(x, y);
+    }
+
+    /// Ends the tracking and returns the values as an ordinary slice, if every value is
+    /// initialized. The values are then owned by the storage and will not be dropped.
+    pub fn into_initialized(self) -> Result<&'a mut [T], TrackedSlice<'a, T>> {
+        if self.initialized.next_zero(0).is_none() {
+            let len = self.vals.len();
+            let ptr = self.vals.as_mut_ptr() as *mut T;
+            mem::forget(self);
+            unsafe { Ok(slice::from_raw_parts_mut(ptr, len)) }
+        } else {
+            Err(self)
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! The storage behind a vector, in memory from a `RawAlloc`.
+
+use std::alloc::{handle_alloc_error, Layout};
+use std::cmp;
+use std::marker::PhantomData;
+use std::mem::{self, ManuallyDrop, MaybeUninit};
+use std::ops::{Deref, DerefMut};
+use std::ptr::{self, NonNull};
+use std::slice;
+
+use alloc::{Global, RawAlloc};
+
+// a growable buffer of slots, like a `Vec<MaybeUninit<T>>` that gets its memory from `A`
+// the slots are never dropped, so it is up to the owner to drop the values in them
+pub(super) struct Slots<T, A: RawAlloc = Global> {
+    ptr: NonNull<MaybeUninit<T>>,
+    len: usize,
+    cap: usize,
+    alloc: A,
+    _marker: PhantomData<MaybeUninit<T>>,
+}
+
+// the slots are owned, so they can be sent and shared like the fields of a `Vec`
+unsafe impl<T: Send, A: RawAlloc + Send> Send for Slots<T, A> {}
+unsafe impl<T: Sync, A: RawAlloc + Sync> Sync for Slots<T, A> {}
+
+impl<T, A: RawAlloc> Drop for Slots<T, A> {
+    fn drop(&mut self) {
+        self.reallocate(0);
+    }
+}
+
+impl<T, A: RawAlloc> Slots<T, A> {
+    pub(super) fn new_in(alloc: A) -> Slots<T, A> {
+        Slots {
+            ptr: NonNull::dangling(),
+            len: 0,
+            // zero-sized slots never need any memory
+            cap: if mem::size_of::<T>() == 0 {
+                usize::MAX
+            } else {
+                0
+            },
+            alloc,
+            _marker: PhantomData,
+        }
+    }
+
+    pub(super) fn with_capacity_in(capacity: usize, alloc: A) -> Slots<T, A> {
+        let mut slots = Slots::new_in(alloc);
+        slots.reserve_exact(capacity);
+        slots
+    }
+
+    pub(super) fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    pub(super) fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    // these cover the whole allocation, unlike the pointers of the slice
+    pub(super) fn as_ptr(&self) -> *const MaybeUninit<T> {
+        self.ptr.as_ptr()
+    }
+
+    pub(super) fn as_mut_ptr(&mut self) -> *mut MaybeUninit<T> {
+        self.ptr.as_ptr()
+    }
+
+    pub(super) unsafe fn set_len(&mut self, len: usize) {
+        debug_assert!(len <= self.cap);
+        self.len = len;
+    }
+
+    pub(super) fn push(&mut self, slot: MaybeUninit<T>) {
+        self.reserve(1);
+        unsafe {
+            ptr::write(self.as_mut_ptr().add(self.len), slot);
+        }
+        self.len += 1;
+    }
+
+    pub(super) fn pop(&mut self) -> Option<MaybeUninit<T>> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        unsafe { Some(ptr::read(self.as_ptr().add(self.len))) }
+    }
+
+    pub(super) fn truncate(&mut self, len: usize) {
+        self.len = cmp::min(self.len, len);
+    }
+
+    pub(super) fn resize_with<F: FnMut() -> MaybeUninit<T>>(&mut self, len: usize, mut f: F) {
+        if len > self.len {
+            self.reserve_exact(len - self.len);
+            while self.len < len {
+                self.push(f());
+            }
+        } else {
+            self.truncate(len);
+        }
+    }
+
+    pub(super) fn reserve(&mut self, additional: usize) {
+        if self.cap - self.len < additional {
+            let needed = self.required(additional);
+            self.reallocate(cmp::max(needed, cmp::max(self.cap * 2, 4)));
+        }
+    }
+
+    pub(super) fn reserve_exact(&mut self, additional: usize) {
+        if self.cap - self.len < additional {
+            let needed = self.required(additional);
+            self.reallocate(needed);
+        }
+    }
+
+    pub(super) fn shrink_to_fit(&mut self) {
+        if self.cap > self.len {
+            let len = self.len;
+            self.reallocate(len);
+        }
+    }
+
+    // the capacity needed to hold `additional` more slots
+    fn required(&self, additional: usize) -> usize {
+        self.len.checked_add(additional).expect("capacity overflow")
+    }
+
+    // moves the slots to an allocation for `cap` slots, which is at least `len`
+    fn reallocate(&mut self, cap: usize) {
+        if mem::size_of::<T>() == 0 || cap == self.cap {
+            return;
+        }
+        let layout = |cap| Layout::array::<T>(cap).expect("capacity overflow");
+        let new = layout(cap);
+        let block = unsafe {
+            match (self.cap, cap) {
+                (0, _) => self.alloc.allocate(new),
+                (old, 0) => {
+                    self.alloc.deallocate(self.ptr.cast(), layout(old));
+                    Some(NonNull::dangling())
+                }
+                (old, _) if cap > old => self.alloc.grow(self.ptr.cast(), layout(old), new),
+                (old, _) => self.alloc.shrink(self.ptr.cast(), layout(old), new),
+            }
+        };
+        self.ptr = block.unwrap_or_else(|| handle_alloc_error(new)).cast();
+        self.cap = cap;
+    }
+}
+
+impl<T, A: RawAlloc + Default> Default for Slots<T, A> {
+    fn default() -> Slots<T, A> {
+        Slots::new_in(A::default())
+    }
+}
+
+impl<T, A: RawAlloc> Deref for Slots<T, A> {
+    type Target = [MaybeUninit<T>];
+
+    fn deref(&self) -> &[MaybeUninit<T>] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T, A: RawAlloc> DerefMut for Slots<T, A> {
+    fn deref_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+// a `Vec` allocates from the global allocator with the same layouts, so its memory can
+// be taken over and handed back without copying
+impl<T> From<Vec<MaybeUninit<T>>> for Slots<T> {
+    fn from(vec: Vec<MaybeUninit<T>>) -> Slots<T> {
+        let mut vec = ManuallyDrop::new(vec);
+        Slots {
+            ptr: NonNull::new(vec.as_mut_ptr()).expect("vec pointers are never null"),
+            len: vec.len(),
+            cap: vec.capacity(),
+            alloc: Global,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> From<Slots<T>> for Vec<MaybeUninit<T>> {
+    fn from(slots: Slots<T>) -> Vec<MaybeUninit<T>> {
+        let slots = ManuallyDrop::new(slots);
+        unsafe { Vec::from_raw_parts(slots.ptr.as_ptr(), slots.len, slots.cap) }
+    }
+}
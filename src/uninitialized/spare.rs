@@ -0,0 +1,129 @@
+//! Writing into the spare capacity of a vector before it becomes part of the vector.
+
+use std::mem;
+use std::ptr;
+
+use super::SafeUninitializedVec;
+use alloc::{Global, RawAlloc};
+use error::{Error, Result};
+use mask::InitMask;
+
+/// A tracked region over the spare capacity of a `SafeUninitializedVec`, past the end of
+/// its slots. Indices are relative to the end of the vector.
+///
+/// Values written here only become part of the vector once they are committed. Values that
+/// are not committed are dropped along with the guard.
+pub struct SpareCapacity<'a, T: 'a, A: 'a + RawAlloc = Global> {
+    vec: &'a mut SafeUninitializedVec<T, A>,
+    // which slots past the end of the vector have been written
+    written: InitMask,
+}
+
+impl<'a, T, A: RawAlloc> Drop for SpareCapacity<'a, T, A> {
+    fn drop(&mut self) {
+        let base = self.slot(0);
+        for i in self.written.ones() {
+            unsafe {
+                ptr::drop_in_place(base.add(i));
+            }
+        }
+    }
+}
+
+impl<T, A: RawAlloc> SafeUninitializedVec<T, A> {
+    /// Borrows the spare capacity of the vector as a tracked region, so that values can be
+    /// written past the end of the vector and then committed to extend it.
+    ///
+    /// Zero-sized values have no storage to write into, so for them the region is empty.
+    pub fn spare_capacity_tracked(&mut self) -> SpareCapacity<'_, T, A> {
+        self.track_tail();
+        let spare = if mem::size_of::<T>() == 0 {
+            0
+        } else {
+            self.vals.capacity() - self.vals.len()
+        };
+        SpareCapacity {
+            vec: self,
+            written: InitMask::new(spare),
+        }
+    }
+}
+
+impl<'a, T, A: RawAlloc> SpareCapacity<'a, T, A> {
+    // the slot `i` places past the end of the vector
+    fn slot(&mut self, i: usize) -> *mut T {
+        unsafe { self.vec.vals.as_mut_ptr().add(self.vec.vals.len() + i) as *mut T }
+    }
+
+    /// The number of slots in the region.
+    pub fn len(&self) -> usize {
+        self.written.len()
+    }
+
+    /// Returns true if the region has no slots.
+    pub fn is_empty(&self) -> bool {
+        self.written.is_empty()
+    }
+
+    /// The number of slots that have been written.
+    pub fn count_initialized(&self) -> usize {
+        self.written.count_ones()
+    }
+
+    /// Sets a value in the region, dropping the value that was written there before.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn set_value(&mut self, i: usize, val: T) {
+        // setting the bit checks that the slot is in bounds
+        let was_written = self.written.set(i, true);
+        let slot = self.slot(i);
+        if was_written {
+            unsafe {
+                *slot = val;
+            }
+        } else {
+            unsafe {
+                ptr::write(slot, val);
+            }
+        }
+    }
+
+    /// Gets a reference to a value in the region. Will return none if it was not written.
+    pub fn get_value(&self, i: usize) -> Option<&T> {
+        if self.written.is_set(i) {
+            unsafe {
+                Some((*self.vec.vals.as_ptr().add(self.vec.vals.len() + i)).assume_init_ref())
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Extends the vector by the first `n` slots of the region, which must all have been
+    /// written. The region then starts after them, so indices shift down by `n`.
+    pub fn commit(&mut self, n: usize) -> Result<()> {
+        if n > self.written.len() {
+            return Err(Error::OutOfBounds {
+                index: n,
+                len: self.written.len(),
+            });
+        }
+        if let Some(index) = self.written.next_zero(0).filter(|&i| i < n) {
+            return Err(Error::Uninitialized {
+                len: n,
+                index: Some(index),
+            });
+        }
+        self.written = self.written.iter().skip(n).collect();
+        let vec = &mut *self.vec;
+        let len = vec.vals.len();
+        unsafe {
+            // the first n slots of the spare capacity were written
+            vec.vals.set_len(len + n);
+        }
+        vec.initialized.resize(len + n, true);
+        vec.report_tracked(n);
+        vec.report_initialized(n);
+        Ok(())
+    }
+}
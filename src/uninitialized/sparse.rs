@@ -0,0 +1,187 @@
+//! A compact binary encoding that only stores the initialized runs of a vector.
+//!
+//! The encoding starts with the length of the vector and the number of runs. Each run is
+//! stored as its offset from the end of the previous run, its length, and then its values.
+//! All counts are LEB128 varints, and values are stored little endian.
+
+use std::error;
+use std::fmt;
+
+use super::{assume_init_slice, SafeUninitializedVec, Slots};
+use mask::InitMask;
+
+/// A value with a fixed size little endian encoding, used by the sparse encoding.
+pub trait SparseElement: Sized {
+    /// The number of bytes in the encoding of a value.
+    const SIZE: usize;
+
+    /// Appends the encoding of the value to `out`.
+    fn write_le(&self, out: &mut Vec<u8>);
+
+    /// Decodes a value from exactly `SIZE` bytes.
+    fn read_le(bytes: &[u8]) -> Self;
+}
+
+macro_rules! impl_sparse_element {
+    ($($t:ty),*) => {
+        $(
+            impl SparseElement for $t {
+                const SIZE: usize = ::std::mem::size_of::<$t>();
+
+                fn write_le(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn read_le(bytes: &[u8]) -> $t {
+                    let mut buf = [0; ::std::mem::size_of::<$t>()];
+                    buf.copy_from_slice(bytes);
+                    <$t>::from_le_bytes(buf)
+                }
+            }
+        )*
+    };
+}
+
+impl_sparse_element!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+/// The error returned when decoding invalid sparse bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SparseFormatError {
+    /// The input ended in the middle of a value.
+    UnexpectedEnd,
+    /// A varint was too large.
+    Overflow,
+    /// A run extends past the end of the vector.
+    RunOutOfBounds,
+    /// There were bytes left after the last run.
+    TrailingBytes,
+    /// The stored length is too large to allocate.
+    TooLarge,
+}
+
+impl fmt::Display for SparseFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let msg = match *self {
+            SparseFormatError::UnexpectedEnd => "unexpected end of sparse bytes",
+            SparseFormatError::Overflow => "varint in sparse bytes is too large",
+            SparseFormatError::RunOutOfBounds => "run in sparse bytes is out of bounds",
+            SparseFormatError::TrailingBytes => "trailing data after sparse bytes",
+            SparseFormatError::TooLarge => "sparse bytes are too large to allocate",
+        };
+        write!(f, "{}", msg)
+    }
+}
+
+impl error::Error for SparseFormatError {}
+
+impl<T: SparseElement> SafeUninitializedVec<T> {
+    /// Encodes the initialized values of the vector along with their positions.
+    ///
+    /// Values past the tracked length of the vector are stored as initialized.
+    pub fn to_sparse_bytes(&self) -> Vec<u8> {
+        let tracked = self.initialized.len();
+        let len = self.vals.len();
+        let mut runs: Vec<_> = self.initialized.runs().collect();
+        if len > tracked {
+            match runs.last_mut() {
+                Some(last) if last.end == tracked => last.end = len,
+                _ => runs.push(tracked..len),
+            }
+        }
+        let mut out = Vec::new();
+        write_varint(&mut out, len as u64);
+        write_varint(&mut out, runs.len() as u64);
+        let mut pos = 0;
+        for run in runs {
+            write_varint(&mut out, (run.start - pos) as u64);
+            write_varint(&mut out, (run.end - run.start) as u64);
+            for val in unsafe { assume_init_slice(&self.vals[run.clone()]) } {
+                val.write_le(&mut out);
+            }
+            pos = run.end;
+        }
+        out
+    }
+
+    /// Decodes a vector from the output of `to_sparse_bytes`.
+    ///
+    /// The vector is allocated with the length stored in the input. If that much memory
+    /// is not available, `SparseFormatError::TooLarge` is returned instead of aborting.
+    pub fn from_sparse_bytes(
+        mut bytes: &[u8],
+    ) -> Result<SafeUninitializedVec<T>, SparseFormatError> {
+        let len = read_varint(&mut bytes)?;
+        let runs = read_varint(&mut bytes)?;
+        // check the header against the input size before allocating anything
+        if runs > bytes.len() {
+            return Err(SparseFormatError::UnexpectedEnd);
+        }
+        let mut vals = Vec::new();
+        vals.try_reserve_exact(len)
+            .map_err(|_| SparseFormatError::TooLarge)?;
+        unsafe {
+            // the slots do not need to be initialized
+            vals.set_len(len);
+        }
+        let mut vec = SafeUninitializedVec::from_parts(Slots::from(vals), InitMask::new(len));
+        let mut pos = 0usize;
+        for _ in 0..runs {
+            let start = pos
+                .checked_add(read_varint(&mut bytes)?)
+                .ok_or(SparseFormatError::RunOutOfBounds)?;
+            let count = read_varint(&mut bytes)?;
+            let end = start
+                .checked_add(count)
+                .filter(|&end| end <= len)
+                .ok_or(SparseFormatError::RunOutOfBounds)?;
+            if count.saturating_mul(T::SIZE) > bytes.len() {
+                return Err(SparseFormatError::UnexpectedEnd);
+            }
+            // the values are written straight into the vector, and the run is marked once
+            // it is complete
+            for slot in &mut vec.vals[start..end] {
+                let (val, rest) = bytes.split_at(T::SIZE);
+                slot.write(T::read_le(val));
+                bytes = rest;
+            }
+            let count = vec.initialized.set_range(start..end, true);
+            vec.report_initialized(count);
+            pos = end;
+        }
+        if !bytes.is_empty() {
+            return Err(SparseFormatError::TrailingBytes);
+        }
+        Ok(vec)
+    }
+}
+
+fn write_varint(out: &mut Vec<u8>, mut val: u64) {
+    while val >= 0x80 {
+        out.push((val as u8) | 0x80);
+        val >>= 7;
+    }
+    out.push(val as u8);
+}
+
+fn read_varint(bytes: &mut &[u8]) -> Result<usize, SparseFormatError> {
+    let mut val: u64 = 0;
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = bytes
+            .split_first()
+            .ok_or(SparseFormatError::UnexpectedEnd)?;
+        *bytes = rest;
+        if shift >= 64 || (shift == 63 && byte > 1) {
+            return Err(SparseFormatError::Overflow);
+        }
+        val |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    if val > usize::MAX as u64 {
+        return Err(SparseFormatError::Overflow);
+    }
+    Ok(val as usize)
+}
@@ -0,0 +1,320 @@
+//! Views of part of a tracked vector.
+//!
+//! A mutable view borrows a range of the values and their flags from a
+//! `SafeUninitializedVec`, so that several disjoint ranges can be worked on at the same
+//! time. Unlike a `TrackedSlice`, a view does not drop its values, since they still belong
+//! to the vector.
+
+use std::mem::MaybeUninit;
+use std::ops::Range;
+use std::ptr;
+use std::slice;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use super::{scrub, SafeUninitializedVec};
+use alloc::{Global, RawAlloc};
+use error::{Error, Result};
+use instrument;
+use mask::BitsMut;
+#[cfg(feature = "registry")]
+use registry::Registration;
+
+/// A view of a range of a `SafeUninitializedVec`. Indices are relative to the start of
+/// the range.
+pub struct TrackedView<'a, T: 'a, A: 'a + RawAlloc = Global> {
+    vec: &'a SafeUninitializedVec<T, A>,
+    range: Range<usize>,
+}
+
+impl<'a, T, A: RawAlloc> Clone for TrackedView<'a, T, A> {
+    fn clone(&self) -> TrackedView<'a, T, A> {
+        TrackedView {
+            vec: self.vec,
+            range: self.range.clone(),
+        }
+    }
+}
+
+impl<'a, T, A: RawAlloc> TrackedView<'a, T, A> {
+    /// The number of slots in the view.
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    /// Returns true if the view has no slots.
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    /// Returns true if the value at `i` is initialized.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn is_initialized(&self, i: usize) -> bool {
+        self.get_value(i).is_some()
+    }
+
+    /// The number of initialized values.
+    pub fn count_initialized(&self) -> usize {
+        // the slots past the end of the mask are all initialized
+        let tracked = self.vec.initialized.len();
+        let masked = self.range.start.min(tracked)..self.range.end.min(tracked);
+        self.vec.initialized.count_ones_in(masked.clone()) + self.range.len() - masked.len()
+    }
+
+    /// Gets a reference to a value. Will return none if the value is not initialized.
+    ///
+    /// Panics if `i` is out of bounds.
+    pub fn get_value(&self, i: usize) -> Option<&'a T> {
+        assert!(
+            i < self.len(),
+            "index {} out of bounds for length {}",
+            i,
+            self.len()
+        );
+        self.vec.get_value(self.range.start + i)
+    }
+}
+
+/// A mutable view of a range of a `SafeUninitializedVec`. Indices are relative to the
+/// start of the range.
+pub struct TrackedViewMut<'a, T: 'a> {
+    vals: &'a mut [MaybeUninit<T>],
+    initialized: BitsMut<'a>,
+    // the vector's count of initialized values, shared by every view
+    initialized_count: &'a AtomicUsize,
+    #[cfg(feature = "registry")]
+    registration: &'a Registration,
+}
+
+impl<'a, T> TrackedViewMut<'a, T> {
+    /// The number of slots in the view.
+    pub fn len(&self) -> usize {
+        self.vals.len()
+    }
+
+    /// Returns true if the view has no slots.
+    pub fn is_empty(&self) -> bool {
+        self.vals.is_empty()
+    }
+
+    /// Returns true if the value at `i` is initialized.
+    pub fn is_initialized(&self, i: usize) -> bool {
+        self.initialized.is_set(i)
+    }
+
+    /// The number of initialized values.
+    pub fn count_initialized(&self) -> usize {
+        self.initialized.count_ones()
+    }
+
+    /// Sets a value in the view. This will initialize the value if it is
+    /// uninitialized, and drops an existing value if present.
+    pub fn set_value(&mut self, i: usize, val: T) {
+        if self.initialized.is_set(i) {
+            unsafe {
+                let slot = self.vals[i].as_mut_ptr();
+                ptr::drop_in_place(slot);
+                scrub(slot, 1);
+                ptr::write(slot, val);
+            }
+        } else {
+            self.vals[i] = MaybeUninit::new(val);
+            self.initialized.set(i, true);
+            self.initialized_count.fetch_add(1, Ordering::Relaxed);
+            instrument::slots_initialized(1);
+            #[cfg(feature = "registry")]
+            self.registration.initialized(1);
+        }
+    }
+
+    /// Gets a reference to a value. Will return none if the value is not initialized.
+    pub fn get_value(&self, i: usize) -> Option<&T> {
+        if self.initialized.is_set(i) {
+            unsafe { Some(&*self.vals[i].as_ptr()) }
+        } else {
+            None
+        }
+    }
+
+    /// Gets a mutable reference to a value. Will return none if the value is not initialized.
+    pub fn get_value_mut(&mut self, i: usize) -> Option<&mut T> {
+        if self.initialized.is_set(i) {
+            unsafe { Some(&mut *self.vals[i].as_mut_ptr()) }
+        } else {
+            None
+        }
+    }
+
+    /// Moves a value out of the view, marking its slot as uninitialized.
+    pub fn take(&mut self, i: usize) -> Option<T> {
+        if self.initialized.is_set(i) {
+            self.initialized.set(i, false);
+            self.initialized_count.fetch_sub(1, Ordering::Relaxed);
+            instrument::slots_deinitialized(1);
+            #[cfg(feature = "registry")]
+            self.registration.deinitialized(1);
+            unsafe {
+                let slot = self.vals[i].as_mut_ptr();
+                let val = ptr::read(slot);
+                scrub(slot, 1);
+                Some(val)
+            }
+        } else {
+            None
+        }
+    }
+
+    /// Swaps two slots.
+    pub fn swap(&mut self, x: usize, y: usize) {
+        self.vals.swap(x, y);
+        self.initialized.swap(x, y);
+    }
+
+    /// Initializes every uninitialized slot in the view with the result of calling `f`
+    /// with its index. Values that are already initialized are left as they are.
+    ///
+    /// The new values are counted once at the end instead of one at a time, so this is
+    /// cheaper than calling `set_value` when several views are filled at once.
+    pub fn init_missing_with<F: FnMut(usize) -> T>(&mut self, mut f: F) {
+        let mut batch = Batch {
+            initialized_count: self.initialized_count,
+            #[cfg(feature = "registry")]
+            registration: self.registration,
+            count: 0,
+        };
+        let mut next = self.initialized.next_zero(0);
+        while let Some(i) = next {
+            self.vals[i] = MaybeUninit::new(f(i));
+            self.initialized.set(i, true);
+            batch.count += 1;
+            next = self.initialized.next_zero(i + 1);
+        }
+    }
+
+    /// Splits the view into two views, of the slots before `mid` and the slots from `mid`
+    /// on.
+    ///
+    /// Panics if `mid` is greater than the length of the view.
+    pub fn split_at_mut(self, mid: usize) -> (TrackedViewMut<'a, T>, TrackedViewMut<'a, T>) {
+        let (left_vals, right_vals) = self.vals.split_at_mut(mid);
+        let (left_flags, right_flags) = self.initialized.split_at(mid);
+        (
+            TrackedViewMut {
+                vals: left_vals,
+                initialized: left_flags,
+                initialized_count: self.initialized_count,
+                #[cfg(feature = "registry")]
+                registration: self.registration,
+            },
+            TrackedViewMut {
+                vals: right_vals,
+                initialized: right_flags,
+                initialized_count: self.initialized_count,
+                #[cfg(feature = "registry")]
+                registration: self.registration,
+            },
+        )
+    }
+}
+
+// reports a number of newly initialized slots when dropped, even if a panic interrupts
+// the loop that initializes them
+struct Batch<'a> {
+    initialized_count: &'a AtomicUsize,
+    #[cfg(feature = "registry")]
+    registration: &'a Registration,
+    count: usize,
+}
+
+impl<'a> Drop for Batch<'a> {
+    fn drop(&mut self) {
+        self.initialized_count
+            .fetch_add(self.count, Ordering::Relaxed);
+        instrument::slots_initialized(self.count);
+        #[cfg(feature = "registry")]
+        self.registration.initialized(self.count);
+    }
+}
+
+impl<T, A: RawAlloc> SafeUninitializedVec<T, A> {
+    /// A view of the slots in `range`.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn view(&self, range: Range<usize>) -> TrackedView<'_, T, A> {
+        let len = self.vals.len();
+        assert!(
+            range.start <= range.end && range.end <= len,
+            "range {:?} out of bounds for length {}",
+            range,
+            len
+        );
+        TrackedView { vec: self, range }
+    }
+
+    /// A mutable view of the slots in `range`.
+    ///
+    /// Panics if the range is out of bounds.
+    pub fn view_mut(&mut self, range: Range<usize>) -> TrackedViewMut<'_, T> {
+        match self.get_disjoint_ranges_mut([range]) {
+            Ok([view]) => view,
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Splits the vector into two views, of the slots before `mid` and the slots from
+    /// `mid` on. The views can be used at the same time, for example from two threads.
+    ///
+    /// Panics if `mid` is greater than the length of the vector.
+    pub fn split_at_mut(&mut self, mid: usize) -> (TrackedViewMut<'_, T>, TrackedViewMut<'_, T>) {
+        let len = self.vals.len();
+        assert!(mid <= len, "mid {} out of bounds for length {}", mid, len);
+        let [left, right] = self.get_disjoint_ranges_mut([0..mid, mid..len]).unwrap();
+        (left, right)
+    }
+
+    /// Borrows several ranges of the vector mutably at once.
+    ///
+    /// Fails if a range is out of bounds, or if two of the ranges overlap. Empty ranges
+    /// never overlap anything.
+    pub fn get_disjoint_ranges_mut<const N: usize>(
+        &mut self,
+        ranges: [Range<usize>; N],
+    ) -> Result<[TrackedViewMut<'_, T>; N]> {
+        self.track_tail();
+        let len = self.vals.len();
+        for r in &ranges {
+            if r.start > r.end || r.end > len {
+                return Err(Error::OutOfBounds {
+                    index: if r.start > r.end { r.start } else { r.end },
+                    len,
+                });
+            }
+        }
+        let mut sorted: Vec<&Range<usize>> = ranges.iter().filter(|r| !r.is_empty()).collect();
+        sorted.sort_by_key(|r| r.start);
+        for pair in sorted.windows(2) {
+            if pair[1].start < pair[0].end {
+                return Err(Error::Overlap {
+                    first: pair[0].clone(),
+                    second: pair[1].clone(),
+                });
+            }
+        }
+
+        let vals = self.vals.as_mut_ptr();
+        let flags = self.initialized.bits_mut();
+        let initialized_count = &self.initialized_count;
+        #[cfg(feature = "registry")]
+        let registration = &self.registration;
+        // the ranges are in bounds and disjoint, so the views never alias
+        Ok(ranges.map(|r| unsafe {
+            TrackedViewMut {
+                vals: slice::from_raw_parts_mut(vals.add(r.start), r.len()),
+                initialized: flags.range(r.clone()),
+                initialized_count,
+                #[cfg(feature = "registry")]
+                registration,
+            }
+        }))
+    }
+}